@@ -1,12 +1,21 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::Mutex;
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 
-use spidermonkey_embedding_splicer::wit::exports::local::spidermonkey_embedding_splicer::splicer::Feature;
-use spidermonkey_embedding_splicer::{splice, stub_wasi};
+use spidermonkey_embedding_splicer::wit::exports::local::spidermonkey_embedding_splicer::splicer::{
+    EmbeddedFile, EmbeddedFilesystem, Feature, RandomSeed, SpliceError, SpliceOptions, SpliceResult,
+    StringEncoding, StubOptions, StubReportEntry, UnsupportedExport,
+};
+use spidermonkey_embedding_splicer::{splice, stub_wasi, validate};
+use wasmtime_wasi::p2::pipe::MemoryInputPipe;
+use wasmtime_wasi::preview1::WasiP1Ctx;
+use wasmtime_wasi::{DirPerms, FilePerms, WasiCtxBuilder};
+use wizer::{StoreData, Wizer};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -38,6 +47,27 @@ enum Commands {
         /// World name to use
         #[arg(long)]
         world_name: Option<String>,
+
+        /// Seed of the random stub when random is not enabled (a u64 or content-hash)
+        #[arg(long)]
+        random_seed: Option<String>,
+
+        /// Epoch in seconds of the virtual clock when clocks are not enabled
+        #[arg(long, env = "SOURCE_DATE_EPOCH")]
+        clock_epoch: Option<u64>,
+
+        /// Capture stdio into a ring buffer of this many bytes when stdio is not enabled,
+        /// exported as drain-logs
+        #[arg(long)]
+        stdio_capture: Option<u32>,
+
+        /// Path to a JSON stub policy of interfaces or functions to stub actions
+        #[arg(long)]
+        stub_policy: Option<PathBuf>,
+
+        /// Print a report of the stubbed, kept and missing imports
+        #[arg(long)]
+        report: bool,
     },
 
     /// Splice bindings into a WebAssembly module
@@ -65,6 +95,121 @@ enum Commands {
         /// Enable debug mode
         #[arg(long)]
         debug: bool,
+
+        /// String encoding of the world functions (utf8, utf16 or compact-utf16)
+        #[arg(long, default_value = "utf8")]
+        string_encoding: String,
+
+        /// Static export names of the guest module, to check against the world
+        /// exports before splicing
+        #[arg(long, value_delimiter = ',')]
+        export_names: Option<Vec<String>>,
+
+        /// Path to a JSON list of the `[specifier, name]` pairs statically
+        /// imported by the guest module, as detected by componentize-js, to only
+        /// bind the world imports which are used
+        #[arg(long)]
+        used_imports: Option<PathBuf>,
+
+        /// Directory to embed as a read-only filesystem when the target world does
+        /// not import wasi:filesystem, served to the engine's wasi:filesystem
+        /// imports only
+        #[arg(long)]
+        embed_dir: Option<PathBuf>,
+
+        /// Path of the preopened directory containing the embedded files
+        #[arg(long, default_value = "/", requires = "embed_dir")]
+        embed_preopen: String,
+    },
+
+    /// Build a component from a JS source, splicing the bindings, initializing
+    /// the engine with wizer and stubbing the WASI imports
+    Componentize {
+        /// Input engine WebAssembly file path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// JS source file path
+        #[arg(short, long)]
+        js_source: PathBuf,
+
+        /// Output component file path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Path to the WASI preview1 reactor adapter module
+        #[arg(long)]
+        adapter: PathBuf,
+
+        /// Features to enable (multiple allowed)
+        #[arg(short, long)]
+        features: Vec<String>,
+
+        /// Path to WIT file or directory
+        #[arg(long)]
+        wit_path: Option<PathBuf>,
+
+        /// World name to use
+        #[arg(long)]
+        world_name: Option<String>,
+
+        /// String encoding of the world functions (utf8, utf16 or compact-utf16)
+        #[arg(long, default_value = "utf8")]
+        string_encoding: String,
+
+        /// Seed of the random stub when random is not enabled (a u64 or content-hash)
+        #[arg(long)]
+        random_seed: Option<String>,
+
+        /// Epoch in seconds of the virtual clock when clocks are not enabled
+        #[arg(long, env = "SOURCE_DATE_EPOCH")]
+        clock_epoch: Option<u64>,
+
+        /// Capture stdio into a ring buffer of this many bytes when stdio is not enabled,
+        /// exported as drain-logs
+        #[arg(long)]
+        stdio_capture: Option<u32>,
+
+        /// Path to a JSON stub policy of interfaces or functions to stub actions
+        #[arg(long)]
+        stub_policy: Option<PathBuf>,
+
+        /// Directory to embed as a read-only filesystem when the target world does
+        /// not import wasi:filesystem, served to the engine's wasi:filesystem
+        /// imports, and preopened for the JS source while initializing
+        #[arg(long)]
+        embed_dir: Option<PathBuf>,
+
+        /// Path of the preopened directory containing the embedded files
+        #[arg(long, default_value = "/", requires = "embed_dir")]
+        embed_preopen: String,
+
+        /// Path to a JSON list of the `[specifier, name]` pairs statically
+        /// imported by the guest module, as detected by componentize-js, to only
+        /// bind the world imports which are used
+        #[arg(long)]
+        used_imports: Option<PathBuf>,
+
+        /// Print a report of the stubbed, kept and missing imports
+        #[arg(long)]
+        report: bool,
+
+        /// Pass the environment variables of the splicer through to the
+        /// initializer, which otherwise only sees the splice environment
+        #[arg(long)]
+        inherit_env: bool,
+
+        /// Keep the working directory and enable the engine's initialization
+        /// logging
+        #[arg(long)]
+        debug: bool,
+    },
+
+    /// Validate that an engine build provides the ABI required for splicing
+    ValidateEngine {
+        /// Input engine WebAssembly file path
+        #[arg(short, long)]
+        input: PathBuf,
     },
 }
 
@@ -78,6 +223,11 @@ fn main() -> Result<()> {
             features,
             wit_path,
             world_name,
+            random_seed,
+            clock_epoch,
+            stdio_capture,
+            stub_policy,
+            report,
         } => {
             let wasm = fs::read(&input)
                 .with_context(|| format!("Failed to read input file: {}", input.display()))?;
@@ -88,10 +238,37 @@ fn main() -> Result<()> {
                 .map(|v| Feature::from_str(v))
                 .collect::<Result<Vec<_>>>()?;
 
-            let result = stub_wasi::stub_wasi(wasm, features, None, wit_path_str, world_name)
-                .map_err(|e| anyhow::anyhow!(e))?;
+            let random_seed = random_seed
+                .map(|seed| RandomSeed::from_str(&seed))
+                .transpose()?;
+            let stub_policy = stub_policy
+                .map(|path| {
+                    fs::read_to_string(&path).with_context(|| {
+                        format!("Failed to read stub policy file: {}", path.display())
+                    })
+                })
+                .transpose()?;
+
+            let result = stub_wasi::stub_wasi(
+                wasm,
+                features,
+                None,
+                wit_path_str,
+                world_name,
+                StubOptions {
+                    random_seed,
+                    clock_epoch,
+                    stdio_capture,
+                    stub_policy,
+                },
+            )
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+            if report {
+                print_stub_report(&result.report);
+            }
 
-            fs::write(&output, result)
+            fs::write(&output, result.wasm)
                 .with_context(|| format!("Failed to write output file: {}", output.display()))?;
 
             println!(
@@ -107,6 +284,11 @@ fn main() -> Result<()> {
             wit_path,
             world_name,
             debug,
+            string_encoding,
+            export_names,
+            used_imports,
+            embed_dir,
+            embed_preopen,
         } => {
             if !out_dir.exists() {
                 fs::create_dir_all(&out_dir).with_context(|| {
@@ -123,9 +305,30 @@ fn main() -> Result<()> {
                 .map(|v| Feature::from_str(v))
                 .collect::<Result<Vec<_>>>()?;
 
-            let result =
-                splice::splice_bindings(engine, features, None, wit_path_str, world_name, debug)
-                    .map_err(|e| anyhow::anyhow!(e))?;
+            let string_encoding = StringEncoding::from_str(&string_encoding)?;
+            let used_imports = used_imports
+                .map(|path| read_used_imports(&path))
+                .transpose()?;
+            let embedded_filesystem = embed_dir
+                .map(|dir| read_embedded_filesystem(&dir, embed_preopen))
+                .transpose()?;
+
+            let result = splice::splice_bindings(
+                engine,
+                features.clone(),
+                None,
+                wit_path_str,
+                world_name,
+                debug,
+                SpliceOptions {
+                    string_encoding: Some(string_encoding),
+                    export_names,
+                    used_imports,
+                    embedded_filesystem,
+                },
+            )
+            .map_err(splice_error)?;
+            let manifest = serde_json::to_string_pretty(&splice_manifest(&result, &features))?;
 
             fs::write(out_dir.join("component.wasm"), result.wasm).with_context(|| {
                 format!(
@@ -139,8 +342,501 @@ fn main() -> Result<()> {
                     out_dir.join("initializer.js").display()
                 )
             })?;
+            fs::write(out_dir.join("guest-types.d.ts"), result.guest_types).with_context(|| {
+                format!(
+                    "Failed to write output file: {}",
+                    out_dir.join("guest-types.d.ts").display()
+                )
+            })?;
+            fs::write(out_dir.join("manifest.json"), manifest).with_context(|| {
+                format!(
+                    "Failed to write output file: {}",
+                    out_dir.join("manifest.json").display()
+                )
+            })?;
+        }
+
+        Commands::Componentize {
+            input,
+            js_source,
+            output,
+            adapter,
+            features,
+            wit_path,
+            world_name,
+            string_encoding,
+            random_seed,
+            clock_epoch,
+            stdio_capture,
+            stub_policy,
+            embed_dir,
+            embed_preopen,
+            used_imports,
+            report,
+            inherit_env,
+            debug,
+        } => {
+            let engine = fs::read(&input)
+                .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+            let adapter = fs::read(&adapter)
+                .with_context(|| format!("Failed to read adapter file: {}", adapter.display()))?;
+
+            let wit_path_str = wit_path.as_ref().map(|p| p.to_string_lossy().to_string());
+            let features = features
+                .iter()
+                .map(|v| Feature::from_str(v))
+                .collect::<Result<Vec<_>>>()?;
+            let string_encoding = StringEncoding::from_str(&string_encoding)?;
+            let random_seed = random_seed
+                .map(|seed| RandomSeed::from_str(&seed))
+                .transpose()?;
+            let stub_policy = stub_policy
+                .map(|path| {
+                    fs::read_to_string(&path).with_context(|| {
+                        format!("Failed to read stub policy file: {}", path.display())
+                    })
+                })
+                .transpose()?;
+            let embedded_filesystem = embed_dir
+                .as_ref()
+                .map(|dir| read_embedded_filesystem(dir, embed_preopen.clone()))
+                .transpose()?;
+            let used_imports = used_imports
+                .map(|path| read_used_imports(&path))
+                .transpose()?;
+
+            let result = splice::splice_bindings(
+                engine,
+                features.clone(),
+                None,
+                wit_path_str.clone(),
+                world_name.clone(),
+                false,
+                SpliceOptions {
+                    string_encoding: Some(string_encoding),
+                    export_names: None,
+                    used_imports,
+                    embedded_filesystem,
+                },
+            )
+            .map_err(splice_error)?;
+
+            let mut work_dir = tempfile::Builder::new()
+                .prefix("splicer")
+                .tempdir()
+                .context("Failed to create working directory")?;
+            work_dir.disable_cleanup(debug);
+            let initialized = wizer_initialize(
+                work_dir.path(),
+                &js_source,
+                &result,
+                &features,
+                embed_dir
+                    .as_deref()
+                    .map(|dir| (dir, embed_preopen.as_str())),
+                inherit_env,
+                debug,
+            );
+            if debug {
+                eprintln!(
+                    "Binary and sources available for debugging at {}",
+                    work_dir.path().display()
+                );
+            }
+
+            // After wizening, stub out the wasi imports depending on what features are enabled
+            let bin = stub_wasi::stub_wasi(
+                initialized?,
+                features,
+                None,
+                wit_path_str,
+                world_name,
+                StubOptions {
+                    random_seed,
+                    clock_epoch,
+                    stdio_capture,
+                    stub_policy,
+                },
+            )
+            .map_err(|e| anyhow::anyhow!(e))?;
+
+            if report {
+                print_stub_report(&bin.report);
+            }
+
+            let component = wit_component::ComponentEncoder::default()
+                .module(&bin.wasm)?
+                .adapter("wasi_snapshot_preview1", &adapter)?
+                .validate(true)
+                .encode()
+                .context("Failed to encode the component")?;
+
+            fs::write(&output, component)
+                .with_context(|| format!("Failed to write output file: {}", output.display()))?;
+
+            println!(
+                "Successfully componentized {} to {}",
+                js_source.display(),
+                output.display()
+            );
+        }
+
+        Commands::ValidateEngine { input } => {
+            let engine = fs::read(&input)
+                .with_context(|| format!("Failed to read input file: {}", input.display()))?;
+
+            let checks = validate::validate_engine(engine).map_err(|e| anyhow::anyhow!(e))?;
+
+            for check in &checks {
+                let status = if check.ok { "ok" } else { "FAILED" };
+                println!("[{status}] {}: {}", check.name, check.message);
+            }
+
+            let failed = checks.iter().filter(|check| !check.ok).count();
+            if failed > 0 {
+                anyhow::bail!(
+                    "{} does not provide the engine ABI required for splicing ({failed} checks failed)",
+                    input.display()
+                );
+            }
+            println!(
+                "{} provides the engine ABI required for splicing",
+                input.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the files of a directory to embed as a read-only filesystem
+fn read_embedded_filesystem(dir: &Path, preopen: String) -> Result<EmbeddedFilesystem> {
+    fn read_dir(dir: &Path, prefix: &str, files: &mut Vec<EmbeddedFile>) -> Result<()> {
+        let mut entries = fs::read_dir(dir)
+            .with_context(|| format!("Failed to read embedded directory: {}", dir.display()))?
+            .collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let path = format!("{prefix}{}", entry.file_name().to_string_lossy());
+            if entry.file_type()?.is_dir() {
+                read_dir(&entry.path(), &format!("{path}/"), files)?;
+            } else {
+                let contents = fs::read(entry.path()).with_context(|| {
+                    format!("Failed to read embedded file: {}", entry.path().display())
+                })?;
+                files.push(EmbeddedFile { path, contents });
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    read_dir(dir, "", &mut files)?;
+    Ok(EmbeddedFilesystem { preopen, files })
+}
+
+/// Reads the `[specifier, name]` pairs of the imports used by the guest module
+fn read_used_imports(path: &Path) -> Result<Vec<(String, String)>> {
+    let json = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read used imports file: {}", path.display()))?;
+    serde_json::from_str(&json)
+        .with_context(|| format!("Failed to parse used imports file: {}", path.display()))
+}
+
+/// Describes the missing exports of the guest module on their own lines, as
+/// the bindings would when initializing the engine, and unsupported exports
+/// by their name
+fn splice_error(err: SpliceError) -> anyhow::Error {
+    match err {
+        SpliceError::MissingExports(errors) => anyhow::anyhow!(errors
+            .iter()
+            .map(|error| format!("The guest module {error}"))
+            .collect::<Vec<_>>()
+            .join("\n")),
+        SpliceError::UnsupportedExport(UnsupportedExport { name, message }) => {
+            anyhow::anyhow!("Unable to bind the export \"{name}\": {message}")
+        }
+        err => anyhow::anyhow!(err),
+    }
+}
+
+/// Prints how each import was treated by stub-wasi
+fn print_stub_report(report: &[StubReportEntry]) {
+    for entry in report {
+        println!(
+            "[{}] {}#{} ({})",
+            entry.status, entry.module, entry.name, entry.reason
+        );
+    }
+}
+
+/// The WASI context of the initializer, taken by the wizer linker when the
+/// engine first calls a WASI import, since wizer only builds contexts
+/// inheriting the environment and stdin of the process
+static INIT_WASI_CTX: Mutex<Option<WasiP1Ctx>> = Mutex::new(None);
+
+/// Runs the `componentize.wizer` initializer of the spliced engine on the JS
+/// source with wizer, using the same environment as componentize.js, then
+/// checks the initialized engine for errors raised while initializing.
+///
+/// The embedded directory is preopened at its preopen path, in which case the
+/// sources are only preopened at their own path rather than at `/`.
+fn wizer_initialize(
+    work_dir: &Path,
+    js_source: &Path,
+    result: &SpliceResult,
+    features: &[Feature],
+    embedded: Option<(&Path, &str)>,
+    inherit_env: bool,
+    debug: bool,
+) -> Result<Vec<u8>> {
+    let sources_dir = work_dir.join("sources");
+    fs::create_dir_all(&sources_dir).with_context(|| {
+        format!(
+            "Failed to create working directory: {}",
+            sources_dir.display()
+        )
+    })?;
+
+    let source_name = js_source
+        .file_name()
+        .context("JS source path has no file name")?
+        .to_string_lossy()
+        .to_string();
+    fs::copy(js_source, sources_dir.join(&source_name))
+        .with_context(|| format!("Failed to read JS source: {}", js_source.display()))?;
+
+    let initializer = sources_dir.join("initializer.js");
+    fs::write(work_dir.join("in.wasm"), &result.wasm)?;
+    fs::write(&initializer, &result.js_bindings)?;
+
+    let mut ctx = WasiCtxBuilder::new();
+    if inherit_env {
+        ctx.inherit_env();
+    }
+    ctx.env("DEBUG", if debug { "1" } else { "" })
+        .env("SOURCE_NAME", &source_name)
+        .env("EXPORT_CNT", result.exports.len().to_string())
+        .env(
+            "FEATURE_CLOCKS",
+            if features.contains(&Feature::Clocks) {
+                "1"
+            } else {
+                ""
+            },
+        )
+        .env("IMPORT_CNT", result.imports.len().to_string());
+    for (idx, (name, core_fn)) in result.exports.iter().enumerate() {
+        let params = core_fn
+            .params
+            .iter()
+            .map(|ty| ty.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let ret = core_fn.ret.map(|ty| ty.to_string()).unwrap_or_default();
+        ctx.env(format!("EXPORT{idx}_NAME"), name)
+            .env(
+                format!("EXPORT{idx}_ARGS"),
+                format!("{}{params}", if core_fn.paramptr { "*" } else { "" }),
+            )
+            .env(
+                format!("EXPORT{idx}_RET"),
+                format!("{}{ret}", if core_fn.retptr { "*" } else { "" }),
+            )
+            .env(format!("EXPORT{idx}_RETSIZE"), core_fn.retsize.to_string());
+    }
+    for (idx, (_, name, argcnt)) in result.imports.iter().enumerate() {
+        ctx.env(format!("IMPORT{idx}_NAME"), name)
+            .env(format!("IMPORT{idx}_ARGCNT"), argcnt.to_string());
+    }
+    let source_path = match embedded {
+        Some(_) => sources_dir.join(&source_name).to_string_lossy().to_string(),
+        None => source_name,
+    };
+    let runtime_args = format!(
+        "--initializer-script-path {} --strip-path-prefix {}/ {source_path}",
+        initializer.display(),
+        sources_dir.display()
+    );
+    ctx.stdin(MemoryInputPipe::new(runtime_args))
+        .inherit_stdout()
+        .inherit_stderr();
+    let mut guest_dirs = vec![sources_dir.to_string_lossy().to_string()];
+    if embedded.is_none() {
+        guest_dirs.push("/".into());
+    }
+    for guest_dir in guest_dirs {
+        ctx.preopened_dir(&sources_dir, &guest_dir, DirPerms::all(), FilePerms::all())
+            .with_context(|| format!("Failed to open directory: {}", sources_dir.display()))?;
+    }
+    if let Some((dir, preopen)) = embedded {
+        ctx.preopened_dir(dir, preopen, DirPerms::READ, FilePerms::READ)
+            .with_context(|| format!("Failed to open directory: {}", dir.display()))?;
+    }
+    *INIT_WASI_CTX.lock().unwrap() = Some(ctx.build_p1());
+
+    let initialized = Wizer::new()
+        .init_func("componentize.wizer")
+        .wasm_bulk_memory(true)
+        .make_linker(Some(Rc::new(|engine: &wasmtime::Engine| {
+            let mut linker = wizer::Linker::new(engine);
+            wasmtime_wasi::preview1::add_to_linker_sync(&mut linker, |data: &mut StoreData| {
+                data.wasi_ctx.get_or_insert_with(|| {
+                    INIT_WASI_CTX
+                        .lock()
+                        .unwrap()
+                        .take()
+                        .expect("the initializer WASI context is only taken once")
+                })
+            })?;
+            Ok(linker)
+        })))?
+        .run(&result.wasm)
+        .context("Failed to initialize component");
+    INIT_WASI_CTX.lock().unwrap().take();
+    let initialized = initialized?;
+
+    check_init(&initialized, &initializer)?;
+    Ok(initialized)
+}
+
+/// Runs the `check_init` export of the initialized engine, describing the
+/// error it reports along with the engine's stderr output.
+///
+/// As in componentize.js, stderr writes are collected and any other import
+/// traps, since the engine must not call them when checking.
+fn check_init(wasm: &[u8], initializer: &Path) -> Result<()> {
+    let engine = wasmi::Engine::default();
+    let module = wasmi::Module::new(&engine, wasm)
+        .map_err(|e| anyhow::anyhow!("Failed to compile the initialized engine: {e}"))?;
+    let mut store = wasmi::Store::new(&engine, String::new());
+    let mut linker = wasmi::Linker::new(&engine);
+    for import in module.imports() {
+        let wasmi::ExternType::Func(ty) = import.ty() else {
+            continue;
+        };
+        let name = import.name();
+        if import.module() == "wasi_snapshot_preview1" && name == "fd_write" {
+            linker
+                .func_new(import.module(), name, ty.clone(), fd_write_stderr)
+                .map_err(|e| anyhow::anyhow!(e))?;
+        } else {
+            let import_name = name.to_string();
+            linker
+                .func_new(import.module(), name, ty.clone(), move |_, _, _| {
+                    Err(wasmi::Error::new(format!(
+                        "Internal error: unexpected call to \"{import_name}\" during Wasm verification"
+                    )))
+                })
+                .map_err(|e| anyhow::anyhow!(e))?;
         }
     }
+    let status = linker
+        .instantiate(&mut store, &module)
+        .and_then(|instance| instance.start(&mut store))
+        .and_then(|instance| instance.get_typed_func::<(), i32>(&store, "check_init"))
+        .and_then(|check_init| check_init.call(&mut store, ()))
+        .map_err(|e| anyhow::anyhow!("Failed to check the initialized engine: {e}"))?;
+
+    let err = match status {
+        0 => return Ok(()),
+        1 => "Unable to extract expected exports list".to_string(),
+        2 => "Unable to parse the core ABI export types".to_string(),
+        status => format!("Unknown error during initialization: {status}"),
+    };
+    let initializer = initializer.display().to_string();
+    let stderr = store
+        .data()
+        .lines()
+        .map(|line| match line.strip_prefix(&initializer) {
+            Some(rest) => rest
+                .trim_start_matches(|c: char| c == ':' || c.is_ascii_digit())
+                .trim_start_matches(' '),
+            None => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let stderr = stderr.trim();
+    if stderr.is_empty() {
+        anyhow::bail!("{err}");
+    }
+    anyhow::bail!("{err}\n{stderr}")
+}
 
+/// The preview1 `fd_write` of `check_init`, collecting the writes to stderr
+fn fd_write_stderr(
+    mut caller: wasmi::Caller<'_, String>,
+    params: &[wasmi::Val],
+    results: &mut [wasmi::Val],
+) -> Result<(), wasmi::Error> {
+    let [wasmi::Val::I32(fd), wasmi::Val::I32(iovs), wasmi::Val::I32(iovs_len), wasmi::Val::I32(nwritten)] =
+        params
+    else {
+        return Err(wasmi::Error::new("invalid fd_write parameters"));
+    };
+    results[0] = wasmi::Val::I32(0);
+    if *fd != 2 {
+        return Ok(());
+    }
+    let memory = caller
+        .get_export("memory")
+        .and_then(wasmi::Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("missing memory export"))?;
+    let (data, stderr) = memory.data_and_store_mut(&mut caller);
+    let read_u32 = |data: &[u8], ptr: usize| -> Result<u32, wasmi::Error> {
+        data.get(ptr..ptr + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .ok_or_else(|| wasmi::Error::new("fd_write out of bounds"))
+    };
+    let mut written = 0;
+    for i in 0..*iovs_len as usize {
+        let iov = *iovs as u32 as usize + i * 8;
+        let buf = read_u32(data, iov)? as usize;
+        let len = read_u32(data, iov + 4)? as usize;
+        let bytes = data
+            .get(buf..buf + len)
+            .ok_or_else(|| wasmi::Error::new("fd_write out of bounds"))?;
+        stderr.push_str(&String::from_utf8_lossy(bytes));
+        written += len as u32;
+    }
+    let nwritten = *nwritten as u32 as usize;
+    data.get_mut(nwritten..nwritten + 4)
+        .ok_or_else(|| wasmi::Error::new("fd_write out of bounds"))?
+        .copy_from_slice(&written.to_le_bytes());
     Ok(())
 }
+
+/// The exports, imports and features of a spliced component, which drivers
+/// other than componentize.js need to initialize the engine with wizer
+fn splice_manifest(result: &SpliceResult, features: &[Feature]) -> serde_json::Value {
+    let exports = result
+        .exports
+        .iter()
+        .map(|(name, core_fn)| {
+            serde_json::json!({
+                "name": name,
+                "params": core_fn.params.iter().map(|ty| ty.to_string()).collect::<Vec<_>>(),
+                "ret": core_fn.ret.map(|ty| ty.to_string()),
+                "retptr": core_fn.retptr,
+                "retsize": core_fn.retsize,
+                "paramptr": core_fn.paramptr,
+            })
+        })
+        .collect::<Vec<_>>();
+    let imports = result
+        .imports
+        .iter()
+        .map(|(specifier, name, arity)| {
+            serde_json::json!({
+                "specifier": specifier,
+                "name": name,
+                "arity": arity,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({
+        "exports": exports,
+        "imports": imports,
+        "features": features.iter().map(|feature| feature.to_string()).collect::<Vec<_>>(),
+    })
+}
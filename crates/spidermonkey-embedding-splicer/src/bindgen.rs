@@ -1,5 +1,6 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::Write;
+use std::mem;
 
 use anyhow::Result;
 use heck::*;
@@ -9,11 +10,11 @@ use js_component_bindgen::function_bindgen::{
 use js_component_bindgen::intrinsics::{render_intrinsics, Intrinsic};
 use js_component_bindgen::names::LocalNames;
 use js_component_bindgen::source::Source;
-use wit_bindgen_core::abi::{self, LiftLower};
+use wit_bindgen_core::abi::{self, Bindgen, Instruction, LiftLower};
 use wit_bindgen_core::wit_parser::Resolve;
 use wit_bindgen_core::wit_parser::{
-    Function, FunctionKind, Handle, InterfaceId, SizeAlign, Type, TypeDefKind, TypeId, TypeOwner,
-    WorldId, WorldItem,
+    Alignment, ArchitectureSize, Function, FunctionKind, Handle, InterfaceId, SizeAlign, Type,
+    TypeDefKind, TypeId, TypeOwner, WorldId, WorldItem, WorldKey,
 };
 use wit_component::StringEncoding;
 use wit_parser::abi::WasmType;
@@ -21,7 +22,7 @@ use wit_parser::abi::{AbiVariant, WasmSignature};
 
 use crate::wit::exports::local::spidermonkey_embedding_splicer::splicer::Feature;
 
-use crate::{uwrite, uwriteln};
+use crate::{guest_types, splice, uwrite, uwriteln};
 
 #[derive(Debug)]
 pub enum Resource {
@@ -77,6 +78,24 @@ pub struct BindingItem {
     pub resource: Resource,
     pub name: String,
     pub func: CoreFn,
+    pub is_async: bool,
+}
+
+impl BindingItem {
+    /// The canonical component model name of the function, including the
+    /// `[async]` prefixes of async functions
+    pub fn canon_string(&self) -> String {
+        let canon = self.resource.canon_string(&self.name);
+        if !self.is_async {
+            return canon;
+        }
+        match &self.resource {
+            Resource::None => format!("[async]{canon}"),
+            Resource::Method(_) => canon.replacen("[method]", "[async method]", 1),
+            Resource::Static(_) => canon.replacen("[static]", "[async static]", 1),
+            Resource::Constructor(_) => canon,
+        }
+    }
 }
 
 struct JsBindgen<'a> {
@@ -109,6 +128,17 @@ struct JsBindgen<'a> {
 
     /// Features that were enabled at the time of generation
     features: &'a Vec<Feature>,
+
+    /// String encoding of the canonical ABI for the world functions
+    string_encoding: StringEncoding,
+
+    /// Versionless names of the world items shared with the engine, which
+    /// always use UTF-8 as the engine itself does
+    engine_items: BTreeSet<String>,
+
+    /// The `(specifier, name)` pairs imported by the guest, when these are
+    /// known, with `*` for namespace imports
+    used_imports: Option<&'a [(String, String)]>,
 }
 
 #[derive(Debug)]
@@ -134,13 +164,29 @@ pub struct Componentization {
     pub exports: Vec<(String, BindingItem)>,
     pub imports: Vec<(String, BindingItem)>,
     pub resource_imports: Vec<(String, String, u32)>,
+    pub guest_types: String,
+    /// Errors for the exports of the world missing from the static export
+    /// names of the guest module, when these are given
+    pub missing_exports: Vec<String>,
 }
 
 pub fn componentize_bindgen(
     resolve: &Resolve,
     wid: WorldId,
+    engine_world: WorldId,
     features: &Vec<Feature>,
+    string_encoding: StringEncoding,
+    export_names: Option<&[String]>,
+    used_imports: Option<&[(String, String)]>,
 ) -> Result<Componentization> {
+    let engine = &resolve.worlds[engine_world];
+    let engine_items = engine
+        .imports
+        .keys()
+        .chain(engine.exports.keys())
+        .map(|key| encoding_key(&resolve.name_world_key(key)).to_string())
+        .collect();
+
     let mut bindgen = JsBindgen {
         src: Source::default(),
         esm_bindgen: EsmBindgen::default(),
@@ -156,6 +202,9 @@ pub fn componentize_bindgen(
         resource_directions: HashMap::new(),
         imported_resources: BTreeSet::new(),
         features,
+        string_encoding,
+        engine_items,
+        used_imports,
     };
 
     bindgen.sizes.fill(resolve);
@@ -169,6 +218,12 @@ pub fn componentize_bindgen(
     bindgen.exports_bindgen()?;
     bindgen.esm_bindgen.populate_export_aliases();
 
+    let guest_types =
+        guest_types::guest_types(resolve, wid, features, &bindgen.esm_bindgen.export_aliases)?;
+    let missing_exports = export_names
+        .map(|names| bindgen.esm_bindgen.missing_exports(names))
+        .unwrap_or_default();
+
     // consolidate import specifiers and generate wrappers
     // we do this separately because function index order matters
     let mut import_bindings = Vec::new();
@@ -181,22 +236,28 @@ pub fn componentize_bindgen(
         ));
     }
 
-    let by_specifier_by_resource = bindgen.imports.iter().fold(
-        BTreeMap::<_, BTreeMap<_, Vec<_>>>::new(),
-        |mut map, (specifier, item)| {
-            map.entry(specifier)
-                .or_default()
-                .entry(match &item.resource {
-                    Resource::None => None,
-                    Resource::Method(name)
-                    | Resource::Static(name)
-                    | Resource::Constructor(name) => Some(name),
-                })
-                .or_default()
-                .push(item);
-            map
-        },
-    );
+    // canonical built-ins bound as imports (such as task.return or the stream
+    // intrinsics) are internal to the bindings and not exposed to the guest
+    let by_specifier_by_resource = bindgen
+        .imports
+        .iter()
+        .filter(|(_, item)| !item.name.starts_with('['))
+        .fold(
+            BTreeMap::<_, BTreeMap<_, Vec<_>>>::new(),
+            |mut map, (specifier, item)| {
+                map.entry(specifier)
+                    .or_default()
+                    .entry(match &item.resource {
+                        Resource::None => None,
+                        Resource::Method(name)
+                        | Resource::Static(name)
+                        | Resource::Constructor(name) => Some(name),
+                    })
+                    .or_default()
+                    .push(item);
+                map
+            },
+        );
 
     let mut import_wrappers = Vec::new();
     for (specifier, by_resource) in by_specifier_by_resource {
@@ -395,38 +456,177 @@ pub fn componentize_bindgen(
         exports: bindgen.exports,
         imports: bindgen.imports,
         resource_imports,
+        guest_types,
+        missing_exports,
     })
 }
 
-impl JsBindgen<'_> {
+impl<'a> JsBindgen<'a> {
     fn intrinsic(&mut self, intrinsic: Intrinsic) -> String {
         self.all_intrinsics.insert(intrinsic);
         intrinsic.name().to_string()
     }
 
+    /// Skip bindings generation for wasi:http/incoming-handler if the fetch-event
+    /// feature was enabled. We expect that the built-in engine implementation will be used
+    fn binds_export(&self, name: &str) -> bool {
+        !(splice::is_versioned_interface(name, &splice::HTTP_HANDLER_INTERFACES)
+            && self.features.contains(&Feature::FetchEvent))
+    }
+
+    /// The specifier and name the guest imports a function of the world by,
+    /// where resource functions are imported by their class, which `$root`
+    /// exports for world-level resources
+    fn guest_import(&self, key: &WorldKey, iface: bool, func: &Function) -> (String, String) {
+        let import_name = self.resolve.name_world_key(key);
+        match func.kind {
+            FunctionKind::Freestanding | FunctionKind::AsyncFreestanding if iface => {
+                (import_name, func.item_name().to_lower_camel_case())
+            }
+            FunctionKind::Freestanding | FunctionKind::AsyncFreestanding => {
+                (import_name, "default".to_string())
+            }
+            FunctionKind::Method(ty)
+            | FunctionKind::Static(ty)
+            | FunctionKind::Constructor(ty)
+            | FunctionKind::AsyncMethod(ty)
+            | FunctionKind::AsyncStatic(ty) => {
+                let resource = self.resolve.types[ty].name.as_ref().unwrap();
+                let specifier = if iface {
+                    import_name
+                } else {
+                    "$root".to_string()
+                };
+                (specifier, resource.to_upper_camel_case())
+            }
+        }
+    }
+
+    /// Whether the guest imports the given name of a specifier, which is
+    /// assumed for all imports when the guest imports are not known
+    fn imports_guest_item(&self, specifier: &str, name: &str) -> bool {
+        self.used_imports.is_none_or(|used| {
+            used.iter()
+                .any(|(s, n)| s == specifier && (n == name || n == "*"))
+        })
+    }
+
+    /// The imported resources which the guest can obtain, either from the
+    /// constructor or static functions of their classes, or through the types
+    /// of the bound functions. When the guest imports are known, importing a
+    /// class makes its resource reachable as well. The methods of a resource
+    /// are all bound along with its class, so the types of those are traced
+    /// recursively.
+    fn reachable_resources(&self) -> BTreeSet<TypeId> {
+        let world = &self.resolve.worlds[self.world];
+
+        let mut resource_functions = BTreeMap::<_, Vec<_>>::new();
+        let mut pending = Vec::new();
+        let trace = |func: &Function, pending: &mut Vec<TypeId>| {
+            for ty in func
+                .params
+                .iter()
+                .map(|(_, ty)| ty)
+                .chain(func.result.iter())
+            {
+                type_resources(self.resolve, ty, pending);
+            }
+        };
+        let imported_functions = world.imports.iter().flat_map(|(key, item)| match item {
+            WorldItem::Function(func) => vec![(key, false, func)],
+            WorldItem::Interface { id, .. } => self.resolve.interfaces[*id]
+                .functions
+                .values()
+                .map(|func| (key, true, func))
+                .collect(),
+            WorldItem::Type(_) => Vec::new(),
+        });
+        for (key, iface, func) in imported_functions {
+            let (specifier, name) = self.guest_import(key, iface, func);
+            let imported = self.imports_guest_item(&specifier, &name);
+            match func.kind {
+                FunctionKind::Freestanding | FunctionKind::AsyncFreestanding => {
+                    if imported {
+                        trace(func, &mut pending);
+                    }
+                }
+                FunctionKind::Constructor(ty)
+                | FunctionKind::Static(ty)
+                | FunctionKind::AsyncStatic(ty) => {
+                    if imported {
+                        pending.push(ty);
+                    }
+                    resource_functions.entry(ty).or_default().push(func);
+                }
+                FunctionKind::Method(ty) | FunctionKind::AsyncMethod(ty) => {
+                    if imported && self.used_imports.is_some() {
+                        pending.push(ty);
+                    }
+                    resource_functions.entry(ty).or_default().push(func);
+                }
+            }
+        }
+        for (key, item) in &world.exports {
+            if !self.binds_export(&self.resolve.name_world_key(key)) {
+                continue;
+            }
+            match item {
+                WorldItem::Function(func) => trace(func, &mut pending),
+                WorldItem::Interface { id, .. } => {
+                    for func in self.resolve.interfaces[*id].functions.values() {
+                        trace(func, &mut pending);
+                    }
+                }
+                WorldItem::Type(_) => {}
+            }
+        }
+
+        let mut reachable = BTreeSet::new();
+        while let Some(resource) = pending.pop() {
+            if reachable.insert(resource) {
+                for func in resource_functions.get(&resource).into_iter().flatten() {
+                    trace(func, &mut pending);
+                }
+            }
+        }
+        reachable
+    }
+
     fn exports_bindgen(&mut self) -> Result<()> {
+        // the bindings of exported interfaces are prefixed by their interface
+        // names, which are not unique across packages
+        let mut interface_names = BTreeSet::new();
         for (key, export) in &self.resolve.worlds[self.world].exports {
             let name = self.resolve.name_world_key(key);
-
-            // Skip bindings generation for wasi:http/incoming-handler if the fetch-event
-            // feature was enabled. We expect that the built-in engine implementation will be used
-            if name.starts_with("wasi:http/incoming-handler@0.2.")
-                && self.features.contains(&Feature::FetchEvent)
-            {
+            if !self.binds_export(&name) {
                 continue;
             }
 
             match export {
                 WorldItem::Function(func) => {
+                    if !matches!(
+                        func.kind,
+                        FunctionKind::Freestanding | FunctionKind::AsyncFreestanding
+                    ) {
+                        return Err(ExportError::world_resource(&name).into());
+                    }
                     let local_name = self.local_names.create_once(&func.name).to_string();
-                    self.export_bindgen(name, false, None, &local_name, StringEncoding::UTF8, func);
+                    let string_encoding = self.string_encoding(&name);
                     self.esm_bindgen.add_export_func(
                         None,
                         local_name.to_string(),
-                        func.name.to_lower_camel_case(),
-                    );
+                        func.item_name().to_lower_camel_case(),
+                    )?;
+                    self.export_bindgen(name, false, None, &local_name, string_encoding, func);
                 }
                 WorldItem::Interface { id, stability: _ } => {
+                    let iface_name = interface_name(self.resolve, *id).map(|iface_name| {
+                        if interface_names.insert(iface_name.clone()) {
+                            iface_name
+                        } else {
+                            qualified_interface_name(self.resolve, *id, &iface_name)
+                        }
+                    });
                     let iface = &self.resolve.interfaces[*id];
                     for id in iface.types.values() {
                         if let TypeDefKind::Resource = &self.resolve.types[*id].kind {
@@ -440,59 +640,65 @@ impl JsBindgen<'_> {
                             .create_once(&format!("{name}-{func_name}"))
                             .to_string();
                         match &func.kind {
-                            FunctionKind::Freestanding => {
+                            FunctionKind::Freestanding | FunctionKind::AsyncFreestanding => {
                                 let name = &name;
+                                self.esm_bindgen.add_export_func(
+                                    Some(name),
+                                    local_name.clone(),
+                                    func.item_name().to_lower_camel_case(),
+                                )?;
                                 self.export_bindgen(
                                     name.to_string(),
                                     true,
-                                    interface_name(self.resolve, *id),
+                                    iface_name.clone(),
                                     &local_name,
-                                    StringEncoding::UTF8,
+                                    self.string_encoding(name),
                                     func,
                                 );
-                                self.esm_bindgen.add_export_func(
-                                    Some(name),
-                                    local_name,
-                                    func.name.to_lower_camel_case(),
-                                );
                             }
                             FunctionKind::Method(ty)
                             | FunctionKind::Static(ty)
-                            | FunctionKind::Constructor(ty) => {
+                            | FunctionKind::Constructor(ty)
+                            | FunctionKind::AsyncMethod(ty)
+                            | FunctionKind::AsyncStatic(ty) => {
                                 let name = &name;
                                 let ty = &self.resolve.types[*ty];
                                 let resource_name = ty.name.as_ref().unwrap().to_upper_camel_case();
+                                // resources of the same name may be exported by
+                                // several interfaces, each bound separately
                                 let local_name = self
                                     .local_names
                                     .get_or_create(
-                                        format!("resource:{resource_name}"),
+                                        format!("resource:{name}:{resource_name}"),
                                         &resource_name,
                                     )
                                     .0
                                     .to_upper_camel_case();
+                                self.esm_bindgen.ensure_exported_resource(
+                                    Some(name),
+                                    local_name.clone(),
+                                    resource_name,
+                                )?;
                                 self.export_bindgen(
                                     name.to_string(),
                                     true,
-                                    interface_name(self.resolve, *id),
+                                    iface_name.clone(),
                                     &local_name,
-                                    StringEncoding::UTF8,
+                                    self.string_encoding(name),
                                     func,
                                 );
-                                self.esm_bindgen.ensure_exported_resource(
-                                    Some(name),
-                                    local_name,
-                                    resource_name,
-                                );
                             }
-                            FunctionKind::AsyncFreestanding => todo!(),
-                            FunctionKind::AsyncMethod(_id) => todo!(),
-                            FunctionKind::AsyncStatic(_id) => todo!(),
                         };
                     }
                 }
 
-                // ignore type exports for now
-                WorldItem::Type(_) => {}
+                // exported types are only referenced by the exported functions,
+                // without bindings of their own
+                WorldItem::Type(id) => {
+                    if self.resolve.types[*id].kind == TypeDefKind::Resource {
+                        return Err(ExportError::world_resource(&name).into());
+                    }
+                }
             }
         }
         Ok(())
@@ -516,11 +722,17 @@ impl JsBindgen<'_> {
 
         uwriteln!(self.src, "\nclass import_{name} {{");
 
-        // TODO: Imports tree-shaking for resources is disabled since it is not functioning correctly.
-        // To make this work properly, we need to trace recursively through the type graph
-        // to include all resources across argument types.
+        // the methods used by the guest cannot be known ahead of time, so all
+        // of them are bound for the resources which are reachable at all
         for (_, func) in functions {
-            self.import_bindgen(import_name.to_string(), func, true, iface_name.clone());
+            let payloads = PayloadIntrinsics::new(self.resolve, import_name, func);
+            self.import_bindgen(
+                import_name.to_string(),
+                func,
+                true,
+                iface_name.clone(),
+                &payloads,
+            );
         }
 
         let lower_camel = &self.resolve.types[resource]
@@ -551,14 +763,37 @@ impl JsBindgen<'_> {
     }
 
     fn imports_bindgen(&mut self) {
+        // canonical built-ins are bound ahead of the imports, as the bindings
+        // using them may be emitted inside of resource class bodies
+        let has_payloads = self.payload_intrinsics_bindgen();
+        if has_payloads || self.has_async_imports() {
+            self.async_intrinsics_bindgen();
+        }
+        if has_payloads {
+            self.payload_helpers_bindgen();
+        }
+        if self.uses_error_context() {
+            self.error_context_bindgen();
+        }
+        if self.string_encoding == StringEncoding::CompactUTF16 {
+            self.compact_utf16_bindgen();
+        }
+
+        let reachable_resources = self.reachable_resources();
+
         for (key, impt) in &self.resolve.worlds[self.world].imports {
             let import_name = self.resolve.name_world_key(key);
             match &impt {
                 WorldItem::Function(f) => {
-                    if !matches!(f.kind, FunctionKind::Freestanding) {
+                    if !matches!(
+                        f.kind,
+                        FunctionKind::Freestanding | FunctionKind::AsyncFreestanding
+                    ) || !self.imports_guest_item(&import_name, "default")
+                    {
                         continue;
                     }
-                    self.import_bindgen(import_name, f, false, None);
+                    let payloads = PayloadIntrinsics::new(self.resolve, "$root", f);
+                    self.import_bindgen(import_name, f, false, None, &payloads);
                 }
                 WorldItem::Interface {
                     id: i,
@@ -595,14 +830,24 @@ impl JsBindgen<'_> {
 
                     for (resource, functions) in by_resource {
                         if let Some(ty) = resource {
+                            if !reachable_resources.contains(&ty) {
+                                continue;
+                            }
                             self.resource_bindgen(ty, &import_name, &iface_name, functions);
                         } else {
                             for (_, func) in functions {
+                                let (specifier, name) = self.guest_import(key, true, func);
+                                if !self.imports_guest_item(&specifier, &name) {
+                                    continue;
+                                }
+                                let payloads =
+                                    PayloadIntrinsics::new(self.resolve, &import_name, func);
                                 self.import_bindgen(
                                     import_name.clone(),
                                     func,
                                     true,
                                     iface_name.clone(),
+                                    &payloads,
                                 );
                             }
                         }
@@ -613,6 +858,9 @@ impl JsBindgen<'_> {
                     if ty.kind == TypeDefKind::Resource {
                         self.resource_directions
                             .insert(*id, AbiVariant::GuestImport);
+                        if !reachable_resources.contains(id) {
+                            continue;
+                        }
 
                         let resource_name = ty.name.as_ref().unwrap();
 
@@ -631,6 +879,14 @@ impl JsBindgen<'_> {
                                     function.name.strip_prefix("[static]")
                                 {
                                     stripped
+                                } else if let Some(stripped) =
+                                    function.name.strip_prefix("[async method]")
+                                {
+                                    stripped
+                                } else if let Some(stripped) =
+                                    function.name.strip_prefix("[async static]")
+                                {
+                                    stripped
                                 } else {
                                     continue;
                                 };
@@ -654,36 +910,47 @@ impl JsBindgen<'_> {
         func: &Function,
         iface: bool,
         iface_name: Option<String>,
+        payloads: &PayloadIntrinsics,
     ) {
         let fn_name = func.item_name();
         let fn_camel_name = fn_name.to_lower_camel_case();
+        let is_async = matches!(
+            func.kind,
+            FunctionKind::AsyncFreestanding
+                | FunctionKind::AsyncMethod(_)
+                | FunctionKind::AsyncStatic(_)
+        );
+        let async_ = if is_async { "async " } else { "" };
 
         use generate_binding_name_import as binding_name_fn;
 
         let (binding_name, resource) = match &func.kind {
-            FunctionKind::Freestanding => {
+            FunctionKind::Freestanding | FunctionKind::AsyncFreestanding => {
                 let binding_name =
                     generate_binding_name_import(&fn_camel_name, &iface_name, &import_name);
 
-                uwrite!(self.src, "\nfunction import_{binding_name}");
+                uwrite!(self.src, "\n{async_}function import_{binding_name}");
 
                 (binding_name, Resource::None)
             }
-            FunctionKind::Method(ty) => {
+            FunctionKind::Method(ty) | FunctionKind::AsyncMethod(ty) => {
                 let args = (0..(func.params.len() - 1))
                     .map(|n| format!("arg{n}"))
                     .collect::<Vec<_>>()
                     .join(", ");
 
-                uwrite!(self.src, "{fn_camel_name}({args}) {{\nfunction helper");
+                uwrite!(
+                    self.src,
+                    "{fn_camel_name}({args}) {{\n{async_}function helper"
+                );
 
                 (
                     "<<INVALID>>".to_string(),
                     Resource::Method(self.resolve.types[*ty].name.clone().unwrap()),
                 )
             }
-            FunctionKind::Static(ty) => {
-                uwrite!(self.src, "static {fn_camel_name}");
+            FunctionKind::Static(ty) | FunctionKind::AsyncStatic(ty) => {
+                uwrite!(self.src, "static {async_}{fn_camel_name}");
                 (
                     "<<INVALID>>".to_string(),
                     Resource::Static(self.resolve.types[*ty].name.clone().unwrap()),
@@ -696,30 +963,39 @@ impl JsBindgen<'_> {
                     Resource::Constructor(self.resolve.types[*ty].name.clone().unwrap()),
                 )
             }
-            FunctionKind::AsyncFreestanding => todo!(),
-            FunctionKind::AsyncMethod(_id) => todo!(),
-            FunctionKind::AsyncStatic(_id) => todo!(),
         };
 
-        // imports are canonicalized as exports because
-        // the function bindgen as currently written still makes this assumption
-        self.bindgen(
-            func.params.len(),
-            &format!(
-                "$import_{}",
-                binding_name_fn(
-                    &resource.func_name(fn_name),
-                    &iface_name,
-                    import_name.as_str()
-                )
-            ),
-            StringEncoding::UTF8,
-            func,
-            AbiVariant::GuestExport,
+        let callee = format!(
+            "$import_{}",
+            binding_name_fn(
+                &resource.func_name(fn_name),
+                &iface_name,
+                import_name.as_str()
+            )
         );
+        if is_async {
+            self.async_import_bindgen(
+                func.params.len(),
+                &callee,
+                self.string_encoding(&import_name),
+                func,
+                payloads,
+            );
+        } else {
+            // imports are canonicalized as exports because
+            // the function bindgen as currently written still makes this assumption
+            self.bindgen(
+                func.params.len(),
+                &callee,
+                self.string_encoding(&import_name),
+                func,
+                AbiVariant::GuestExport,
+                payloads,
+            );
+        }
         self.src.push_str("\n");
 
-        if let FunctionKind::Method(_) = &func.kind {
+        if let FunctionKind::Method(_) | FunctionKind::AsyncMethod(_) = &func.kind {
             let args = (0..(func.params.len() - 1))
                 .map(|n| format!(", arg{n}"))
                 .collect::<Vec<_>>()
@@ -728,7 +1004,12 @@ impl JsBindgen<'_> {
             uwriteln!(self.src, "return helper(this{args});\n}}");
         }
 
-        let sig = self.resolve.wasm_signature(AbiVariant::GuestImport, func);
+        let func = if is_async {
+            self.async_import_core_fn(func)
+        } else {
+            let sig = self.resolve.wasm_signature(AbiVariant::GuestImport, func);
+            self.core_fn(func, &sig)
+        };
 
         let component_item = if let Some(iface_name) = iface_name {
             BindingItem {
@@ -737,7 +1018,8 @@ impl JsBindgen<'_> {
                 iface_name: Some(iface_name),
                 resource,
                 name: fn_name.to_string(),
-                func: self.core_fn(func, &sig),
+                func,
+                is_async,
             }
         } else {
             BindingItem {
@@ -746,7 +1028,8 @@ impl JsBindgen<'_> {
                 iface_name: None,
                 resource,
                 name: fn_name.to_string(),
-                func: self.core_fn(func, &sig),
+                func,
+                is_async,
             }
         };
 
@@ -836,6 +1119,11 @@ impl JsBindgen<'_> {
             TypeDefKind::Type(ty) => {
                 self.iter_resources(ty, map);
             }
+            TypeDefKind::Future(ty) | TypeDefKind::Stream(ty) => {
+                if let Some(ty) = ty {
+                    self.iter_resources(ty, map);
+                }
+            }
             _ => unreachable!(),
         }
     }
@@ -847,6 +1135,7 @@ impl JsBindgen<'_> {
         string_encoding: StringEncoding,
         func: &Function,
         abi: AbiVariant,
+        payloads: &PayloadIntrinsics,
     ) {
         self.src.push_str("(");
         let mut params = Vec::new();
@@ -872,15 +1161,18 @@ impl JsBindgen<'_> {
             match abi {
                 AbiVariant::GuestExport => ErrHandling::ThrowResultErr,
                 AbiVariant::GuestImport => ErrHandling::ResultCatchHandler,
-                AbiVariant::GuestImportAsync => todo!(),
-                AbiVariant::GuestExportAsync => todo!(),
-                AbiVariant::GuestExportAsyncStackful => todo!(),
+                AbiVariant::GuestImportAsync => {
+                    unreachable!("async imports are bound by async_import_bindgen")
+                }
+                AbiVariant::GuestExportAsync | AbiVariant::GuestExportAsyncStackful => {
+                    unreachable!("async exports are bound by async_export_bindgen")
+                }
             }
         } else {
             ErrHandling::None
         };
 
-        let mut f = FunctionBindgen {
+        let f = FunctionBindgen {
             is_async: false,
             tracing_prefix: None,
             intrinsics: &mut self.all_intrinsics,
@@ -895,32 +1187,32 @@ impl JsBindgen<'_> {
             tmp: 0,
             params,
             post_return: None,
-            encoding: match string_encoding {
-                StringEncoding::UTF8 => StringEncoding::UTF8,
-                StringEncoding::UTF16 => todo!("UTF16 encoding"),
-                StringEncoding::CompactUTF16 => todo!("Compact UTF16 encoding"),
-            },
+            encoding: string_encoding,
             src: Source::default(),
             resource_map: &resource_map,
             cur_resource_borrows: false,
             resolve: self.resolve,
             callee_resource_dynamic: false,
         };
+        let mut f = PayloadBindgen { inner: f, payloads };
         abi::call(
             self.resolve,
             abi,
             match abi {
                 AbiVariant::GuestImport => LiftLower::LiftArgsLowerResults,
                 AbiVariant::GuestExport => LiftLower::LowerArgsLiftResults,
-                AbiVariant::GuestImportAsync => todo!(),
-                AbiVariant::GuestExportAsync => todo!(),
-                AbiVariant::GuestExportAsyncStackful => todo!(),
+                AbiVariant::GuestImportAsync => {
+                    unreachable!("async imports are bound by async_import_bindgen")
+                }
+                AbiVariant::GuestExportAsync | AbiVariant::GuestExportAsyncStackful => {
+                    unreachable!("async exports are bound by async_export_bindgen")
+                }
             },
             func,
             &mut f,
             false,
         );
-        self.src.push_str(&f.src);
+        self.src.push_str(&f.inner.src);
         self.src.push_str("}");
     }
 
@@ -937,12 +1229,14 @@ impl JsBindgen<'_> {
         let fn_camel_name = fn_name.to_lower_camel_case();
 
         let (resource, callee) = match &func.kind {
-            FunctionKind::Freestanding => (Resource::None, callee.to_string()),
-            FunctionKind::Method(ty) => (
+            FunctionKind::Freestanding | FunctionKind::AsyncFreestanding => {
+                (Resource::None, callee.to_string())
+            }
+            FunctionKind::Method(ty) | FunctionKind::AsyncMethod(ty) => (
                 Resource::Method(self.resolve.types[*ty].name.clone().unwrap()),
                 format!("{callee}.prototype.{fn_camel_name}.call"),
             ),
-            FunctionKind::Static(ty) => (
+            FunctionKind::Static(ty) | FunctionKind::AsyncStatic(ty) => (
                 Resource::Static(self.resolve.types[*ty].name.clone().unwrap()),
                 format!("{callee}.{fn_camel_name}"),
             ),
@@ -950,9 +1244,6 @@ impl JsBindgen<'_> {
                 Resource::Constructor(self.resolve.types[*ty].name.clone().unwrap()),
                 format!("new {callee}"),
             ),
-            FunctionKind::AsyncFreestanding => todo!(),
-            FunctionKind::AsyncMethod(_id) => todo!(),
-            FunctionKind::AsyncStatic(_id) => todo!(),
         };
 
         let binding_name = format!(
@@ -960,6 +1251,54 @@ impl JsBindgen<'_> {
             binding_name(&resource.func_name(fn_name), &iface_name)
         );
 
+        let is_async = matches!(
+            func.kind,
+            FunctionKind::AsyncFreestanding
+                | FunctionKind::AsyncMethod(_)
+                | FunctionKind::AsyncStatic(_)
+        );
+
+        let payload_module = if iface {
+            format!("[export]{name}")
+        } else {
+            "[export]$root".to_string()
+        };
+        let payloads = PayloadIntrinsics::new(self.resolve, &payload_module, func);
+
+        if is_async {
+            self.async_export_bindgen(
+                &name,
+                iface,
+                iface_name.clone(),
+                &binding_name,
+                &callee,
+                string_encoding,
+                func,
+                &payloads,
+            );
+            self.exports.push((
+                name,
+                BindingItem {
+                    iface,
+                    binding_name,
+                    iface_name,
+                    name: fn_name.to_string(),
+                    resource,
+                    func: self.core_fn(
+                        &Function {
+                            result: None,
+                            ..func.clone()
+                        },
+                        &self
+                            .resolve
+                            .wasm_signature(AbiVariant::GuestExportAsyncStackful, func),
+                    ),
+                    is_async,
+                },
+            ));
+            return;
+        }
+
         // all exports are supported as async functions
         uwrite!(self.src, "\nasync function {binding_name}");
 
@@ -973,6 +1312,7 @@ impl JsBindgen<'_> {
             string_encoding,
             func,
             AbiVariant::GuestImport,
+            &payloads,
         );
         self.src.push_str("\n");
 
@@ -989,125 +1329,993 @@ impl JsBindgen<'_> {
                     func,
                     &self.resolve.wasm_signature(AbiVariant::GuestExport, func),
                 ),
+                is_async,
             },
         ));
     }
 
-    fn core_fn(&self, func: &Function, sig: &WasmSignature) -> CoreFn {
-        CoreFn {
-            retsize: if sig.retptr {
-                let mut retsize: u32 = 0;
-                if let Some(ret_ty) = func.result {
-                    retsize += self.sizes.size(&ret_ty).size_wasm32() as u32;
-                }
-                retsize
-            } else {
-                0
-            },
-            retptr: sig.retptr,
-            paramptr: sig.indirect_params,
-            params: sig
-                .params
+    /// Async exports are lifted with the stackful async ABI. The core export
+    /// itself returns nothing, instead the JS binding lowers the resolved
+    /// value of the guest function through the `[task-return]` intrinsic,
+    /// which is bound as a regular import function.
+    fn async_export_bindgen(
+        &mut self,
+        name: &str,
+        iface: bool,
+        iface_name: Option<String>,
+        binding_name: &str,
+        callee: &str,
+        string_encoding: StringEncoding,
+        func: &Function,
+        payloads: &PayloadIntrinsics,
+    ) {
+        let task_return_specifier = if iface {
+            format!("[export]{name}")
+        } else {
+            "[export]$root".to_string()
+        };
+        let task_return = Function {
+            name: format!("[task-return]{}", func.name),
+            kind: FunctionKind::Freestanding,
+            params: func
+                .result
                 .iter()
-                .map(|v| match v {
-                    WasmType::I32 => CoreTy::I32,
-                    WasmType::I64 => CoreTy::I64,
-                    WasmType::F32 => CoreTy::F32,
-                    WasmType::F64 => CoreTy::F64,
-                    WasmType::PointerOrI64 => CoreTy::I64,
-                    WasmType::Pointer => CoreTy::I32,
-                    WasmType::Length => CoreTy::I32,
-                })
+                .map(|ty| ("ret".to_string(), *ty))
                 .collect(),
-            ret: match sig.results.first() {
-                None => None,
-                Some(WasmType::I32) => Some(CoreTy::I32),
-                Some(WasmType::I64) => Some(CoreTy::I64),
-                Some(WasmType::F32) => Some(CoreTy::F32),
-                Some(WasmType::F64) => Some(CoreTy::F64),
-                Some(WasmType::PointerOrI64) => Some(CoreTy::I64),
-                Some(WasmType::Pointer) => Some(CoreTy::I32),
-                Some(WasmType::Length) => Some(CoreTy::I32),
-            },
+            result: None,
+            docs: Default::default(),
+            stability: Default::default(),
+        };
+        let task_return_binding = generate_binding_name_import(
+            &task_return.item_name().to_lower_camel_case(),
+            &iface_name,
+            &task_return_specifier,
+        );
+        // the futures and streams of the result keep the built-ins of the
+        // export function itself
+        self.import_bindgen(
+            task_return_specifier,
+            &task_return,
+            true,
+            iface_name,
+            payloads,
+        );
+
+        let task_callee = format!("{binding_name}$task");
+        uwriteln!(self.src, "\nasync function {task_callee}(...args) {{");
+        match func.result {
+            None => uwriteln!(
+                self.src,
+                "await {callee}(...args);\nimport_{task_return_binding}();"
+            ),
+            Some(_) if get_result_types(self.resolve, func.result).is_some() => {
+                let err_payload = self.intrinsic(Intrinsic::GetErrorPayload);
+                uwriteln!(
+                    self.src,
+                    "let ret;
+                    try {{
+                        ret = {{ tag: 'ok', val: await {callee}(...args) }};
+                    }} catch (e) {{
+                        ret = {{ tag: 'err', val: {err_payload}(e) }};
+                    }}
+                    import_{task_return_binding}(ret);"
+                );
+            }
+            Some(_) => uwriteln!(
+                self.src,
+                "import_{task_return_binding}(await {callee}(...args));"
+            ),
         }
+        uwriteln!(self.src, "}}");
+
+        // the export binding itself lifts the arguments and awaits the task,
+        // with the result having already been handed off to task.return
+        let task_func = Function {
+            result: None,
+            ..func.clone()
+        };
+        let sig = self
+            .resolve
+            .wasm_signature(AbiVariant::GuestImport, &task_func);
+        uwrite!(self.src, "\nasync function {binding_name}");
+        self.bindgen(
+            sig.params.len(),
+            &format!("await {task_callee}"),
+            string_encoding,
+            &task_func,
+            AbiVariant::GuestImport,
+            payloads,
+        );
+        self.src.push_str("\n");
     }
-}
 
-type LocalName = String;
+    /// The string encoding of the functions of a world item, given by name.
+    /// Items shared with the engine are bound by the engine as UTF-8.
+    fn string_encoding(&self, name: &str) -> StringEncoding {
+        if self.engine_items.contains(encoding_key(name)) {
+            StringEncoding::UTF8
+        } else {
+            self.string_encoding
+        }
+    }
 
-#[derive(Debug)]
-enum Binding {
-    Interface(BTreeMap<String, Binding>),
-    Resource(LocalName),
-    Local(LocalName),
-}
+    /// Compact UTF-16 strings are stored as latin1 when every code unit fits
+    /// in a byte, otherwise as UTF-16 with the high bit of the length set
+    fn compact_utf16_bindgen(&mut self) {
+        uwriteln!(
+            self.src,
+            "
+            const COMPACT_UTF16_TAG = 0x80000000;
+            let $compactUtf16EncodedLen = 0;
+            function $compactUtf16Encode(str, realloc, memory) {{
+                const len = str.length;
+                let latin1 = true;
+                for (let i = 0; i < len; i++) {{
+                    if (str.charCodeAt(i) > 0xff) {{
+                        latin1 = false;
+                        break;
+                    }}
+                }}
+                if (latin1) {{
+                    const ptr = realloc(0, 0, 2, len);
+                    const out = new Uint8Array(memory.buffer, ptr, len);
+                    for (let i = 0; i < len; i++) out[i] = str.charCodeAt(i);
+                    $compactUtf16EncodedLen = len;
+                    return ptr;
+                }}
+                const ptr = realloc(0, 0, 2, len * 2);
+                const view = new DataView(memory.buffer, ptr, len * 2);
+                for (let i = 0; i < len; i++) view.setUint16(i * 2, str.charCodeAt(i), true);
+                $compactUtf16EncodedLen = len | COMPACT_UTF16_TAG;
+                return ptr;
+            }}
+            function $compactUtf16Decode(ptr, len, memory) {{
+                const units = [];
+                if (len & COMPACT_UTF16_TAG) {{
+                    const view = new DataView(memory.buffer, ptr, (len & ~COMPACT_UTF16_TAG) * 2);
+                    for (let i = 0; i < view.byteLength; i += 2) units.push(view.getUint16(i, true));
+                }} else {{
+                    units.push(...new Uint8Array(memory.buffer, ptr, len));
+                }}
+                let str = '';
+                for (let i = 0; i < units.length; i += 4096) {{
+                    str += String.fromCharCode(...units.slice(i, i + 4096));
+                }}
+                return str;
+            }}"
+        );
+    }
 
-#[derive(Default)]
-struct EsmBindgen {
-    exports: BTreeMap<String, Binding>,
-    export_aliases: BTreeMap<String, String>,
-}
+    fn has_async_imports(&self) -> bool {
+        let is_async = |func: &Function| {
+            matches!(
+                func.kind,
+                FunctionKind::AsyncFreestanding
+                    | FunctionKind::AsyncMethod(_)
+                    | FunctionKind::AsyncStatic(_)
+            )
+        };
+        self.resolve.worlds[self.world]
+            .imports
+            .values()
+            .any(|impt| match impt {
+                WorldItem::Function(func) => is_async(func),
+                WorldItem::Interface { id, .. } => self.resolve.interfaces[*id]
+                    .functions
+                    .values()
+                    .any(is_async),
+                WorldItem::Type(_) => false,
+            })
+    }
 
-impl EsmBindgen {
-    /// add an exported function binding, optionally on an interface id or kebab name
-    pub fn add_export_func(
+    /// Binds a canonical built-in taking and returning `i32`s as a regular
+    /// import function, returning the name of its JS binding
+    fn intrinsic_import_bindgen(
         &mut self,
-        iface_id_or_kebab: Option<&str>,
-        local_name: String,
-        func_name: String,
-    ) {
-        let mut iface = &mut self.exports;
-        if let Some(iface_id_or_kebab) = iface_id_or_kebab {
-            // convert kebab names to camel case, leave ids as-is
-            let iface_id_or_kebab = if iface_id_or_kebab.contains(':') {
-                iface_id_or_kebab.to_string()
-            } else {
-                iface_id_or_kebab.to_lower_camel_case()
+        module: &str,
+        name: &str,
+        nparams: usize,
+        ret: bool,
+    ) -> String {
+        let func = Function {
+            name: name.to_string(),
+            kind: FunctionKind::Freestanding,
+            params: (0..nparams)
+                .map(|i| (format!("arg{i}"), Type::U32))
+                .collect(),
+            result: ret.then_some(Type::U32),
+            docs: Default::default(),
+            stability: Default::default(),
+        };
+        let payloads = PayloadIntrinsics::new(self.resolve, module, &func);
+        self.import_bindgen(module.to_string(), &func, true, None, &payloads);
+        intrinsic_binding(name, module)
+    }
+
+    /// Binds the canonical built-ins used to await subtasks and other
+    /// waitables, along with the `$awaitEvent` and `$awaitSubtask` helpers
+    /// driving them.
+    ///
+    /// The helpers block on `waitable-set.wait` until the waitable delivers
+    /// the awaited event, with the event record in a buffer freed once it has
+    /// been read.
+    fn async_intrinsics_bindgen(&mut self) {
+        let set_new = self.intrinsic_import_bindgen("$root", "[waitable-set-new]", 0, true);
+        let join = self.intrinsic_import_bindgen("$root", "[waitable-join]", 2, false);
+        let set_wait = self.intrinsic_import_bindgen("$root", "[waitable-set-wait]", 2, true);
+        let set_drop = self.intrinsic_import_bindgen("$root", "[waitable-set-drop]", 1, false);
+        let subtask_drop = self.intrinsic_import_bindgen("$root", "[subtask-drop]", 1, false);
+
+        let memory = &self.memory;
+        let realloc = &self.realloc;
+        uwriteln!(
+            self.src,
+            "
+            const SUBTASK_DONE = 3;
+            const EVENT_STREAM_READ = 5;
+            const EVENT_STREAM_WRITE = 6;
+            const EVENT_FUTURE_READ = 7;
+            const EVENT_FUTURE_WRITE = 8;
+            async function $awaitEvent(waitable, code) {{
+                const set = {set_new}();
+                {join}(waitable, set);
+                const event = {realloc}(0, 0, 4, 8);
+                try {{
+                    while ({set_wait}(set, event) !== code ||
+                        new DataView({memory}.buffer).getInt32(event, true) !== waitable) {{}}
+                    return new DataView({memory}.buffer).getUint32(event + 4, true);
+                }} finally {{
+                    {realloc}.free(event);
+                    {join}(waitable, 0);
+                    {set_drop}(set);
+                }}
+            }}
+            async function $awaitSubtask(status) {{
+                if (status >>> 30 === SUBTASK_DONE) return;
+                const subtask = status & 0x3fffffff;
+                await $awaitEvent(subtask, SUBTASK_DONE);
+                {subtask_drop}(subtask);
+            }}
+            "
+        );
+    }
+
+    /// The functions of the world along with the module their canonical
+    /// built-ins are imported from
+    fn world_functions(&self) -> Vec<(String, &'a Function)> {
+        let resolve = self.resolve;
+        let world = &resolve.worlds[self.world];
+        let imports = world.imports.iter().map(|item| (false, item));
+        let exports = world.exports.iter().map(|item| (true, item));
+        let mut funcs = Vec::new();
+        for (export, (key, item)) in imports.chain(exports) {
+            let module = match (item, export) {
+                (WorldItem::Interface { .. }, false) => resolve.name_world_key(key),
+                (WorldItem::Interface { .. }, true) => {
+                    format!("[export]{}", resolve.name_world_key(key))
+                }
+                (_, false) => "$root".to_string(),
+                (_, true) => "[export]$root".to_string(),
             };
-            if !iface.contains_key(&iface_id_or_kebab) {
-                iface.insert(
-                    iface_id_or_kebab.to_string(),
-                    Binding::Interface(BTreeMap::new()),
-                );
-            }
-            iface = match iface.get_mut(&iface_id_or_kebab).unwrap() {
-                Binding::Interface(iface) => iface,
-                Binding::Resource(_) | Binding::Local(_) => panic!(
-                    "Exported interface {iface_id_or_kebab} cannot be both a function and an interface or resource"
+            match item {
+                WorldItem::Function(func) => funcs.push((module, func)),
+                WorldItem::Interface { id, .. } => funcs.extend(
+                    resolve.interfaces[*id]
+                        .functions
+                        .values()
+                        .map(|func| (module.clone(), func)),
                 ),
-            };
+                WorldItem::Type(_) => {}
+            }
         }
-        iface.insert(func_name, Binding::Local(local_name));
+        funcs
     }
 
-    pub fn ensure_exported_resource(
-        &mut self,
-        iface_id_or_kebab: Option<&str>,
-        local_name: String,
-        resource_name: String,
-    ) {
-        let mut iface = &mut self.exports;
-        if let Some(iface_id_or_kebab) = iface_id_or_kebab {
-            // convert kebab names to camel case, leave ids as-is
-            let iface_id_or_kebab = if iface_id_or_kebab.contains(':') {
-                iface_id_or_kebab.to_string()
-            } else {
-                iface_id_or_kebab.to_lower_camel_case()
-            };
-            if !iface.contains_key(&iface_id_or_kebab) {
-                iface.insert(
-                    iface_id_or_kebab.to_string(),
-                    Binding::Interface(BTreeMap::new()),
+    /// Binds the canonical built-ins for the futures and streams of the
+    /// world's functions, returning whether there were any
+    fn payload_intrinsics_bindgen(&mut self) -> bool {
+        let mut found = false;
+        for (module, func) in self.world_functions() {
+            let payloads = PayloadIntrinsics::new(self.resolve, &module, func);
+            for (idx, ty) in payloads.types.iter().enumerate() {
+                // types appearing more than once share the built-ins of the
+                // first occurrence
+                if payloads.types[..idx].contains(ty) {
+                    continue;
+                }
+                found = true;
+                let (kind, nparams) = match &self.resolve.types[*ty].kind {
+                    TypeDefKind::Stream(_) => ("stream", 3),
+                    TypeDefKind::Future(_) => ("future", 2),
+                    _ => unreachable!(),
+                };
+                for (prefix, intrinsic, nparams, ret) in [
+                    ("", "new", 0, true),
+                    ("[async-lower]", "read", nparams, true),
+                    ("[async-lower]", "write", nparams, true),
+                    ("", "close-readable", 2, false),
+                    ("", "close-writable", 2, false),
+                ] {
+                    let name = format!("{prefix}[{kind}-{intrinsic}-{idx}]{}", func.name);
+                    self.intrinsic_import_bindgen(&module, &name, nparams, ret);
+                }
+            }
+        }
+        found
+    }
+
+    /// Emits the helpers mapping streams to JS async iterables of chunks and
+    /// futures to promises.
+    ///
+    /// Chunks are `Uint8Array`s for `stream<u8>` and arrays of the lifted
+    /// values otherwise. Streams and futures passed out of the guest are
+    /// written from a task queued once the current call has taken their
+    /// handle. Payload buffers are freed as soon as they have been lifted or
+    /// written, rather than with the other allocations at the end of the call.
+    fn payload_helpers_bindgen(&mut self) {
+        let memory = &self.memory;
+        let realloc = &self.realloc;
+        uwriteln!(
+            self.src,
+            "
+            const BLOCKED = 0xffffffff;
+            const CLOSED = 0x80000000;
+            const STREAM_CHUNK_BYTES = 4096;
+            function $payloadBuffer(size, align, count) {{
+                return size ? {realloc}(0, 0, align, size * count) : 0;
+            }}
+            function $payloadFree(ptr) {{
+                if (ptr) {realloc}.free(ptr);
+            }}
+            function $streamReader(handle, read, closeReadable, size, align, liftElem) {{
+                const count = size ? Math.max(1, Math.floor(STREAM_CHUNK_BYTES / size)) : STREAM_CHUNK_BYTES;
+                return {{
+                    async *[Symbol.asyncIterator]() {{
+                        try {{
+                            while (true) {{
+                                const ptr = $payloadBuffer(size, align, count);
+                                let chunk;
+                                try {{
+                                    let result = read(handle, ptr, count);
+                                    if (result === BLOCKED) result = await $awaitEvent(handle, EVENT_STREAM_READ);
+                                    if (result === 0 || result === CLOSED) return;
+                                    if (result & CLOSED) throw new Error('stream closed with an error');
+                                    chunk = liftElem
+                                        ? Array.from({{ length: result }}, (_, i) => liftElem(ptr + i * size))
+                                        : new Uint8Array({memory}.buffer.slice(ptr, ptr + result));
+                                }} finally {{
+                                    $payloadFree(ptr);
+                                }}
+                                yield chunk;
+                            }}
+                        }} finally {{
+                            closeReadable(handle, 0);
+                        }}
+                    }}
+                }};
+            }}
+            function $streamWriter(chunks, newStream, write, closeWritable, size, align, lowerElem) {{
+                const handle = newStream();
+                if (ArrayBuffer.isView(chunks)) chunks = [chunks];
+                Promise.resolve().then(async () => {{
+                    try {{
+                        for await (const chunk of chunks) {{
+                            const bytes = lowerElem ? null : chunk instanceof Uint8Array ? chunk : new Uint8Array(chunk);
+                            const len = bytes ? bytes.byteLength : chunk.length;
+                            const ptr = $payloadBuffer(size, align, len);
+                            try {{
+                                if (bytes) new Uint8Array({memory}.buffer, ptr, len).set(bytes);
+                                else for (let i = 0; i < len; i++) lowerElem(chunk[i], ptr + i * size);
+                                let written = 0;
+                                while (written < len) {{
+                                    let result = write(handle, ptr + written * size, len - written);
+                                    if (result === BLOCKED) result = await $awaitEvent(handle, EVENT_STREAM_WRITE);
+                                    if (result === 0 || result & CLOSED) return;
+                                    written += result;
+                                }}
+                            }} finally {{
+                                $payloadFree(ptr);
+                            }}
+                        }}
+                    }} finally {{
+                        closeWritable(handle, 0);
+                    }}
+                }});
+                return handle;
+            }}
+            async function $futureReader(handle, read, closeReadable, size, align, liftElem) {{
+                const ptr = $payloadBuffer(size, align, 1);
+                try {{
+                    let result = read(handle, ptr);
+                    if (result === BLOCKED) result = await $awaitEvent(handle, EVENT_FUTURE_READ);
+                    closeReadable(handle, 0);
+                    if (result === 0 || result & CLOSED) throw new Error('future closed without a value');
+                    return liftElem(ptr);
+                }} finally {{
+                    $payloadFree(ptr);
+                }}
+            }}
+            function $futureWriter(value, newFuture, write, closeWritable, size, align, lowerElem) {{
+                const handle = newFuture();
+                Promise.resolve().then(async () => {{
+                    const ptr = $payloadBuffer(size, align, 1);
+                    try {{
+                        lowerElem(await value, ptr);
+                        if (write(handle, ptr) === BLOCKED) await $awaitEvent(handle, EVENT_FUTURE_WRITE);
+                    }} finally {{
+                        $payloadFree(ptr);
+                        closeWritable(handle, 0);
+                    }}
+                }});
+                return handle;
+            }}
+            "
+        );
+    }
+
+    /// Binds the `error-context` built-ins, with error contexts lifted as JS
+    /// errors carrying the debug message
+    fn error_context_bindgen(&mut self) {
+        let new = self.intrinsic_import_bindgen("$root", "[error-context-new-utf8]", 2, true);
+        let debug_message =
+            self.intrinsic_import_bindgen("$root", "[error-context-debug-message-utf8]", 2, false);
+        let drop = self.intrinsic_import_bindgen("$root", "[error-context-drop]", 1, false);
+        let utf8_decoder = self.intrinsic(Intrinsic::Utf8Decoder);
+        let utf8_encode = self.intrinsic(Intrinsic::Utf8Encode);
+        let utf8_encoded_len = self.intrinsic(Intrinsic::Utf8EncodedLen);
+
+        let memory = &self.memory;
+        let realloc = &self.realloc;
+        uwriteln!(
+            self.src,
+            "
+            function $liftErrorContext(handle) {{
+                const retptr = {realloc}(0, 0, 4, 8);
+                {debug_message}(handle, retptr);
+                const view = new DataView({memory}.buffer);
+                const ptr = view.getInt32(retptr, true);
+                const message = {utf8_decoder}.decode(new Uint8Array({memory}.buffer, ptr, view.getInt32(retptr + 4, true)));
+                {realloc}.free(ptr);
+                {realloc}.free(retptr);
+                {drop}(handle);
+                return new Error(message);
+            }}
+            function $lowerErrorContext(err) {{
+                const ptr = {utf8_encode}(String(err?.message ?? err), {realloc}, {memory});
+                return {new}(ptr, {utf8_encoded_len});
+            }}
+            "
+        );
+    }
+
+    fn uses_error_context(&self) -> bool {
+        self.world_functions().iter().any(|(_, func)| {
+            func.params
+                .iter()
+                .map(|(_, ty)| ty)
+                .chain(func.result.iter())
+                .any(|ty| has_error_context(self.resolve, ty))
+        })
+    }
+
+    /// Async imports are lowered with the async ABI, passing both the
+    /// parameters and the results through memory. The binding lowers the
+    /// arguments, awaits the subtask of the call and then lifts the results.
+    fn async_import_bindgen(
+        &mut self,
+        nparams: usize,
+        callee: &str,
+        string_encoding: StringEncoding,
+        func: &Function,
+        payloads: &PayloadIntrinsics,
+    ) {
+        self.src.push_str("(");
+        let mut params = Vec::new();
+        for i in 0..nparams {
+            if i > 0 {
+                self.src.push_str(", ");
+            }
+            let param = format!("arg{i}");
+            self.src.push_str(&param);
+            params.push(param);
+        }
+        uwriteln!(self.src, ") {{");
+
+        let resource_map = self.create_resource_map(func);
+
+        for (id, table) in &resource_map {
+            if table.imported {
+                self.imported_resources.insert(*id);
+            }
+        }
+
+        let params_info = self.sizes.record(func.params.iter().map(|(_, ty)| ty));
+        let param_offsets = self
+            .sizes
+            .field_offsets(func.params.iter().map(|(_, ty)| ty))
+            .into_iter()
+            .map(|(offset, ty)| (offset.size_wasm32(), *ty))
+            .collect::<Vec<_>>();
+        let results_info = self.sizes.record(func.result.iter());
+
+        let f = FunctionBindgen {
+            is_async: false,
+            tracing_prefix: None,
+            intrinsics: &mut self.all_intrinsics,
+            valid_lifting_optimization: true,
+            sizes: &self.sizes,
+            err: ErrHandling::None,
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            callee,
+            memory: Some(&self.memory),
+            realloc: Some(&self.realloc),
+            tmp: 0,
+            params: params.clone(),
+            post_return: None,
+            encoding: string_encoding,
+            src: Source::default(),
+            resource_map: &resource_map,
+            cur_resource_borrows: false,
+            resolve: self.resolve,
+            callee_resource_dynamic: false,
+        };
+        let mut f = PayloadBindgen { inner: f, payloads };
+
+        let realloc = &self.realloc;
+        if func.params.is_empty() {
+            uwriteln!(f.inner.src, "const asyncParams = 0;");
+        } else {
+            uwriteln!(
+                f.inner.src,
+                "const asyncParams = {realloc}(0, 0, {}, {});",
+                params_info.align.align_wasm32(),
+                params_info.size.size_wasm32()
+            );
+        }
+        if func.result.is_some() {
+            uwriteln!(
+                f.inner.src,
+                "const asyncResults = {realloc}(0, 0, {}, {});",
+                results_info.align.align_wasm32(),
+                results_info.size.size_wasm32()
+            );
+        } else {
+            uwriteln!(f.inner.src, "const asyncResults = 0;");
+        }
+        // the parameter and result areas are freed once the results have been
+        // lifted, as concurrent calls cannot share a scratch area
+        let areas = [
+            (!func.params.is_empty()).then_some("asyncParams"),
+            func.result.is_some().then_some("asyncResults"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        if !areas.is_empty() {
+            uwriteln!(f.inner.src, "try {{");
+        }
+        for ((offset, ty), param) in param_offsets.iter().zip(params) {
+            abi::lower_to_memory(
+                self.resolve,
+                &mut f,
+                format!("asyncParams + {offset}"),
+                param,
+                ty,
+            );
+        }
+        uwriteln!(
+            f.inner.src,
+            "await $awaitSubtask({callee}(asyncParams, asyncResults));"
+        );
+
+        if let Some(ty) = &func.result {
+            let ret = abi::lift_from_memory(self.resolve, &mut f, "asyncResults".into(), ty);
+            if get_result_types(self.resolve, func.result).is_some() {
+                f.inner.intrinsics.insert(Intrinsic::ComponentError);
+                let component_err = Intrinsic::ComponentError.name();
+                uwriteln!(
+                    f.inner.src,
+                    "const retVal = {ret};
+                    if (retVal.tag === 'err') {{
+                        throw new {component_err}(retVal.val);
+                    }}
+                    return retVal.val;"
                 );
+            } else {
+                uwriteln!(f.inner.src, "return {ret};");
+            }
+        }
+        if !areas.is_empty() {
+            uwriteln!(f.inner.src, "}} finally {{");
+            for area in areas {
+                uwriteln!(f.inner.src, "{realloc}.free({area});");
+            }
+            uwriteln!(f.inner.src, "}}");
+        }
+
+        let src = f.inner.src;
+        self.src.push_str(&src);
+        self.src.push_str("}");
+    }
+
+    /// The core signature of an async lowered import, taking pointers to the
+    /// parameters and results which are both allocated by the JS binding
+    fn async_import_core_fn(&self, func: &Function) -> CoreFn {
+        let sig = self
+            .resolve
+            .wasm_signature(AbiVariant::GuestImportAsync, func);
+        CoreFn {
+            retptr: false,
+            retsize: 0,
+            ..self.core_fn(func, &sig)
+        }
+    }
+
+    fn core_fn(&self, func: &Function, sig: &WasmSignature) -> CoreFn {
+        CoreFn {
+            retsize: if sig.retptr {
+                let mut retsize: u32 = 0;
+                if let Some(ret_ty) = func.result {
+                    retsize += self.sizes.size(&ret_ty).size_wasm32() as u32;
+                }
+                retsize
+            } else {
+                0
+            },
+            retptr: sig.retptr,
+            paramptr: sig.indirect_params,
+            params: sig
+                .params
+                .iter()
+                .map(|v| match v {
+                    WasmType::I32 => CoreTy::I32,
+                    WasmType::I64 => CoreTy::I64,
+                    WasmType::F32 => CoreTy::F32,
+                    WasmType::F64 => CoreTy::F64,
+                    WasmType::PointerOrI64 => CoreTy::I64,
+                    WasmType::Pointer => CoreTy::I32,
+                    WasmType::Length => CoreTy::I32,
+                })
+                .collect(),
+            ret: match sig.results.first() {
+                None => None,
+                Some(WasmType::I32) => Some(CoreTy::I32),
+                Some(WasmType::I64) => Some(CoreTy::I64),
+                Some(WasmType::F32) => Some(CoreTy::F32),
+                Some(WasmType::F64) => Some(CoreTy::F64),
+                Some(WasmType::PointerOrI64) => Some(CoreTy::I64),
+                Some(WasmType::Pointer) => Some(CoreTy::I32),
+                Some(WasmType::Length) => Some(CoreTy::I32),
+            },
+        }
+    }
+}
+
+/// The future and stream types of a function, by the index used to name
+/// their canonical built-ins (such as `[stream-new-N]func`)
+struct PayloadIntrinsics {
+    module: String,
+    func_name: String,
+    types: Vec<TypeId>,
+}
+
+impl PayloadIntrinsics {
+    fn new(resolve: &Resolve, module: &str, func: &Function) -> Self {
+        Self {
+            module: module.to_string(),
+            func_name: func.name.clone(),
+            types: func.find_futures_and_streams(resolve),
+        }
+    }
+
+    /// The JS binding of the given built-in for a future or stream type
+    fn binding(&self, prefix: &str, intrinsic: &str, ty: TypeId) -> String {
+        let idx = self.types.iter().position(|t| *t == ty).unwrap();
+        intrinsic_binding(
+            &format!("{prefix}[{intrinsic}-{idx}]{}", self.func_name),
+            &self.module,
+        )
+    }
+}
+
+/// Function bindgen extended with the lifting and lowering of futures, streams,
+/// error contexts and compact UTF-16 strings, which js-component-bindgen does
+/// not yet implement
+struct PayloadBindgen<'a, 'b> {
+    inner: FunctionBindgen<'a>,
+    payloads: &'b PayloadIntrinsics,
+}
+
+impl PayloadBindgen<'_, '_> {
+    fn tmp(&mut self) -> usize {
+        let ret = self.inner.tmp;
+        self.inner.tmp += 1;
+        ret
+    }
+
+    fn payload_layout(&self, payload: &Option<Type>) -> (usize, usize) {
+        match payload {
+            Some(ty) => (
+                self.inner.sizes.size(ty).size_wasm32(),
+                self.inner.sizes.align(ty).align_wasm32(),
+            ),
+            None => (0, 1),
+        }
+    }
+
+    /// A JS function lifting a single payload value from memory
+    fn lift_elem(&mut self, resolve: &Resolve, payload: &Option<Type>) -> String {
+        let Some(ty) = payload else {
+            return "() => undefined".to_string();
+        };
+        let prev = mem::take(&mut self.inner.src);
+        let val = abi::lift_from_memory(resolve, self, "elemPtr".to_string(), ty);
+        let body = mem::replace(&mut self.inner.src, prev);
+        format!("elemPtr => {{\n{}return {val};\n}}", &body as &str)
+    }
+
+    /// A JS function lowering a single payload value into memory
+    fn lower_elem(&mut self, resolve: &Resolve, payload: &Option<Type>) -> String {
+        let Some(ty) = payload else {
+            return "() => {}".to_string();
+        };
+        let prev = mem::take(&mut self.inner.src);
+        abi::lower_to_memory(
+            resolve,
+            self,
+            "elemPtr".to_string(),
+            "elemValue".to_string(),
+            ty,
+        );
+        let body = mem::replace(&mut self.inner.src, prev);
+        format!("(elemValue, elemPtr) => {{\n{}}}", &body as &str)
+    }
+}
+
+impl Bindgen for PayloadBindgen<'_, '_> {
+    type Operand = String;
+
+    fn emit(
+        &mut self,
+        resolve: &Resolve,
+        inst: &Instruction<'_>,
+        operands: &mut Vec<String>,
+        results: &mut Vec<String>,
+    ) {
+        let (helper, kind, args) = match inst {
+            Instruction::StreamLift { payload, ty } => {
+                let (size, align) = self.payload_layout(payload);
+                // byte streams are lifted directly into Uint8Arrays
+                let lift = if let Some(Type::U8) = payload {
+                    "null".to_string()
+                } else {
+                    self.lift_elem(resolve, payload)
+                };
+                let read = self.payloads.binding("[async-lower]", "stream-read", *ty);
+                let close = self.payloads.binding("", "stream-close-readable", *ty);
+                (
+                    "$streamReader",
+                    "stream",
+                    format!("{read}, {close}, {size}, {align}, {lift}"),
+                )
+            }
+            Instruction::StreamLower { payload, ty } => {
+                let (size, align) = self.payload_layout(payload);
+                let lower = if let Some(Type::U8) = payload {
+                    "null".to_string()
+                } else {
+                    self.lower_elem(resolve, payload)
+                };
+                let new = self.payloads.binding("", "stream-new", *ty);
+                let write = self.payloads.binding("[async-lower]", "stream-write", *ty);
+                let close = self.payloads.binding("", "stream-close-writable", *ty);
+                (
+                    "$streamWriter",
+                    "handle",
+                    format!("{new}, {write}, {close}, {size}, {align}, {lower}"),
+                )
+            }
+            Instruction::FutureLift { payload, ty } => {
+                let (size, align) = self.payload_layout(payload);
+                let lift = self.lift_elem(resolve, payload);
+                let read = self.payloads.binding("[async-lower]", "future-read", *ty);
+                let close = self.payloads.binding("", "future-close-readable", *ty);
+                (
+                    "$futureReader",
+                    "future",
+                    format!("{read}, {close}, {size}, {align}, {lift}"),
+                )
+            }
+            Instruction::FutureLower { payload, ty } => {
+                let (size, align) = self.payload_layout(payload);
+                let lower = self.lower_elem(resolve, payload);
+                let new = self.payloads.binding("", "future-new", *ty);
+                let write = self.payloads.binding("[async-lower]", "future-write", *ty);
+                let close = self.payloads.binding("", "future-close-writable", *ty);
+                (
+                    "$futureWriter",
+                    "handle",
+                    format!("{new}, {write}, {close}, {size}, {align}, {lower}"),
+                )
+            }
+            Instruction::StringLower { .. }
+                if self.inner.encoding == StringEncoding::CompactUTF16 =>
+            {
+                let tmp = self.tmp();
+                let memory = self.inner.memory.unwrap();
+                let realloc = self.inner.realloc.unwrap();
+                uwriteln!(
+                    self.inner.src,
+                    "var ptr{tmp} = $compactUtf16Encode({}, {realloc}, {memory});
+                    var len{tmp} = $compactUtf16EncodedLen;",
+                    operands[0]
+                );
+                results.push(format!("ptr{tmp}"));
+                results.push(format!("len{tmp}"));
+                return;
+            }
+            Instruction::StringLift if self.inner.encoding == StringEncoding::CompactUTF16 => {
+                let tmp = self.tmp();
+                let memory = self.inner.memory.unwrap();
+                uwriteln!(
+                    self.inner.src,
+                    "var result{tmp} = $compactUtf16Decode({}, {}, {memory});",
+                    operands[0],
+                    operands[1]
+                );
+                results.push(format!("result{tmp}"));
+                return;
+            }
+            Instruction::ErrorContextLift => ("$liftErrorContext", "err", String::new()),
+            Instruction::ErrorContextLower => ("$lowerErrorContext", "handle", String::new()),
+            _ => return self.inner.emit(resolve, inst, operands, results),
+        };
+        let tmp = self.tmp();
+        let op = &operands[0];
+        let args = if args.is_empty() {
+            op.to_string()
+        } else {
+            format!("{op}, {args}")
+        };
+        uwriteln!(self.inner.src, "var {kind}{tmp} = {helper}({args});");
+        results.push(format!("{kind}{tmp}"));
+    }
+
+    fn return_pointer(&mut self, size: ArchitectureSize, align: Alignment) -> String {
+        self.inner.return_pointer(size, align)
+    }
+
+    fn push_block(&mut self) {
+        self.inner.push_block()
+    }
+
+    fn finish_block(&mut self, operands: &mut Vec<String>) {
+        self.inner.finish_block(operands)
+    }
+
+    fn sizes(&self) -> &SizeAlign {
+        self.inner.sizes()
+    }
+
+    fn is_list_canonical(&self, resolve: &Resolve, element: &Type) -> bool {
+        self.inner.is_list_canonical(resolve, element)
+    }
+}
+
+type LocalName = String;
+
+/// An export of the world which cannot be bound to the guest module
+#[derive(Debug)]
+pub struct ExportError {
+    /// The name of the export, as the guest module would define it
+    pub name: String,
+    pub message: String,
+}
+
+impl ExportError {
+    /// Resources exported directly by the world have no interface to bind
+    /// their class and handle intrinsics to
+    fn world_resource(name: &str) -> Self {
+        ExportError {
+            name: name.to_string(),
+            message: "resources can only be exported from an interface".to_string(),
+        }
+    }
+
+    fn collision(iface_id_or_kebab: Option<&str>, name: &str) -> Self {
+        ExportError {
+            name: match iface_id_or_kebab {
+                Some(iface) => format!("{iface}#{name}"),
+                None => name.to_string(),
+            },
+            message: "the name is used by more than one function, resource or interface export"
+                .to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unable to bind the export \"{}\": {}",
+            self.name, self.message
+        )
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+#[derive(Debug)]
+enum Binding {
+    Interface(BTreeMap<String, Binding>),
+    Resource(LocalName),
+    Local(LocalName),
+}
+
+#[derive(Default)]
+struct EsmBindgen {
+    exports: BTreeMap<String, Binding>,
+    export_aliases: BTreeMap<String, String>,
+}
+
+impl EsmBindgen {
+    /// add an exported function binding, optionally on an interface id or kebab name
+    pub fn add_export_func(
+        &mut self,
+        iface_id_or_kebab: Option<&str>,
+        local_name: String,
+        func_name: String,
+    ) -> Result<(), ExportError> {
+        let iface = self.export_bindings(iface_id_or_kebab)?;
+        if iface.contains_key(&func_name) {
+            return Err(ExportError::collision(iface_id_or_kebab, &func_name));
+        }
+        iface.insert(func_name, Binding::Local(local_name));
+        Ok(())
+    }
+
+    pub fn ensure_exported_resource(
+        &mut self,
+        iface_id_or_kebab: Option<&str>,
+        local_name: String,
+        resource_name: String,
+    ) -> Result<(), ExportError> {
+        let iface = self.export_bindings(iface_id_or_kebab)?;
+        match iface.get(&resource_name) {
+            None | Some(Binding::Resource(_)) => {
+                iface.insert(resource_name, Binding::Resource(local_name));
+                Ok(())
+            }
+            Some(_) => Err(ExportError::collision(iface_id_or_kebab, &resource_name)),
+        }
+    }
+
+    /// The bindings of an exported interface, or the top-level bindings
+    fn export_bindings(
+        &mut self,
+        iface_id_or_kebab: Option<&str>,
+    ) -> Result<&mut BTreeMap<String, Binding>, ExportError> {
+        let Some(iface_id_or_kebab) = iface_id_or_kebab else {
+            return Ok(&mut self.exports);
+        };
+        // convert kebab names to camel case, leave ids as-is
+        let iface_id_or_kebab = if iface_id_or_kebab.contains(':') {
+            iface_id_or_kebab.to_string()
+        } else {
+            iface_id_or_kebab.to_lower_camel_case()
+        };
+        match self
+            .exports
+            .entry(iface_id_or_kebab.clone())
+            .or_insert_with(|| Binding::Interface(BTreeMap::new()))
+        {
+            Binding::Interface(iface) => Ok(iface),
+            Binding::Resource(_) | Binding::Local(_) => {
+                Err(ExportError::collision(None, &iface_id_or_kebab))
             }
-            iface = match iface.get_mut(&iface_id_or_kebab).unwrap() {
-                Binding::Interface(iface) => iface,
-                Binding::Resource(_) | Binding::Local(_) => panic!(
-                    "Exported interface {iface_id_or_kebab} cannot be both a function and an interface or resource"
-                ),
-            };
         }
-        iface.insert(resource_name, Binding::Resource(local_name));
     }
 
     /// once all exports have been created, aliases can be populated for interface
@@ -1129,6 +2337,58 @@ impl EsmBindgen {
         }
     }
 
+    /// Checks the static export names of the guest module against the exports
+    /// bound by the world, returning the same errors as the generated
+    /// `bindExports` would for those which are missing. Only the interface
+    /// objects themselves can be checked, not the functions they define.
+    pub fn missing_exports(&self, export_names: &[String]) -> Vec<String> {
+        let exported = |name: &str| export_names.iter().any(|export| export == name);
+        let mut missing = Vec::new();
+        for (export_name, binding) in &self.exports {
+            match binding {
+                Binding::Interface(_) => {
+                    let alias = self.export_aliases.get(export_name);
+                    if exported(export_name) || alias.is_some_and(|alias| exported(alias)) {
+                        continue;
+                    }
+                    missing.push(if let Some(alias) = alias {
+                        bindings_error(
+                            alias,
+                            "interface",
+                            " by its alias",
+                            &format!("export const {alias} = {{}};"),
+                        )
+                    } else if export_name.contains(':') {
+                        bindings_error(
+                            export_name,
+                            "interface",
+                            " by its qualified interface name",
+                            &format!("const obj = {{}};\n\nexport {{ obj as '{export_name}' }}\n"),
+                        )
+                    } else {
+                        bindings_error(
+                            export_name,
+                            "interface",
+                            " by name",
+                            &format!("export const {export_name} = {{}};"),
+                        )
+                    });
+                }
+                Binding::Resource(_) | Binding::Local(_) => {
+                    if !exported(export_name) {
+                        missing.push(bindings_error(
+                            export_name,
+                            "function",
+                            "",
+                            &format!("export function {export_name}() {{}};\n"),
+                        ));
+                    }
+                }
+            }
+        }
+        missing
+    }
+
     pub fn render_export_imports(
         &mut self,
         output: &mut Source,
@@ -1152,7 +2412,7 @@ impl EsmBindgen {
                     if (exportNameOrAlias && typeof mod[exportNameOrAlias] === 'object')
                         return mod[exportNameOrAlias];
                     if (!exportNameOrAlias)
-                        throw new BindingsError(exportId, 'interface', ' by its qualified interface name', `const obj = {{}};\n\nexport {{ obj as '${{exportId}}' }}\n`);
+                        throw new BindingsError(exportId, 'interface', ' by its qualified interface name', `const obj = {{}};\\n\\nexport {{ obj as '${{exportId}}' }}\\n`);
                     else
                         throw new BindingsError(exportNameOrAlias, 'interface', exportId && exportNameOrAlias ? ' by its alias' : ' by name', `export const ${{exportNameOrAlias}} = {{}};`);
                 }}
@@ -1216,7 +2476,9 @@ impl EsmBindgen {
                             output.push_str(", ");
                         }
                         let local_name = match import {
-                            Binding::Interface(_) => panic!("Nested interfaces unsupported"),
+                            Binding::Interface(_) => {
+                                unreachable!("interfaces are only exported at the top level")
+                            }
                             Binding::Resource(local_name) | Binding::Local(local_name) => {
                                 local_name
                             }
@@ -1228,7 +2490,9 @@ impl EsmBindgen {
                     // After defining all the local bindings, verify them throwing errors as necessary
                     for (external_name, import) in bindings {
                         let local_name = match import {
-                            Binding::Interface(_) => panic!("Nested interfaces unsupported"),
+                            Binding::Interface(_) => {
+                                unreachable!("interfaces are only exported at the top level")
+                            }
                             Binding::Resource(local_name) | Binding::Local(local_name) => {
                                 local_name
                             }
@@ -1256,7 +2520,7 @@ impl EsmBindgen {
                     uwriteln!(bind_exports, "
                         {local_name} = {imports_object}.{export_name};
                         if (typeof {local_name} !== 'function')
-                            throw new BindingsError('{export_name}', 'function', '', `export function {export_name} () {{}};\n`);
+                            throw new BindingsError('{export_name}', 'function', '', `export function {export_name}() {{}};\\n`);
                     ");
                 }
                 Binding::Local(local_name) => {
@@ -1269,7 +2533,7 @@ impl EsmBindgen {
                     uwriteln!(bind_exports, "
                         {local_name} = {imports_object}.{export_name};
                         if (typeof {local_name} !== 'function')
-                            throw new BindingsError('{export_name}', 'function', '', `export function {export_name} () {{}};\n`);
+                            throw new BindingsError('{export_name}', 'function', '', `export function {export_name}() {{}};\\n`);
                     ");
                 }
             }
@@ -1279,10 +2543,39 @@ impl EsmBindgen {
     }
 }
 
+/// The message of a `BindingsError` thrown by `bindExports`, following the
+/// name of the guest module
+fn bindings_error(path: &str, ty: &str, help_context: &str, help: &str) -> String {
+    let help = help
+        .split('\n')
+        .map(|line| format!("  {line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "does not export a \"{path}\" {ty} as expected by the world.\n  Try defining it{help_context}:\n{help}"
+    )
+}
+
 fn interface_name(resolve: &Resolve, interface: InterfaceId) -> Option<String> {
     interface_name_from_string(&resolve.id_of(interface)?)
 }
 
+/// The interface name prefixed by its package, for interfaces which share
+/// their name with another interface of the world
+fn qualified_interface_name(resolve: &Resolve, interface: InterfaceId, iface_name: &str) -> String {
+    match resolve.interfaces[interface].package {
+        Some(package) => {
+            let package = &resolve.packages[package].name;
+            format!(
+                "{}_{}_{iface_name}",
+                package.namespace.to_lower_camel_case(),
+                package.name.to_lower_camel_case()
+            )
+        }
+        None => iface_name.to_string(),
+    }
+}
+
 fn interface_name_from_string(name: &str) -> Option<String> {
     let path_idx = name.rfind('/')?;
     let name = &name[path_idx + 1..];
@@ -1337,6 +2630,88 @@ fn generate_binding_name_import(
     }
 }
 
+/// The name of a world item as keyed by string encodings, which applies
+/// regardless of the interface version
+pub fn encoding_key(name: &str) -> &str {
+    let name = name.strip_prefix("[export]").unwrap_or(name);
+    match name.split_once('@') {
+        Some((name, _)) => name,
+        None => name,
+    }
+}
+
+/// The JS binding name of a canonical built-in bound as an import
+fn intrinsic_binding(name: &str, module: &str) -> String {
+    format!(
+        "import_{}",
+        generate_binding_name_import(&name.to_lower_camel_case(), &None, module)
+    )
+}
+
+/// Collects the resources of the handles within a type
+fn type_resources(resolve: &Resolve, ty: &Type, resources: &mut Vec<TypeId>) {
+    let Type::Id(id) = ty else { return };
+    match &resolve.types[*id].kind {
+        TypeDefKind::Handle(Handle::Own(t) | Handle::Borrow(t)) => {
+            resources.push(js_component_bindgen::dealias(resolve, *t));
+        }
+        TypeDefKind::Record(r) => {
+            for field in &r.fields {
+                type_resources(resolve, &field.ty, resources);
+            }
+        }
+        TypeDefKind::Tuple(t) => {
+            for ty in &t.types {
+                type_resources(resolve, ty, resources);
+            }
+        }
+        TypeDefKind::Variant(v) => {
+            for ty in v.cases.iter().filter_map(|c| c.ty.as_ref()) {
+                type_resources(resolve, ty, resources);
+            }
+        }
+        TypeDefKind::Result(r) => {
+            for ty in r.ok.iter().chain(r.err.iter()) {
+                type_resources(resolve, ty, resources);
+            }
+        }
+        TypeDefKind::Option(ty) | TypeDefKind::List(ty) | TypeDefKind::Type(ty) => {
+            type_resources(resolve, ty, resources)
+        }
+        TypeDefKind::Future(Some(ty)) | TypeDefKind::Stream(Some(ty)) => {
+            type_resources(resolve, ty, resources)
+        }
+        _ => {}
+    }
+}
+
+fn has_error_context(resolve: &Resolve, ty: &Type) -> bool {
+    let Type::Id(id) = ty else {
+        return matches!(ty, Type::ErrorContext);
+    };
+    match &resolve.types[*id].kind {
+        TypeDefKind::Record(r) => r.fields.iter().any(|f| has_error_context(resolve, &f.ty)),
+        TypeDefKind::Tuple(t) => t.types.iter().any(|ty| has_error_context(resolve, ty)),
+        TypeDefKind::Variant(v) => v
+            .cases
+            .iter()
+            .filter_map(|c| c.ty.as_ref())
+            .any(|ty| has_error_context(resolve, ty)),
+        TypeDefKind::Result(r) => {
+            r.ok.iter()
+                .chain(r.err.iter())
+                .any(|ty| has_error_context(resolve, ty))
+        }
+        TypeDefKind::Option(ty) | TypeDefKind::List(ty) | TypeDefKind::Type(ty) => {
+            has_error_context(resolve, ty)
+        }
+        TypeDefKind::Future(ty) | TypeDefKind::Stream(ty) => {
+            ty.as_ref().is_some_and(|ty| has_error_context(resolve, ty))
+        }
+        _ => false,
+    }
+}
+
 /// Extract success and error types from a given optional type, if it is a Result
 pub fn get_result_types(
     resolve: &Resolve,
@@ -1351,3 +2726,362 @@ pub fn get_result_types(
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_bindings(wit: &str) -> Result<Componentization> {
+        bindings_with(wit, "w", StringEncoding::UTF8, None)
+    }
+
+    /// Binds the world of the given name, with a world named `engine` in the
+    /// same package standing for the engine world when there is one
+    fn bindings_with(
+        wit: &str,
+        world: &str,
+        string_encoding: StringEncoding,
+        export_names: Option<&[String]>,
+    ) -> Result<Componentization> {
+        let mut resolve = Resolve::default();
+        let pkg = resolve.push_str("world.wit", wit).unwrap();
+        let world = resolve.select_world(pkg, Some(world)).unwrap();
+        let engine_world = resolve.select_world(pkg, Some("engine")).unwrap_or(world);
+        componentize_bindgen(
+            &resolve,
+            world,
+            engine_world,
+            &vec![],
+            string_encoding,
+            export_names,
+            None,
+        )
+    }
+
+    #[test]
+    fn same_named_interfaces_are_bound_separately() {
+        let bindings = world_bindings(
+            "package a:x;
+            interface foo { hello: func(); }
+            world w {
+              export foo;
+              export b:y/foo;
+            }
+            package b:y {
+              interface foo { hello: func(); }
+            }",
+        )
+        .unwrap();
+        let js = &bindings.js_bindings;
+        assert!(js.contains("function export_foo$hello("));
+        assert!(js.contains("function export_b_y_foo$hello("));
+        assert!(js.contains("getInterfaceExport($source_mod, 'foo', 'a:x/foo');"));
+        assert!(js.contains("getInterfaceExport($source_mod, null, 'b:y/foo');"));
+    }
+
+    #[test]
+    fn colliding_export_names_are_unsupported() {
+        let err = world_bindings(
+            "package a:x;
+            world w {
+              export x-y: func();
+              export x-Y: interface { f: func(); }
+            }",
+        )
+        .unwrap_err()
+        .downcast::<ExportError>()
+        .unwrap();
+        assert_eq!(err.name, "xY");
+        assert_eq!(
+            err.message,
+            "the name is used by more than one function, resource or interface export"
+        );
+    }
+
+    #[test]
+    fn encoding_keys_ignore_the_export_prefix_and_version() {
+        assert_eq!(
+            encoding_key("[export]wasi:http/handler@0.2.3"),
+            "wasi:http/handler"
+        );
+        assert_eq!(encoding_key("wasi:cli/stdout@0.2.3"), "wasi:cli/stdout");
+        assert_eq!(encoding_key("run"), "run");
+    }
+
+    #[test]
+    fn items_shared_with_the_engine_are_bound_as_utf8() {
+        let wit = "package a:x;
+            interface log { log: func(msg: string); }
+            interface names { name: func(id: u32) -> string; }
+            world engine {
+              import log;
+            }
+            world w {
+              import log;
+              import names;
+            }";
+        let js = bindings_with(wit, "w", StringEncoding::UTF16, None)
+            .unwrap()
+            .js_bindings;
+        assert!(js.contains(
+            "function import_a_x_log$log(arg0) {
+  var ptr0 = utf8Encode(arg0, $realloc, $memory);"
+        ));
+        assert!(js.contains(
+            "var result0 = utf16Decoder.decode(new Uint16Array($memory.buffer, ptr0, len0));"
+        ));
+    }
+
+    #[test]
+    fn missing_exports_are_reported() {
+        let wit = "package a:x;
+            interface api { call: func(); }
+            world w {
+              export run: func();
+              export api;
+            }";
+        let missing = |names: &[&str]| {
+            let names = names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>();
+            bindings_with(wit, "w", StringEncoding::UTF8, Some(&names))
+                .unwrap()
+                .missing_exports
+        };
+        assert!(missing(&["run", "api"]).is_empty());
+        assert!(missing(&["run", "a:x/api"]).is_empty());
+        let run = missing(&["api"]);
+        assert_eq!(run.len(), 1);
+        assert!(run[0].starts_with("does not export a \"run\" function"));
+        let api = missing(&["run"]);
+        assert_eq!(api.len(), 1);
+        assert!(api[0].starts_with("does not export a \"api\" interface"));
+    }
+
+    #[test]
+    fn missing_exports_match_the_runtime_errors() {
+        let wit = "package a:x;
+            interface api { call: func(); }
+            world w {
+              export run: func();
+              export api;
+              export b:y/api;
+            }
+            package b:y {
+              interface api { call: func(); }
+            }";
+        let bindings = bindings_with(wit, "w", StringEncoding::UTF8, Some(&[])).unwrap();
+
+        // the runtime BindingsError indents each line of the help after the
+        // message, as bindings_error does
+        let js = &bindings.js_bindings;
+        assert!(js.contains(
+            "throw new BindingsError('run', 'function', '', `export function run() {};\\n`);"
+        ));
+        assert!(js.contains(
+            "throw new BindingsError(exportId, 'interface', ' by its qualified interface name', \
+             `const obj = {};\\n\\nexport { obj as '${exportId}' }\\n`);"
+        ));
+        let missing = &bindings.missing_exports;
+        assert!(missing.iter().any(|err| err
+            == "does not export a \"run\" function as expected by the world.\n  \
+                Try defining it:\n  export function run() {};\n  "));
+        assert!(missing.iter().any(|err| err
+            == "does not export a \"b:y/api\" interface as expected by the world.\n  \
+                Try defining it by its qualified interface name:\n  const obj = {};\n  \n  \
+                export { obj as 'b:y/api' }\n  "));
+    }
+
+    #[test]
+    fn async_exports_return_through_task_return() {
+        let bindings = world_bindings(
+            "package a:x;
+            world w {
+              export compute: async func(x: u32) -> u32;
+            }",
+        )
+        .unwrap();
+        let js = &bindings.js_bindings;
+        assert!(js.contains(
+            "async function export_compute$task(...args) {
+  import__export__root$taskReturnAsyncCompute(await asyncCompute(...args));
+}"
+        ));
+        assert!(js.contains("asyncCompute = $source_mod.compute;"));
+        let (_, compute) = &bindings.exports[0];
+        assert!(compute.is_async);
+        assert!(bindings
+            .imports
+            .iter()
+            .any(|(_, item)| item.name == "[task-return][async]compute"));
+    }
+
+    #[test]
+    fn async_imports_return_promises() {
+        let bindings = world_bindings(
+            "package a:x;
+            world w {
+              import double: async func(x: u32) -> u32;
+              export compute: async func(x: u32) -> u32;
+            }",
+        )
+        .unwrap();
+        let js = &bindings.js_bindings;
+        assert!(js.contains("async function import_double$double(arg0) {"));
+        assert!(
+            js.contains("await $awaitSubtask($import_double$double(asyncParams, asyncResults));")
+        );
+        let (_, double) = bindings
+            .imports
+            .iter()
+            .find(|(_, item)| item.name == "double")
+            .unwrap();
+        assert!(double.is_async);
+        assert!(double.func.paramptr);
+        assert!(matches!(double.func.ret, Some(CoreTy::I32)));
+    }
+
+    fn import_names(bindings: &Componentization) -> Vec<&str> {
+        bindings
+            .imports
+            .iter()
+            .map(|(_, item)| item.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn streams_and_futures_are_bound_to_the_payload_helpers() {
+        let bindings = world_bindings(
+            "package a:x;
+            world w {
+              import bytes: async func() -> stream<u8>;
+              import check: func(s: stream<u8>) -> future<u32>;
+            }",
+        )
+        .unwrap();
+        let names = import_names(&bindings);
+        for name in [
+            "[stream-new-0][async]bytes",
+            "[async-lower][stream-read-0][async]bytes",
+            "[stream-close-readable-0][async]bytes",
+            "[async-lower][stream-write-0]check",
+            "[async-lower][future-read-1]check",
+            "[waitable-set-wait]",
+        ] {
+            assert!(names.contains(&name), "{name}");
+        }
+        assert!(!names.contains(&"[waitable-set-poll]"));
+
+        let js = &bindings.js_bindings;
+        assert!(js.contains(
+            "$streamReader(dataView($memory).getInt32(asyncResults + 0, true), \
+             import__root$asyncLowerStreamRead0AsyncBytes, \
+             import__root$streamCloseReadable0AsyncBytes, 1, 1, null);"
+        ));
+        assert!(js.contains(
+            "$streamWriter(arg0, import__root$streamNew0Check, \
+             import__root$asyncLowerStreamWrite0Check, import__root$streamCloseWritable0Check, \
+             1, 1, null);"
+        ));
+        assert!(js.contains(
+            "$futureReader(ret, import__root$asyncLowerFutureRead1Check, \
+             import__root$futureCloseReadable1Check, 4, 4, elemPtr => {"
+        ));
+        // the event buffer is freed once the awaited event has been read
+        assert!(js.contains("while (import__root$waitableSetWait(set, event) !== code ||"));
+        assert!(js.contains("$realloc.free(event);"));
+    }
+
+    #[test]
+    fn error_contexts_are_bound_as_errors() {
+        let bindings = world_bindings(
+            "package a:x;
+            world w {
+              import check: func() -> result<_, error-context>;
+              export run: func() -> result<_, error-context>;
+            }",
+        )
+        .unwrap();
+        let names = import_names(&bindings);
+        for name in [
+            "[error-context-new-utf8]",
+            "[error-context-debug-message-utf8]",
+            "[error-context-drop]",
+        ] {
+            assert!(names.contains(&name), "{name}");
+        }
+
+        let js = &bindings.js_bindings;
+        assert!(js.contains("$liftErrorContext(dataView($memory).getInt32("));
+        assert!(js.contains("$lowerErrorContext("));
+        // both the return area and the debug message are freed once decoded
+        assert!(js.contains("$realloc.free(ptr);\n  $realloc.free(retptr);"));
+    }
+
+    /// The imported resources bound for the guest imports, by their drop
+    /// intrinsics
+    fn bound_resources(used_imports: Option<&[(&str, &str)]>) -> Vec<String> {
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push_str(
+                "world.wit",
+                "package a:x;
+                interface fs {
+                  resource dir { open: func(name: string) -> file; }
+                  resource file { read: func() -> list<u8>; }
+                  resource lock { constructor(); }
+                  resource unused;
+                  root: func() -> dir;
+                }
+                world w {
+                  import fs;
+                }",
+            )
+            .unwrap();
+        let world = resolve.select_world(pkg, Some("w")).unwrap();
+        let used_imports = used_imports.map(|used| {
+            used.iter()
+                .map(|(specifier, name)| (specifier.to_string(), name.to_string()))
+                .collect::<Vec<_>>()
+        });
+        componentize_bindgen(
+            &resolve,
+            world,
+            world,
+            &vec![],
+            StringEncoding::UTF8,
+            None,
+            used_imports.as_deref(),
+        )
+        .unwrap()
+        .resource_imports
+        .into_iter()
+        .map(|(_, name, _)| name)
+        .filter(|name| name.starts_with("[resource-drop]"))
+        .collect()
+    }
+
+    #[test]
+    fn unreachable_resources_are_pruned() {
+        let all = [
+            "[resource-drop]dir",
+            "[resource-drop]file",
+            "[resource-drop]lock",
+        ];
+        // resources without a constructor or static function are only
+        // reachable through the types of other functions
+        assert_eq!(bound_resources(None), all);
+        assert_eq!(bound_resources(Some(&[("a:x/fs", "*")])), all);
+        assert!(bound_resources(Some(&[])).is_empty());
+        // the methods of reachable resources are traced as well
+        assert_eq!(
+            bound_resources(Some(&[("a:x/fs", "root")])),
+            ["[resource-drop]dir", "[resource-drop]file"]
+        );
+        assert_eq!(
+            bound_resources(Some(&[("a:x/fs", "Lock")])),
+            ["[resource-drop]lock"]
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::BTreeSet;
+
+use wasmparser::{ExternalKind, Operator, TypeRef};
+use wirm::ir::id::{FunctionID, GlobalID};
+use wirm::ir::module::module_functions::FuncKind;
+use wirm::ir::module::module_globals::GlobalKind;
+use wirm::ir::types::{DataSegmentKind, ElementItems, ElementKind, InitExpr, InitInstr};
+use wirm::Module;
+
+/// Items of the module reachable from its exports
+#[derive(Default)]
+struct Reachable {
+    functions: BTreeSet<u32>,
+    globals: BTreeSet<u32>,
+    tables: BTreeSet<u32>,
+    elements: BTreeSet<u32>,
+    data: BTreeSet<u32>,
+    pending: Vec<u32>,
+}
+
+impl Reachable {
+    fn function(&mut self, fid: u32) {
+        if self.functions.insert(fid) {
+            self.pending.push(fid);
+        }
+    }
+
+    fn init_expr(&mut self, module: &Module, expr: &InitExpr) {
+        for instr in expr.instructions() {
+            match instr {
+                InitInstr::RefFunc(fid) => self.function(**fid),
+                InitInstr::Global(gid) => self.global(module, **gid),
+                _ => {}
+            }
+        }
+    }
+
+    fn global(&mut self, module: &Module, gid: u32) {
+        if !self.globals.insert(gid) {
+            return;
+        }
+        if let GlobalKind::Local(global) = module.globals.get_kind(GlobalID(gid)) {
+            self.init_expr(module, &global.init_expr);
+        }
+    }
+
+    /// Marks the table as used, along with the functions its active element
+    /// segments place in it
+    fn table(&mut self, module: &Module, table: u32) {
+        if !self.tables.insert(table) {
+            return;
+        }
+        for (index, element) in module.elements.iter().enumerate() {
+            if let ElementKind::Active { table_index, .. } = &element.kind {
+                if table_index.unwrap_or(0) == table {
+                    self.element(module, index as u32);
+                }
+            }
+        }
+    }
+
+    fn element(&mut self, module: &Module, index: u32) {
+        if !self.elements.insert(index) {
+            return;
+        }
+        let element = &module.elements[index as usize];
+        if let ElementKind::Active { offset_expr, .. } = &element.kind {
+            self.init_expr(module, offset_expr);
+        }
+        match &element.items {
+            ElementItems::Functions(fids) => {
+                for fid in fids {
+                    self.function(**fid);
+                }
+            }
+            ElementItems::ConstExprs { exprs, .. } => {
+                for expr in exprs {
+                    self.init_expr(module, expr);
+                }
+            }
+        }
+    }
+
+    fn operator(&mut self, module: &Module, op: &Operator) {
+        match op {
+            Operator::Call { function_index }
+            | Operator::ReturnCall { function_index }
+            | Operator::RefFunc { function_index } => self.function(*function_index),
+            Operator::GlobalGet { global_index }
+            | Operator::GlobalSet { global_index }
+            | Operator::GlobalAtomicGet { global_index, .. }
+            | Operator::GlobalAtomicSet { global_index, .. }
+            | Operator::GlobalAtomicRmwAdd { global_index, .. }
+            | Operator::GlobalAtomicRmwAnd { global_index, .. }
+            | Operator::GlobalAtomicRmwCmpxchg { global_index, .. }
+            | Operator::GlobalAtomicRmwOr { global_index, .. }
+            | Operator::GlobalAtomicRmwSub { global_index, .. }
+            | Operator::GlobalAtomicRmwXchg { global_index, .. }
+            | Operator::GlobalAtomicRmwXor { global_index, .. } => {
+                self.global(module, *global_index)
+            }
+            Operator::CallIndirect { table_index, .. }
+            | Operator::ReturnCallIndirect { table_index, .. } => self.table(module, *table_index),
+            Operator::TableGet { table }
+            | Operator::TableSet { table }
+            | Operator::TableGrow { table }
+            | Operator::TableSize { table }
+            | Operator::TableFill { table } => self.table(module, *table),
+            Operator::TableCopy {
+                dst_table,
+                src_table,
+            } => {
+                self.table(module, *dst_table);
+                self.table(module, *src_table);
+            }
+            Operator::TableInit { elem_index, table } => {
+                self.table(module, *table);
+                self.element(module, *elem_index);
+            }
+            Operator::ElemDrop { elem_index } => self.element(module, *elem_index),
+            Operator::MemoryInit { data_index, .. } | Operator::DataDrop { data_index } => {
+                self.data.insert(*data_index);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Removes the functions, function imports and trailing globals which are not
+/// reachable from the exports or start function of the module, returning the
+/// module and name of each removed function import.
+///
+/// Functions placed in a table are reachable as soon as the table is used.
+/// Since table and data indices are not remapped when encoding, element and
+/// passive data segments which are not reachable are emptied rather than
+/// removed.
+pub fn eliminate_dead_code(module: &mut Module) -> Vec<(String, String)> {
+    let mut reachable = Reachable::default();
+
+    for export in module.exports.iter() {
+        match export.kind {
+            ExternalKind::Func => reachable.function(export.index),
+            ExternalKind::Global => reachable.global(module, export.index),
+            ExternalKind::Table => reachable.table(module, export.index),
+            _ => {}
+        }
+    }
+    if let Some(start) = module.start {
+        reachable.function(*start);
+    }
+    let imported_tables = module
+        .imports
+        .iter()
+        .filter(|import| matches!(import.ty, TypeRef::Table(_)))
+        .count() as u32;
+    for table in 0..imported_tables {
+        reachable.table(module, table);
+    }
+    for data in &module.data {
+        if let DataSegmentKind::Active { offset_expr, .. } = &data.kind {
+            reachable.init_expr(module, offset_expr);
+        }
+    }
+
+    while let Some(fid) = reachable.pending.pop() {
+        if let FuncKind::Local(func) = module.functions.get_kind(FunctionID(fid)) {
+            for op in func.body.instructions.get_ops() {
+                reachable.operator(module, op);
+            }
+        }
+    }
+
+    let mut removed_imports = Vec::new();
+    let dead_functions = module
+        .functions
+        .iter()
+        .enumerate()
+        .map(|(fid, _)| fid as u32)
+        .filter(|fid| {
+            !reachable.functions.contains(fid) && !module.functions.is_deleted(FunctionID(*fid))
+        })
+        .collect::<Vec<_>>();
+    for fid in dead_functions {
+        if let FuncKind::Import(import) = module.functions.get_kind(FunctionID(fid)) {
+            let import = module.imports.get(import.import_id);
+            removed_imports.push((import.module.to_string(), import.name.to_string()));
+        }
+        module.delete_func(FunctionID(fid));
+    }
+
+    // global names are encoded as parsed, so only the trailing globals (such
+    // as those added by the stubs) can be removed without renaming the others
+    let dead_globals = (0..module.globals.len() as u32)
+        .rev()
+        .take_while(|gid| !reachable.globals.contains(gid))
+        .collect::<Vec<_>>();
+    for gid in dead_globals {
+        module.delete_global(GlobalID(gid));
+    }
+
+    for (index, element) in module.elements.iter_mut().enumerate() {
+        let used = reachable.elements.contains(&(index as u32));
+        match (&element.kind, &mut element.items) {
+            // declarations only need to list the functions which remain
+            (ElementKind::Declared, ElementItems::Functions(fids)) => {
+                fids.retain(|fid| reachable.functions.contains(&**fid))
+            }
+            (ElementKind::Declared, ElementItems::ConstExprs { exprs, .. }) => {
+                exprs.retain(|expr| {
+                    expr.instructions().iter().all(|instr| match instr {
+                        InitInstr::RefFunc(fid) => reachable.functions.contains(&**fid),
+                        _ => true,
+                    })
+                })
+            }
+            (_, ElementItems::Functions(fids)) if !used => fids.clear(),
+            (_, ElementItems::ConstExprs { exprs, .. }) if !used => exprs.clear(),
+            _ => {}
+        }
+    }
+
+    for (index, data) in module.data.iter_mut().enumerate() {
+        if matches!(data.kind, DataSegmentKind::Passive)
+            && !reachable.data.contains(&(index as u32))
+        {
+            data.data.clear();
+        }
+    }
+
+    removed_imports
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_encoder::Instruction;
+
+    use super::*;
+    use crate::test_utils::TestModule;
+
+    /// A module importing `env.used` and `env.unused`, with an exported `run`
+    /// calling `used` with the given instructions following, a dead function
+    /// calling `unused` and a function only placed in a table
+    fn module(run: &[Instruction<'static>]) -> Vec<u8> {
+        let mut module = TestModule::default();
+        let used = module.import("env", "used", &[], &[]);
+        let unused = module.import("env", "unused", &[], &[]);
+        let body = [&[Instruction::Call(used)], run].concat();
+        module.export_function("run", &[], &[], &body);
+        module.function(&[], &[], &[], &[Instruction::Call(unused)]);
+        let table_fn = module.function(&[], &[], &[], &[]);
+        module.table(1).element(0, &[table_fn]);
+        module.finish()
+    }
+
+    /// Eliminates the dead code of the module, returning the removed imports,
+    /// the remaining imports, the number of remaining local functions and the
+    /// number of functions left in the element segment
+    fn eliminate(wasm: &[u8]) -> (Vec<(String, String)>, Vec<String>, usize, usize) {
+        let mut module = Module::parse(wasm, false).unwrap();
+        let removed = eliminate_dead_code(&mut module);
+        let wasm = module.encode();
+        wasmparser::Validator::new().validate_all(&wasm).unwrap();
+
+        let module = Module::parse(&wasm, false).unwrap();
+        let imports = module
+            .imports
+            .iter()
+            .map(|import| import.name.to_string())
+            .collect();
+        let locals = module
+            .functions
+            .iter()
+            .filter(|func| matches!(func.kind(), FuncKind::Local(_)))
+            .count();
+        let elements = match &module.elements[0].items {
+            ElementItems::Functions(fids) => fids.len(),
+            ElementItems::ConstExprs { exprs, .. } => exprs.len(),
+        };
+        (removed, imports, locals, elements)
+    }
+
+    #[test]
+    fn removes_unreachable_functions_and_imports() {
+        let (removed, imports, locals, elements) = eliminate(&module(&[]));
+        assert_eq!(removed, [("env".to_string(), "unused".to_string())]);
+        assert_eq!(imports, ["used"]);
+        assert_eq!(locals, 1);
+        assert_eq!(elements, 0);
+    }
+
+    #[test]
+    fn keeps_the_functions_of_used_tables() {
+        let (removed, imports, locals, elements) = eliminate(&module(&[
+            Instruction::I32Const(0),
+            Instruction::CallIndirect {
+                type_index: 0,
+                table_index: 0,
+            },
+        ]));
+        assert_eq!(removed, [("env".to_string(), "unused".to_string())]);
+        assert_eq!(imports, ["used"]);
+        assert_eq!(locals, 2);
+        assert_eq!(elements, 1);
+    }
+}
@@ -0,0 +1,655 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use anyhow::{Context, Result};
+use heck::*;
+use wit_parser::{
+    Function, FunctionKind, Handle, InterfaceId, Resolve, Type, TypeDefKind, TypeId, TypeOwner,
+    WorldId, WorldItem,
+};
+
+use crate::bindgen::get_result_types;
+use crate::wit::exports::local::spidermonkey_embedding_splicer::splicer::Feature;
+use crate::{splice, uwriteln};
+
+/// Specifier of the built-in module defining the world-level resource imports
+const ROOT_SPECIFIER: &str = "$root";
+
+/// Words which cannot be used as parameter or function names
+const RESERVED_WORDS: [&str; 45] = [
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+];
+
+/// Generates TypeScript declarations for the guest side of the bindings: a
+/// `declare module` for each import specifier defined by the bindings, and a
+/// `GuestExports` interface describing the exports the JS module must provide.
+///
+/// Functions returning a `result` are declared with the `ok` type, as the
+/// bindings throw the `err` case of imports and catch it from exports.
+/// Interfaces exported with an alias are described by the alias, although
+/// their qualified name is also accepted.
+pub fn guest_types(
+    resolve: &Resolve,
+    world: WorldId,
+    features: &[Feature],
+    export_aliases: &BTreeMap<String, String>,
+) -> Result<String> {
+    let mut generator = GuestTypes {
+        resolve,
+        src: String::new(),
+        current: None,
+        exporting: false,
+        import_specifiers: HashMap::new(),
+        export_namespaces: HashMap::new(),
+        root_resources: Vec::new(),
+    };
+
+    let root_functions = resolve.worlds[world]
+        .imports
+        .values()
+        .filter_map(|item| match item {
+            WorldItem::Function(func) => Some(func),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    for (key, item) in &resolve.worlds[world].imports {
+        match item {
+            WorldItem::Interface { id, .. } => {
+                generator
+                    .import_specifiers
+                    .insert(*id, resolve.name_world_key(key));
+            }
+            WorldItem::Type(id)
+                if resolve.types[*id].kind == TypeDefKind::Resource
+                    && root_functions
+                        .iter()
+                        .any(|func| func.kind.resource() == Some(*id)) =>
+            {
+                generator.root_resources.push(*id);
+            }
+            _ => {}
+        }
+    }
+
+    // the exports bound by the JS module, keyed as the bindings expect them
+    let mut exports = Vec::new();
+    for (key, item) in &resolve.worlds[world].exports {
+        let name = resolve.name_world_key(key);
+        if splice::is_versioned_interface(&name, &splice::HTTP_HANDLER_INTERFACES)
+            && features.contains(&Feature::FetchEvent)
+        {
+            continue;
+        }
+        match item {
+            WorldItem::Function(func) => {
+                if matches!(
+                    func.kind,
+                    FunctionKind::Freestanding | FunctionKind::AsyncFreestanding
+                ) {
+                    exports.push((func.item_name().to_lower_camel_case(), None, item));
+                }
+            }
+            WorldItem::Interface { id, .. } => {
+                let export_name = if name.contains(':') {
+                    name.clone()
+                } else {
+                    name.to_lower_camel_case()
+                };
+                let property = export_aliases
+                    .get(&export_name)
+                    .cloned()
+                    .unwrap_or_else(|| export_name.clone());
+                let namespace = property
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                    .collect::<String>()
+                    .to_upper_camel_case();
+                generator.export_namespaces.insert(*id, namespace);
+                let qualified = (property != export_name).then_some(export_name);
+                exports.push((property, qualified, item));
+            }
+            WorldItem::Type(_) => {}
+        }
+    }
+
+    uwriteln!(
+        generator.src,
+        "// Guest types of the world {}, generated from its WIT",
+        resolve.worlds[world].name
+    );
+
+    for (key, item) in &resolve.worlds[world].imports {
+        let specifier = resolve.name_world_key(key);
+        match item {
+            WorldItem::Function(func) => {
+                if !matches!(
+                    func.kind,
+                    FunctionKind::Freestanding | FunctionKind::AsyncFreestanding
+                ) {
+                    continue;
+                }
+                let name = identifier(&func.item_name().to_lower_camel_case());
+                let signature = generator.signature(func);
+                uwriteln!(
+                    generator.src,
+                    "\ndeclare module '{specifier}' {{\n  export default function {name}{signature};\n}}"
+                );
+            }
+            WorldItem::Interface { id, .. } => {
+                uwriteln!(generator.src, "\ndeclare module '{specifier}' {{");
+                generator.interface(*id, "  ")?;
+                uwriteln!(generator.src, "}}");
+            }
+            WorldItem::Type(_) => {}
+        }
+    }
+
+    if !generator.root_resources.is_empty() {
+        uwriteln!(generator.src, "\ndeclare module '{ROOT_SPECIFIER}' {{");
+        for id in generator.root_resources.clone() {
+            generator.resource(id, &root_functions, "  ")?;
+        }
+        uwriteln!(generator.src, "}}");
+    }
+
+    // the types and resources of exported interfaces are declared in a
+    // namespace merged with the exports interface
+    let namespaces = exports
+        .iter()
+        .filter_map(|(_, _, item)| match item {
+            WorldItem::Interface { id, .. } => Some(*id),
+            _ => None,
+        })
+        .filter(|id| !resolve.interfaces[*id].types.is_empty())
+        .collect::<Vec<_>>();
+    generator.exporting = true;
+    if !namespaces.is_empty() {
+        uwriteln!(generator.src, "\ndeclare namespace GuestExports {{");
+        for id in namespaces {
+            let namespace = &generator.export_namespaces[&id];
+            uwriteln!(generator.src, "  export namespace {namespace} {{");
+            generator.interface_types(id, "    ")?;
+            uwriteln!(generator.src, "  }}");
+        }
+        uwriteln!(generator.src, "}}");
+    }
+
+    uwriteln!(
+        generator.src,
+        "\n/** The exports the JS module must provide for the world */\ninterface GuestExports {{"
+    );
+    for (property, qualified, item) in &exports {
+        let property = property_name(property);
+        match item {
+            WorldItem::Function(func) => {
+                let signature = generator.signature(func);
+                uwriteln!(generator.src, "  {property}{signature};");
+            }
+            // interfaces exporting only types have nothing to bind
+            WorldItem::Interface { id, .. } if resolve.interfaces[*id].functions.is_empty() => {}
+            WorldItem::Interface { id, .. } => {
+                if let Some(qualified) = qualified {
+                    uwriteln!(
+                        generator.src,
+                        "  /** Can also be exported by its qualified name '{qualified}' */"
+                    );
+                }
+                uwriteln!(generator.src, "  {property}: {{");
+                let iface = &resolve.interfaces[*id];
+                for func in iface.functions.values() {
+                    if matches!(
+                        func.kind,
+                        FunctionKind::Freestanding | FunctionKind::AsyncFreestanding
+                    ) {
+                        let name = func.item_name().to_lower_camel_case();
+                        let signature = generator.signature(func);
+                        uwriteln!(generator.src, "    {}{signature};", property_name(&name));
+                    }
+                }
+                for (name, ty) in &iface.types {
+                    if resolve.types[*ty].kind == TypeDefKind::Resource {
+                        let name = name.to_upper_camel_case();
+                        let namespace = &generator.export_namespaces[id];
+                        uwriteln!(
+                            generator.src,
+                            "    {name}: typeof GuestExports.{namespace}.{name};"
+                        );
+                    }
+                }
+                uwriteln!(generator.src, "  }};");
+            }
+            WorldItem::Type(_) => {}
+        }
+    }
+    uwriteln!(generator.src, "}}");
+
+    Ok(generator.src)
+}
+
+struct GuestTypes<'a> {
+    resolve: &'a Resolve,
+    src: String,
+    /// The interface whose declarations are being generated, whose types are
+    /// referenced by their local names
+    current: Option<InterfaceId>,
+    /// Whether the exports are being declared, for which the types of
+    /// interfaces both imported and exported are taken from the export
+    exporting: bool,
+    /// Import specifiers of the imported interfaces
+    import_specifiers: HashMap<InterfaceId, String>,
+    /// Namespaces of the `GuestExports` types of the exported interfaces
+    export_namespaces: HashMap<InterfaceId, String>,
+    /// Resources imported at the level of the world which have functions, as
+    /// only those are bound by `$root`
+    root_resources: Vec<TypeId>,
+}
+
+impl<'a> GuestTypes<'a> {
+    /// Declares the types, resources and functions of an interface
+    fn interface(&mut self, id: InterfaceId, indent: &str) -> Result<()> {
+        self.interface_types(id, indent)?;
+        self.current = Some(id);
+        for func in self.resolve.interfaces[id].functions.values() {
+            if matches!(
+                func.kind,
+                FunctionKind::Freestanding | FunctionKind::AsyncFreestanding
+            ) {
+                let export_name = func.item_name().to_lower_camel_case();
+                let name = identifier(&export_name);
+                let signature = self.signature(func);
+                if name == export_name {
+                    uwriteln!(self.src, "{indent}export function {name}{signature};");
+                } else {
+                    // reserved words can only be exported under another name
+                    uwriteln!(self.src, "{indent}function {name}{signature};");
+                    uwriteln!(self.src, "{indent}export {{ {name} as {export_name} }};");
+                }
+            }
+        }
+        self.current = None;
+        Ok(())
+    }
+
+    /// Declares the types and resources of an interface
+    fn interface_types(&mut self, id: InterfaceId, indent: &str) -> Result<()> {
+        self.current = Some(id);
+        let iface = &self.resolve.interfaces[id];
+        let functions = iface.functions.values().collect::<Vec<_>>();
+        for (name, ty) in &iface.types {
+            let name = name.to_upper_camel_case();
+            match &self.resolve.types[*ty].kind {
+                TypeDefKind::Resource => self.resource(*ty, &functions, indent)?,
+                TypeDefKind::Record(record) => {
+                    uwriteln!(self.src, "{indent}export interface {name} {{");
+                    for field in &record.fields {
+                        let ty = self.ty(&field.ty);
+                        let field = property_name(&field.name.to_lower_camel_case());
+                        uwriteln!(self.src, "{indent}  {field}: {ty};");
+                    }
+                    uwriteln!(self.src, "{indent}}}");
+                }
+                TypeDefKind::Flags(flags) => {
+                    uwriteln!(self.src, "{indent}export interface {name} {{");
+                    for flag in &flags.flags {
+                        let flag = property_name(&flag.name.to_lower_camel_case());
+                        uwriteln!(self.src, "{indent}  {flag}?: boolean;");
+                    }
+                    uwriteln!(self.src, "{indent}}}");
+                }
+                kind => {
+                    let ty = self.kind(kind);
+                    uwriteln!(self.src, "{indent}export type {name} = {ty};");
+                }
+            }
+        }
+        self.current = None;
+        Ok(())
+    }
+
+    /// Declares a resource class with its constructor, static functions and
+    /// methods among the functions. Imported resources can also be disposed.
+    fn resource(&mut self, id: TypeId, functions: &[&Function], indent: &str) -> Result<()> {
+        let name = self.type_name(id)?.to_upper_camel_case();
+        uwriteln!(self.src, "{indent}export class {name} {{");
+        for func in functions {
+            match func.kind {
+                FunctionKind::Constructor(ty) if ty == id => {
+                    let params = self.params(func, false);
+                    uwriteln!(self.src, "{indent}  constructor({params});");
+                }
+                FunctionKind::Static(ty) | FunctionKind::AsyncStatic(ty) if ty == id => {
+                    let name = property_name(&func.item_name().to_lower_camel_case());
+                    let signature = self.signature(func);
+                    uwriteln!(self.src, "{indent}  static {name}{signature};");
+                }
+                FunctionKind::Method(ty) | FunctionKind::AsyncMethod(ty) if ty == id => {
+                    let name = property_name(&func.item_name().to_lower_camel_case());
+                    let signature = self.signature(func);
+                    uwriteln!(self.src, "{indent}  {name}{signature};");
+                }
+                _ => {}
+            }
+        }
+        if !self.exporting {
+            uwriteln!(self.src, "{indent}  [Symbol.dispose](): void;");
+        }
+        uwriteln!(self.src, "{indent}}}");
+        Ok(())
+    }
+
+    /// The WIT name of a named type
+    fn type_name(&self, id: TypeId) -> Result<&'a str> {
+        self.resolve.types[id]
+            .name
+            .as_deref()
+            .context("a resource type has no name")
+    }
+
+    /// The parameters and return type of a function
+    fn signature(&self, func: &Function) -> String {
+        let params = self.params(func, true);
+        let result = match get_result_types(self.resolve, func.result) {
+            Some((Some(ok), _)) => self.ty(ok),
+            Some((None, _)) => "void".to_string(),
+            None => match &func.result {
+                Some(ty) => self.ty(ty),
+                None => "void".to_string(),
+            },
+        };
+        match func.kind {
+            FunctionKind::AsyncFreestanding
+            | FunctionKind::AsyncMethod(_)
+            | FunctionKind::AsyncStatic(_) => format!("({params}): Promise<{result}>"),
+            _ => format!("({params}): {result}"),
+        }
+    }
+
+    /// The parameters of a function, excluding the `self` parameter of methods
+    fn params(&self, func: &Function, skip_self: bool) -> String {
+        let skip = match func.kind {
+            FunctionKind::Method(_) | FunctionKind::AsyncMethod(_) if skip_self => 1,
+            _ => 0,
+        };
+        func.params
+            .iter()
+            .skip(skip)
+            .map(|(name, ty)| {
+                format!(
+                    "{}: {}",
+                    identifier(&name.to_lower_camel_case()),
+                    self.ty(ty)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn ty(&self, ty: &Type) -> String {
+        match ty {
+            Type::Bool => "boolean".to_string(),
+            Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::S8
+            | Type::S16
+            | Type::S32
+            | Type::F32
+            | Type::F64 => "number".to_string(),
+            Type::U64 | Type::S64 => "bigint".to_string(),
+            Type::Char | Type::String => "string".to_string(),
+            Type::ErrorContext => "Error".to_string(),
+            Type::Id(id) => {
+                let ty = &self.resolve.types[*id];
+                match (&ty.name, &ty.owner) {
+                    (Some(name), TypeOwner::Interface(iface)) => self.reference(*iface, name),
+                    (Some(name), TypeOwner::World(_)) if ty.kind == TypeDefKind::Resource => {
+                        if self.root_resources.contains(id) {
+                            format!("import('{ROOT_SPECIFIER}').{}", name.to_upper_camel_case())
+                        } else {
+                            "unknown".to_string()
+                        }
+                    }
+                    _ => self.kind(&ty.kind),
+                }
+            }
+        }
+    }
+
+    /// A reference to a named type of an interface
+    fn reference(&self, iface: InterfaceId, name: &str) -> String {
+        let name = name.to_upper_camel_case();
+        let specifier = self.import_specifiers.get(&iface);
+        let namespace = self.export_namespaces.get(&iface);
+        match (specifier, namespace) {
+            _ if self.current == Some(iface) => name,
+            (_, Some(namespace)) if self.exporting => format!("GuestExports.{namespace}.{name}"),
+            (Some(specifier), _) => format!("import('{specifier}').{name}"),
+            (None, Some(namespace)) => format!("GuestExports.{namespace}.{name}"),
+            (None, None) => "unknown".to_string(),
+        }
+    }
+
+    fn kind(&self, kind: &TypeDefKind) -> String {
+        match kind {
+            TypeDefKind::Record(record) => format!(
+                "{{ {} }}",
+                record
+                    .fields
+                    .iter()
+                    .map(|field| format!(
+                        "{}: {}",
+                        property_name(&field.name.to_lower_camel_case()),
+                        self.ty(&field.ty)
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            TypeDefKind::Flags(flags) => format!(
+                "{{ {} }}",
+                flags
+                    .flags
+                    .iter()
+                    .map(|flag| format!(
+                        "{}?: boolean",
+                        property_name(&flag.name.to_lower_camel_case())
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            TypeDefKind::Tuple(tuple) => format!(
+                "[{}]",
+                tuple
+                    .types
+                    .iter()
+                    .map(|ty| self.ty(ty))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            TypeDefKind::Variant(variant) => variant
+                .cases
+                .iter()
+                .map(|case| tagged(&case.name, case.ty.as_ref().map(|ty| self.ty(ty))))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            TypeDefKind::Enum(enum_) => enum_
+                .cases
+                .iter()
+                .map(|case| format!("'{}'", case.name))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            TypeDefKind::Option(ty) => {
+                if self.is_option(ty) {
+                    format!(
+                        "{} | {}",
+                        tagged("some", Some(self.ty(ty))),
+                        tagged("none", None)
+                    )
+                } else {
+                    format!("{} | undefined", self.ty(ty))
+                }
+            }
+            TypeDefKind::Result(result) => format!(
+                "{} | {}",
+                tagged("ok", result.ok.as_ref().map(|ty| self.ty(ty))),
+                tagged("err", result.err.as_ref().map(|ty| self.ty(ty)))
+            ),
+            TypeDefKind::List(ty) => match self.typed_array(ty) {
+                Some(array) => array.to_string(),
+                None => format!("Array<{}>", self.ty(ty)),
+            },
+            TypeDefKind::Future(ty) => match ty {
+                Some(ty) => format!("Promise<{}>", self.ty(ty)),
+                None => "Promise<void>".to_string(),
+            },
+            TypeDefKind::Stream(ty) => match ty {
+                Some(Type::U8) => "AsyncIterable<Uint8Array>".to_string(),
+                Some(ty) => format!("AsyncIterable<Array<{}>>", self.ty(ty)),
+                None => "AsyncIterable<unknown>".to_string(),
+            },
+            TypeDefKind::Handle(Handle::Own(id) | Handle::Borrow(id)) => self.ty(&Type::Id(*id)),
+            TypeDefKind::Type(ty) => self.ty(ty),
+            TypeDefKind::Resource | TypeDefKind::Unknown => "unknown".to_string(),
+        }
+    }
+
+    /// Whether the type is an option, which makes an option of it nested
+    fn is_option(&self, ty: &Type) -> bool {
+        let Type::Id(id) = ty else {
+            return false;
+        };
+        match &self.resolve.types[*id].kind {
+            TypeDefKind::Option(_) => true,
+            TypeDefKind::Type(ty) => self.is_option(ty),
+            _ => false,
+        }
+    }
+
+    /// The typed array of a list of numbers
+    fn typed_array(&self, ty: &Type) -> Option<&'static str> {
+        match ty {
+            Type::U8 => Some("Uint8Array"),
+            Type::S8 => Some("Int8Array"),
+            Type::U16 => Some("Uint16Array"),
+            Type::S16 => Some("Int16Array"),
+            Type::U32 => Some("Uint32Array"),
+            Type::S32 => Some("Int32Array"),
+            Type::U64 => Some("BigUint64Array"),
+            Type::S64 => Some("BigInt64Array"),
+            Type::F32 => Some("Float32Array"),
+            Type::F64 => Some("Float64Array"),
+            Type::Id(id) => match &self.resolve.types[*id].kind {
+                TypeDefKind::Type(ty) => self.typed_array(ty),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A case of a tagged union, as variants, nested options and results are lifted
+fn tagged(tag: &str, val: Option<String>) -> String {
+    match val {
+        Some(val) => format!("{{ tag: '{tag}', val: {val} }}"),
+        None => format!("{{ tag: '{tag}' }}"),
+    }
+}
+
+/// A function or parameter name, avoiding reserved words
+fn identifier(name: &str) -> String {
+    if RESERVED_WORDS.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
+
+/// A property name, quoted when it is not an identifier
+fn property_name(name: &str) -> String {
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        name.to_string()
+    } else {
+        format!("'{name}'")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_types(wit: &str) -> String {
+        let mut resolve = Resolve::default();
+        let pkg = resolve.push_str("world.wit", wit).unwrap();
+        let world = resolve.select_world(pkg, None).unwrap();
+        guest_types(&resolve, world, &[], &BTreeMap::new()).unwrap()
+    }
+
+    #[test]
+    fn root_declares_bound_resources() {
+        let types = world_types(
+            "package local:root;
+            world w {
+              resource beta { constructor(); }
+              resource alpha { get: func() -> beta; }
+              resource unbound;
+              import make: func(u: borrow<unbound>) -> alpha;
+            }",
+        );
+        let root = &types[types.find("declare module '$root'").unwrap()..];
+        assert!(root.contains("export class Alpha {"));
+        assert!(root.contains("export class Beta {"));
+        assert!(!root.contains("Unbound"));
+        assert!(!root.contains("default"));
+        assert!(types.contains("export default function make(u: unknown): import('$root').Alpha;"));
+    }
+}
@@ -4,8 +4,14 @@ use anyhow::{bail, Context, Result};
 use wit_parser::{PackageId, Resolve};
 
 pub mod bindgen;
+pub mod dead_code;
+pub mod guest_types;
 pub mod splice;
+pub mod stub_policy;
 pub mod stub_wasi;
+#[cfg(test)]
+mod test_utils;
+pub mod validate;
 pub mod wit;
 
 use wit::exports::local::spidermonkey_embedding_splicer::splicer::{CoreFn, CoreTy};
@@ -1,26 +1,62 @@
+use std::collections::BTreeSet;
 use std::path::PathBuf;
 
-use anyhow::Result;
 use wasm_encoder::{Encode, Section};
 use wasmparser::ExternalKind;
 use wasmparser::MemArg;
 use wasmparser::Operator;
 use wirm::ir::function::{FunctionBuilder, FunctionModifier};
-use wirm::ir::id::{ExportsID, FunctionID, LocalID};
+use wirm::ir::id::{ExportsID, FunctionID, LocalID, TableID};
+use wirm::ir::module::module_tables::Element;
 use wirm::ir::module::Module;
-use wirm::ir::types::{BlockType, ElementItems, InstrumentationMode};
+use wirm::ir::types::{
+    BlockType, ElementItems, ElementKind, InitExpr, InitInstr, InstrumentationMode, Value,
+};
 use wirm::module_builder::AddLocal;
 use wirm::opcode::{Inject, InjectAt};
 use wirm::{DataType, Opcode};
 use wit_component::metadata::{decode, Bindgen};
 use wit_component::StringEncoding;
-use wit_parser::Resolve;
+use wit_parser::{Resolve, WorldId, WorldItem};
 
 use crate::bindgen::BindingItem;
 use crate::wit::exports::local::spidermonkey_embedding_splicer::splicer::{
-    CoreFn, CoreTy, Feature, SpliceResult,
+    self, CoreFn, CoreTy, Feature, SpliceError, SpliceOptions, SpliceResult, UnsupportedExport,
 };
-use crate::{bindgen, map_core_fn, parse_wit, splice};
+use crate::{bindgen, map_core_fn, parse_wit, splice, stub_wasi};
+
+/// Core export name prefix for exports lifted with the stackful async ABI
+const ASYNC_LIFT_PREFIX: &str = "[async-lift-stackful]";
+
+/// Core import name prefix for imports lowered with the async ABI
+const ASYNC_LOWER_PREFIX: &str = "[async-lower]";
+
+/// Versions of `wasi:cli/run` implemented by the engine, as name prefixes
+const CLI_RUN_INTERFACES: [&str; 2] = ["wasi:cli/run@0.2.", "wasi:cli/run@0.3."];
+
+/// Versions of the HTTP handler implemented by the engine for the
+/// `fetch-event` feature, as name prefixes
+pub(crate) const HTTP_HANDLER_INTERFACES: [&str; 2] =
+    ["wasi:http/incoming-handler@0.2.", "wasi:http/handler@0.3."];
+
+/// Whether an interface name has one of the versioned name prefixes
+pub(crate) fn is_versioned_interface(name: &str, prefixes: &[&str]) -> bool {
+    prefixes.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// Whether a core export name is the export of a function of one of the
+/// interfaces, lifted either synchronously or asynchronously
+fn is_interface_export(export: &str, prefixes: &[&str], func: &str) -> bool {
+    let export = export
+        .strip_prefix(ASYNC_LIFT_PREFIX)
+        .or_else(|| export.strip_prefix("[async-lift]"))
+        .unwrap_or(export);
+    let Some((interface, name)) = export.split_once('#') else {
+        return false;
+    };
+    is_versioned_interface(interface, prefixes)
+        && name.strip_prefix("[async]").unwrap_or(name) == func
+}
 
 // Returns
 // pub struct SpliceResult {
@@ -37,101 +73,164 @@ pub fn splice_bindings(
     wit_path: Option<String>,
     world_name: Option<String>,
     debug: bool,
-) -> Result<SpliceResult, String> {
+    options: SpliceOptions,
+) -> Result<SpliceResult, SpliceError> {
+    let SpliceOptions {
+        string_encoding,
+        export_names,
+        used_imports,
+        embedded_filesystem,
+    } = options;
+    let string_encoding =
+        StringEncoding::from(string_encoding.unwrap_or(splicer::StringEncoding::Utf8));
+
     let (mut resolve, id) = match (wit_source, wit_path) {
         (Some(wit_source), _) => {
             let mut resolve = Resolve::default();
             let path = PathBuf::from("component.wit");
             let id = resolve
                 .push_str(&path, &wit_source)
-                .map_err(|e| e.to_string())?;
+                .map_err(|e| SpliceError::InvalidWit(e.to_string()))?;
             (resolve, id)
         }
-        (_, Some(wit_path)) => parse_wit(&wit_path).map_err(|e| format!("{e:?}"))?,
+        (_, Some(wit_path)) => {
+            parse_wit(&wit_path).map_err(|e| SpliceError::InvalidWit(format!("{e:?}")))?
+        }
         (None, None) => {
-            return Err("neither wit source nor path have been specified".into());
+            return Err(SpliceError::InvalidWit(
+                "neither wit source nor path have been specified".into(),
+            ));
         }
     };
 
     let world = resolve
         .select_world(id, world_name.as_deref())
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| SpliceError::InvalidWit(e.to_string()))?;
+
+    if embedded_filesystem.is_some()
+        && resolve.worlds[world].imports.keys().any(|key| {
+            matches!(
+                resolve.name_world_key(key).split('@').next(),
+                Some("wasi:filesystem/types" | "wasi:filesystem/preopens")
+            )
+        })
+    {
+        return Err(SpliceError::InvalidEmbeddedFilesystem(
+            "a filesystem cannot be embedded when the target world imports wasi:filesystem".into(),
+        ));
+    }
 
     let mut wasm_bytes =
         wit_component::dummy_module(&resolve, world, wit_parser::ManglingAndAbi::Standard32);
 
     // merge the engine world with the target world, retaining the engine producers
 
-    let (engine_world, producers) = if let Ok((
-        _,
-        Bindgen {
-            resolve: mut engine_resolve,
-            world: engine_world,
-            metadata: _,
-            producers,
-        },
-    )) = decode(&engine)
-    {
-        // we disable the engine run and incoming handler as we recreate these exports
-        // when needed, so remove these from the world before initiating the merge
-        let maybe_run = engine_resolve.worlds[engine_world]
-            .exports
-            .iter()
-            .find(|(key, _)| {
-                engine_resolve
-                    .name_world_key(key)
-                    .starts_with("wasi:cli/run@0.2")
-            })
-            .map(|(key, _)| key.clone());
-        if let Some(run) = maybe_run {
-            engine_resolve.worlds[engine_world]
+    let (engine_world, producers) = match decode(&engine) {
+        Ok((
+            Some(_),
+            Bindgen {
+                resolve: mut engine_resolve,
+                world: engine_world,
+                metadata: _,
+                producers,
+            },
+        )) => {
+            // we disable the engine run and incoming handler as we recreate these exports
+            // when needed, so remove these from the world before initiating the merge
+            let replaced = engine_resolve.worlds[engine_world]
                 .exports
-                .shift_remove(&run)
-                .unwrap();
+                .keys()
+                .filter(|key| {
+                    let name = engine_resolve.name_world_key(key);
+                    is_versioned_interface(&name, &CLI_RUN_INTERFACES)
+                        || is_versioned_interface(&name, &HTTP_HANDLER_INTERFACES)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            for key in replaced {
+                engine_resolve.worlds[engine_world]
+                    .exports
+                    .shift_remove(&key)
+                    .unwrap();
+            }
+            let map = resolve.merge(engine_resolve).map_err(|e| {
+                SpliceError::InvalidWit(format!("unable to merge with engine world: {e}"))
+            })?;
+            let engine_world = map
+                .map_world(engine_world, None)
+                .map_err(|e| SpliceError::InvalidWit(e.to_string()))?;
+            (engine_world, producers)
         }
-        let maybe_serve = engine_resolve.worlds[engine_world]
-            .exports
-            .iter()
-            .find(|(key, _)| {
-                engine_resolve
-                    .name_world_key(key)
-                    .starts_with("wasi:http/incoming-handler@0.2.")
-            })
-            .map(|(key, _)| key.clone());
-
-        if let Some(serve) = maybe_serve {
-            engine_resolve.worlds[engine_world]
-                .exports
-                .shift_remove(&serve)
-                .unwrap();
+        Ok((None, _)) => {
+            return Err(SpliceError::MissingEngineSection(
+                "component-type".to_string(),
+            ))
         }
-        let map = resolve
-            .merge(engine_resolve)
-            .expect("unable to merge with engine world");
-        let engine_world = map.map_world(engine_world, None).unwrap();
-        (engine_world, producers)
-    } else {
-        unreachable!();
+        Err(e) => return Err(SpliceError::InvalidEngine(format!("{e:#}"))),
     };
 
-    let componentized =
-        bindgen::componentize_bindgen(&resolve, world, &features).map_err(|err| err.to_string())?;
-
-    resolve
-        .merge_worlds(engine_world, world)
-        .expect("unable to merge with engine world");
-
-    let encoded =
-        wit_component::metadata::encode(&resolve, world, StringEncoding::UTF8, producers.as_ref())
-            .map_err(|e| e.to_string())?;
-
-    let section = wasm_encoder::CustomSection {
-        name: "component-type".into(),
-        data: encoded.into(),
-    };
+    let componentized = bindgen::componentize_bindgen(
+        &resolve,
+        world,
+        engine_world,
+        &features,
+        string_encoding,
+        export_names.as_deref(),
+        used_imports.as_deref(),
+    )
+    .map_err(|err| match err.downcast::<bindgen::ExportError>() {
+        Ok(bindgen::ExportError { name, message }) => {
+            SpliceError::UnsupportedExport(UnsupportedExport { name, message })
+        }
+        Err(err) => SpliceError::Bindgen(err.to_string()),
+    })?;
+    if !componentized.missing_exports.is_empty() {
+        return Err(SpliceError::MissingExports(componentized.missing_exports));
+    }
 
-    wasm_bytes.push(section.id());
-    section.encode(&mut wasm_bytes);
+    // the engine always binds its own imports as UTF-8, so any other string
+    // encoding for the guest world is recorded in a separate section
+    let mut sections = Vec::new();
+    if string_encoding == StringEncoding::UTF8 {
+        resolve.merge_worlds(engine_world, world).map_err(|e| {
+            SpliceError::InvalidWit(format!("unable to merge with engine world: {e}"))
+        })?;
+
+        let encoded = wit_component::metadata::encode(
+            &resolve,
+            world,
+            StringEncoding::UTF8,
+            producers.as_ref(),
+        )
+        .map_err(|e| SpliceError::InvalidWit(e.to_string()))?;
+        sections.push(("component-type", encoded));
+    } else {
+        let encoded = wit_component::metadata::encode(
+            &resolve,
+            engine_world,
+            StringEncoding::UTF8,
+            producers.as_ref(),
+        )
+        .map_err(|e| SpliceError::InvalidWit(e.to_string()))?;
+        sections.push(("component-type", encoded));
+
+        let guest_resolve = guest_world_resolve(&resolve, world, engine_world);
+        let encoded = wit_component::metadata::encode(&guest_resolve, world, string_encoding, None)
+            .map_err(|e| SpliceError::InvalidWit(e.to_string()))?;
+        sections.push(("component-type:guest", encoded));
+    }
+    let sections = sections
+        .into_iter()
+        .map(|(name, encoded)| wasm_encoder::CustomSection {
+            name: name.into(),
+            data: encoded.into(),
+        })
+        .collect::<Vec<_>>();
+
+    for section in &sections {
+        wasm_bytes.push(section.id());
+        section.encode(&mut wasm_bytes);
+    }
 
     let mut generated_bindings = componentized.js_bindings;
 
@@ -181,42 +280,43 @@ pub fn splice_bindings(
     }
 
     let mut exports = Vec::new();
-    for (
-        export_name,
-        BindingItem {
-            name,
-            func,
-            iface,
-            resource,
-            ..
-        },
-    ) in &componentized.exports
-    {
-        let expt = if *iface {
-            let name = resource.canon_string(name);
-            format!("{export_name}#{name}")
+    for (export_name, item) in &componentized.exports {
+        let expt = if item.iface {
+            format!("{export_name}#{}", item.canon_string())
+        } else if item.is_async {
+            item.canon_string()
         } else {
             export_name.clone()
         };
-        exports.push((expt, map_core_fn(func)));
+        // async exports are lifted with the stackful async ABI
+        let expt = if item.is_async {
+            format!("{ASYNC_LIFT_PREFIX}{expt}")
+        } else {
+            expt
+        };
+        exports.push((expt, map_core_fn(&item.func)));
     }
 
     let mut imports = Vec::new();
-    for (
-        specifier,
-        BindingItem {
-            name,
-            func,
-            iface,
-            resource,
-            ..
-        },
-    ) in &componentized.imports
-    {
-        if *iface {
+    for (specifier, item) in &componentized.imports {
+        let BindingItem { func, iface, .. } = item;
+        if item.is_async {
+            // async imports take the parameter and result pointers allocated
+            // by the JS binding, returning the subtask status
+            imports.push((
+                if *iface {
+                    specifier.to_string()
+                } else {
+                    "$root".into()
+                },
+                format!("{ASYNC_LOWER_PREFIX}{}", item.canon_string()),
+                map_core_fn(func),
+                None,
+            ));
+        } else if *iface {
             imports.push((
                 specifier.to_string(),
-                resource.canon_string(name),
+                item.canon_string(),
                 map_core_fn(func),
                 if func.retsize > 0 {
                     Some(func.retsize as i32)
@@ -257,12 +357,20 @@ pub fn splice_bindings(
         ));
     }
 
-    let mut wasm =
-        splice::splice(engine, imports, exports, features, debug).map_err(|e| format!("{e:?}"))?;
+    let mut wasm = splice::splice(engine, imports, exports, features, debug)?;
 
-    // add the world section to the spliced wasm
-    wasm.push(section.id());
-    section.encode(&mut wasm);
+    // add the world sections to the spliced wasm
+    for section in &sections {
+        wasm.push(section.id());
+        section.encode(&mut wasm);
+    }
+
+    // the filesystem is embedded before initialization, once the world
+    // sections describe the filesystem imports of the engine
+    if let Some(filesystem) = &embedded_filesystem {
+        wasm = stub_wasi::embed_filesystem(&wasm, filesystem)
+            .map_err(|e| SpliceError::InvalidEmbeddedFilesystem(format!("{e:#}")))?;
+    }
 
     Ok(SpliceResult {
         wasm,
@@ -281,38 +389,62 @@ pub fn splice_bindings(
         imports: componentized
             .imports
             .iter()
-            .map(
-                |(
-                    specifier,
-                    BindingItem {
-                        name,
-                        iface,
-                        func,
-                        resource,
-                        ..
+            .map(|(specifier, item)| {
+                (
+                    if item.iface {
+                        specifier.to_string()
+                    } else {
+                        "$root".into()
                     },
-                )| {
-                    (
-                        if *iface {
-                            specifier.to_string()
-                        } else {
-                            "$root".into()
-                        },
-                        if *iface {
-                            resource.canon_string(name)
-                        } else {
-                            specifier.to_string()
-                        },
-                        func.params.len() as u32,
-                    )
-                },
-            )
+                    if item.is_async {
+                        format!("{ASYNC_LOWER_PREFIX}{}", item.canon_string())
+                    } else if item.iface {
+                        item.canon_string()
+                    } else {
+                        specifier.to_string()
+                    },
+                    item.func.params.len() as u32,
+                )
+            })
             .chain(componentized.resource_imports)
             .collect(),
         js_bindings: generated_bindings,
+        guest_types: componentized.guest_types,
     })
 }
 
+/// A copy of the resolve in which the guest world only describes the functions
+/// bound by the guest. Items shared with the engine keep their types for the
+/// guest world to reference, while their functions are left to the engine world.
+fn guest_world_resolve(resolve: &Resolve, world: WorldId, engine_world: WorldId) -> Resolve {
+    let engine = &resolve.worlds[engine_world];
+    let engine_items = engine
+        .imports
+        .keys()
+        .chain(engine.exports.keys())
+        .map(|key| bindgen::encoding_key(&resolve.name_world_key(key)).to_string())
+        .collect::<BTreeSet<_>>();
+    let is_shared =
+        |key| engine_items.contains(bindgen::encoding_key(&resolve.name_world_key(key)));
+
+    let mut guest = resolve.clone();
+    let guest_world = &resolve.worlds[world];
+    for (key, item) in guest_world.imports.iter().chain(guest_world.exports.iter()) {
+        if !is_shared(key) {
+            continue;
+        }
+        match item {
+            WorldItem::Interface { id, .. } => guest.interfaces[*id].functions.clear(),
+            WorldItem::Function(_) => {
+                guest.worlds[world].imports.shift_remove(key);
+                guest.worlds[world].exports.shift_remove(key);
+            }
+            WorldItem::Type(_) => {}
+        }
+    }
+    guest
+}
+
 //
 // Parses the Spidermonkey binary into section data for reserialization
 // into an output binary, and in the process:
@@ -325,6 +457,7 @@ pub fn splice_bindings(
 //
 //    The following sample functions are used for imports:
 //    - "coreabi_get_import"
+//    - "coreabi_sample_i32_table_index"
 //    - "coreabi_sample_i32"
 //    - "coreabi_sample_i64"
 //    - "coreabi_sample_f32"
@@ -342,22 +475,18 @@ pub fn splice(
     exports: Vec<(String, CoreFn)>,
     features: Vec<Feature>,
     debug: bool,
-) -> Result<Vec<u8>> {
-    let mut module = Module::parse(&engine, false).unwrap();
+) -> Result<Vec<u8>, SpliceError> {
+    let mut module =
+        Module::parse(&engine, false).map_err(|e| SpliceError::InvalidEngine(format!("{e:#}")))?;
 
     // since StarlingMonkey implements CLI Run and incoming handler,
     // we override them only if the guest content exports those functions
-    remove_if_exported_by_js(&mut module, &exports, "wasi:cli/run@0.2.", "#run");
+    remove_if_exported_by_js(&mut module, &exports, &CLI_RUN_INTERFACES, "run")?;
 
     // if 'fetch-event' feature is disabled (default being default-enabled),
     // remove the built-in incoming-handler which is built around it's use.
     if !features.contains(&Feature::FetchEvent) {
-        remove_if_exported_by_js(
-            &mut module,
-            &exports,
-            "wasi:http/incoming-handler@0.2.",
-            "#handle",
-        );
+        remove_if_exported_by_js(&mut module, &exports, &HTTP_HANDLER_INTERFACES, "handle")?;
     }
 
     // we reencode the WASI world component data, so strip it out from the
@@ -383,91 +512,201 @@ pub fn splice(
 fn remove_if_exported_by_js(
     module: &mut Module,
     content_exports: &[(String, CoreFn)],
-    name_start: &str,
-    name_end: &str,
-) {
+    interfaces: &[&str],
+    func: &str,
+) -> Result<(), SpliceError> {
     let content_exports_run = content_exports
         .iter()
-        .any(|(name, _)| name.starts_with(name_start) && name.ends_with(name_end));
-    if content_exports_run {
-        let exported_run_fn = module
-            .exports
-            .iter()
-            .find(|export| export.name.starts_with(name_start) && export.name.ends_with(name_end))
-            .unwrap();
-        let export_id = module
-            .exports
-            .get_export_id_by_name(String::from(&exported_run_fn.name))
-            .unwrap();
-        let function_id = module
-            .exports
-            .get_func_by_name(String::from(&exported_run_fn.name))
-            .unwrap();
-        module.exports.delete(export_id);
-        module.delete_func(function_id);
+        .any(|(name, _)| is_interface_export(name, interfaces, func));
+    if !content_exports_run {
+        return Ok(());
     }
+    let Some(exported_run_fn) = module
+        .exports
+        .iter()
+        .find(|export| is_interface_export(&export.name, interfaces, func))
+    else {
+        return Ok(());
+    };
+    let name = exported_run_fn.name.to_string();
+    let export_id = required_export(module, &name)?;
+    let function_id = get_export_fid(module, &export_id)?;
+    module.exports.delete(export_id);
+    module.delete_func(function_id);
+    Ok(())
 }
 
-fn get_export_fid(module: &Module, expt_id: &ExportsID) -> FunctionID {
-    let expt = module.exports.get_by_id(*expt_id).unwrap();
+/// Finds the `I32Const` table base in the body of `coreabi_get_import`,
+/// returning its instruction index and value.
+///
+/// The table base is the table index of `coreabi_sample_i32`, which the engine
+/// passes to `JS_NewFunction`. It is returned by the engine's
+/// `coreabi_sample_i32_table_index` export, and must be referenced by exactly
+/// one constant of `coreabi_get_import`.
+pub(crate) fn find_table_base(
+    module: &Module,
+    coreabi_get_import_fid: FunctionID,
+) -> Result<(usize, i32), SpliceError> {
+    let marker_fid = local_export_fid(module, "coreabi_sample_i32_table_index")?;
+    let table_base = match module
+        .functions
+        .get(marker_fid)
+        .unwrap_local()
+        .body
+        .instructions
+        .get_ops()
+    {
+        [Operator::I32Const { value }, Operator::End] => *value,
+        _ => {
+            return Err(SpliceError::InvalidEngine(
+                "coreabi_sample_i32_table_index does not return a constant table index".to_string(),
+            ))
+        }
+    };
+
+    let ops = module
+        .functions
+        .get(coreabi_get_import_fid)
+        .unwrap_local()
+        .body
+        .instructions
+        .get_ops();
+    let mut table_base_ops = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| matches!(op, Operator::I32Const { value } if *value == table_base));
+    match (table_base_ops.next(), table_base_ops.next()) {
+        (Some((idx, _)), None) => Ok((idx, table_base)),
+        (None, _) => Err(SpliceError::InvalidEngine(format!(
+            "coreabi_get_import does not reference the coreabi_sample_i32 table index {table_base}"
+        ))),
+        (Some(_), Some(_)) => Err(SpliceError::InvalidEngine(format!(
+            "coreabi_get_import references the coreabi_sample_i32 table index {table_base} more than once"
+        ))),
+    }
+}
+
+/// The function table and index of a function, as placed by an active element
+/// segment with a constant offset
+fn function_table_slot(module: &Module, fid: FunctionID) -> Option<(TableID, i32)> {
+    module.elements.iter().find_map(|element| {
+        let ElementKind::Active {
+            table_index,
+            offset_expr,
+        } = &element.kind
+        else {
+            return None;
+        };
+        let [InitInstr::Value(Value::I32(offset))] = offset_expr.instructions() else {
+            return None;
+        };
+        let position = match &element.items {
+            ElementItems::Functions(funcs) => funcs.iter().position(|f| *f == fid),
+            ElementItems::ConstExprs { exprs, .. } => exprs.iter().position(
+                |expr| matches!(expr.instructions(), [InitInstr::RefFunc(f)] if *f == fid),
+            ),
+        }?;
+        Some((TableID(table_index.unwrap_or(0)), offset + position as i32))
+    })
+}
+
+/// The function table used by the engine for `JS_NewFunction`, which is the
+/// table holding `coreabi_sample_i32`.
+///
+/// Engines may contain passive or declared segments and further tables, so the
+/// table is identified through the sample function rather than by position.
+pub(crate) fn engine_function_table(module: &Module) -> Result<TableID, SpliceError> {
+    let sample_fid = local_export_fid(module, "coreabi_sample_i32")?;
+    let (tid, _) = function_table_slot(module, sample_fid)
+        .ok_or_else(|| SpliceError::MissingEngineSection("element".to_string()))?;
+    if module.tables.get(tid).is_none() {
+        return Err(SpliceError::MissingEngineSection("table".to_string()));
+    }
+    Ok(tid)
+}
+
+/// Looks up an export the engine must provide for splicing
+pub(crate) fn required_export(module: &Module, name: &str) -> Result<ExportsID, SpliceError> {
+    module
+        .exports
+        .get_export_id_by_name(name.to_string())
+        .ok_or_else(|| SpliceError::MissingEngineExport(name.to_string()))
+}
+
+pub(crate) fn get_export_fid(
+    module: &Module,
+    expt_id: &ExportsID,
+) -> Result<FunctionID, SpliceError> {
+    let expt = module
+        .exports
+        .get_by_id(*expt_id)
+        .ok_or_else(|| SpliceError::InvalidEngine(format!("invalid export id {}", **expt_id)))?;
 
     match expt.kind {
-        ExternalKind::Func => FunctionID::from(expt.index),
-        _ => panic!("Missing coreabi_get_import"),
+        ExternalKind::Func => Ok(FunctionID::from(expt.index)),
+        _ => Err(SpliceError::InvalidEngine(format!(
+            "engine export `{}` is not a function",
+            expt.name
+        ))),
+    }
+}
+
+/// Looks up a function export of the engine whose body is used for splicing
+fn local_export(module: &Module, name: &str) -> Result<(ExportsID, FunctionID), SpliceError> {
+    let expt_id = required_export(module, name)?;
+    let fid = get_export_fid(module, &expt_id)?;
+    if !module.functions.is_local(fid) {
+        return Err(SpliceError::InvalidEngine(format!(
+            "engine export `{name}` is not a locally defined function"
+        )));
     }
+    Ok((expt_id, fid))
+}
+
+fn local_export_fid(module: &Module, name: &str) -> Result<FunctionID, SpliceError> {
+    local_export(module, name).map(|(_, fid)| fid)
 }
 
 fn synthesize_import_functions(
     module: &mut Module,
     imports: &[(String, String, CoreFn, Option<i32>)],
     debug: bool,
-) -> Result<()> {
-    let mut coreabi_get_import: Option<ExportsID> = None;
-    let mut cabi_realloc: Option<ExportsID> = None;
-
-    let mut coreabi_sample_ids = Vec::new();
-    for (id, expt) in module.exports.iter().enumerate() {
-        match expt.name.as_str() {
-            "coreabi_sample_i32" | "coreabi_sample_i64" | "coreabi_sample_f32"
-            | "coreabi_sample_f64" => coreabi_sample_ids.push(ExportsID::from(id)),
-            "coreabi_get_import" => coreabi_get_import = Some(ExportsID::from(id)),
-            "cabi_realloc" => cabi_realloc = Some(ExportsID::from(id)),
-            _ => {}
-        };
-    }
+) -> Result<(), SpliceError> {
+    let coreabi_get_import = required_export(module, "coreabi_get_import")?;
+    let cabi_realloc = required_export(module, "cabi_realloc")?;
+
+    // the sample functions are templates for the synthesized imports
+    let coreabi_samples = [
+        "coreabi_sample_i32",
+        "coreabi_sample_i64",
+        "coreabi_sample_f32",
+        "coreabi_sample_f64",
+    ]
+    .into_iter()
+    .map(|name| local_export(module, name))
+    .collect::<Result<Vec<_>, _>>()?;
 
     let memory = 0;
 
-    let main_tid = module.tables.main_function().unwrap();
+    let main_tid = engine_function_table(module)?;
 
-    let import_fn_table_start_idx = module.tables.get(main_tid).unwrap().initial as i32;
+    let import_fn_table_start_idx = module
+        .tables
+        .get(main_tid)
+        .ok_or_else(|| SpliceError::MissingEngineSection("table".to_string()))?
+        .initial as i32;
 
-    let cabi_realloc_fid = get_export_fid(module, &cabi_realloc.unwrap());
+    let cabi_realloc_fid = get_export_fid(module, &cabi_realloc)?;
 
-    let fid = get_export_fid(module, &coreabi_sample_ids[0]);
-    let coreabi_sample_i32 = module.functions.get(fid).unwrap_local();
-    let _coreabi_sample_i64 = module
-        .functions
-        .get(get_export_fid(module, &coreabi_sample_ids[1]))
-        .unwrap_local();
-    let _coreabi_sample_f32 = module
-        .functions
-        .get(get_export_fid(module, &coreabi_sample_ids[2]))
-        .unwrap_local();
-    let _coreabi_sample_f64 = module
-        .functions
-        .get(get_export_fid(module, &coreabi_sample_ids[3]))
-        .unwrap_local();
+    let coreabi_sample_i32 = module.functions.get(coreabi_samples[0].1).unwrap_local();
 
     // These functions retrieve the corresponding type
     // from a JS::HandleValue
     // All except for the BigInt one are trivial and thus
     // do not require regular explicit template extraction
     // unless there are major ABI changes in Spidermonkey
-    let coreabi_from_bigint64 = module
-        .exports
-        .get_export_id_by_name("coreabi_from_bigint64".to_string())
-        .unwrap();
+    let coreabi_from_bigint64 = required_export(module, "coreabi_from_bigint64")?;
+    let coreabi_from_bigint64_fid = get_export_fid(module, &coreabi_from_bigint64)?;
 
     // Sets the return value on args from the stack
     let args_ret_i32: Vec<Operator> = vec![
@@ -488,10 +727,8 @@ fn synthesize_import_functions(
 
     // BigInt instructions are a little more involved as we need to extract
     // the separate ToBigInt call from the get_i64 sample
-    let coreabi_to_bigint64 = module
-        .exports
-        .get_export_id_by_name("coreabi_to_bigint64".to_string())
-        .unwrap();
+    let coreabi_to_bigint64 = required_export(module, "coreabi_to_bigint64")?;
+    let coreabi_to_bigint64_fid = get_export_fid(module, &coreabi_to_bigint64)?;
 
     // create the import functions
     // All JS wrapper function bindings have the same type, the
@@ -509,10 +746,7 @@ fn synthesize_import_functions(
         // if we need to tee the retptr
         for (impt_specifier, impt_name, impt_sig, retptr_size) in imports.iter() {
             if debug {
-                println!(
-                    "> IMPORT {} {} > {:?}",
-                    impt_specifier, impt_name, &impt_sig
-                );
+                println!("> IMPORT {impt_specifier} {impt_name} > {impt_sig:?}");
             }
 
             // add the imported function type
@@ -562,11 +796,11 @@ fn synthesize_import_functions(
 
             // stack the return arg now as it chains with the
             // args we're about to add to the stack
-            if impt_sig.ret.is_some() {
+            if let Some(ret) = impt_sig.ret {
                 func.local_get(vp_arg);
 
                 // if an i64 return, then we need to stack the extra BigInt constructor arg for that now
-                if matches!(impt_sig.ret.unwrap(), CoreTy::I64) {
+                if matches!(ret, CoreTy::I64) {
                     func.local_get(ctx_arg);
                 }
             }
@@ -592,7 +826,7 @@ fn synthesize_import_functions(
                         func.i32_wrap_i64();
                     }
                     CoreTy::I64 => {
-                        func.call(get_export_fid(module, &coreabi_from_bigint64));
+                        func.call(coreabi_from_bigint64_fid);
                     }
                     CoreTy::F32 => {
                         // isInt: (r.asRawBits() >> 32) == 0xFFFFFF81
@@ -681,7 +915,7 @@ fn synthesize_import_functions(
                     func.inject(instr.clone());
                 }),
                 Some(CoreTy::I64) => {
-                    func.call(get_export_fid(module, &coreabi_to_bigint64));
+                    func.call(coreabi_to_bigint64_fid);
                     func.i64_extend_i32u();
                     func.i64_const(-511101108224);
                     func.i64_or();
@@ -721,15 +955,20 @@ fn synthesize_import_functions(
         // extend the main table to include indices for generated imported functions
         let table = module.tables.get_mut(main_tid);
         table.initial += imports.len() as u64;
-        table.maximum = Some(table.maximum.unwrap() + imports.len() as u64);
-
-        // create imported function table
-        let els = module.elements.iter_mut().next().unwrap();
-        if let ElementItems::Functions(ref mut funcs) = &mut els.items {
-            for fid in import_fnids {
-                funcs.push(fid);
-            }
-        }
+        table.maximum = table.maximum.map(|max| max + imports.len() as u64);
+
+        // place the imported functions in their own active segment after the
+        // existing table entries, leaving the engine's segments untouched
+        module.elements.push(Element::new(
+            ElementKind::Active {
+                table_index: (*main_tid != 0).then_some(*main_tid),
+                offset_expr: InitExpr::new(vec![InitInstr::Value(Value::I32(
+                    import_fn_table_start_idx,
+                ))]),
+            },
+            ElementItems::Functions(import_fnids),
+            None,
+        ));
     }
 
     // Populate the import creation function of the form:
@@ -741,7 +980,7 @@ fn synthesize_import_functions(
     // }
     //
     {
-        let coreabi_get_import_fid = get_export_fid(module, &coreabi_get_import.unwrap());
+        let coreabi_get_import_fid = local_export_fid(module, "coreabi_get_import")?;
 
         let args = &module
             .functions
@@ -752,30 +991,15 @@ fn synthesize_import_functions(
 
         let arg_idx = args[0];
 
+        // Find the I32Const base index and compute the delta to new base
+        let (table_instr_idx, table_base) = find_table_base(module, coreabi_get_import_fid)?;
+        let delta = import_fn_table_start_idx - table_base;
+
         let builder: &mut FunctionModifier = &mut module
             .functions
             .get_fn_modifier(coreabi_get_import_fid)
             .unwrap();
 
-        // Find the I32Const base index and compute the delta to new base
-        let mut table_instr_idx = 0usize;
-        let mut delta: i32 = 0;
-        {
-            let ops_ro = builder.body.instructions.get_ops();
-            for (idx, op) in ops_ro.iter().enumerate() {
-                if let Operator::I32Const { value } = op {
-                    // we specifically need the const "around" 3393
-                    // which is the coreabi_sample_i32 table offset
-                    if *value < 1000 || *value > 5000 {
-                        continue;
-                    }
-                    delta = import_fn_table_start_idx - *value;
-                    table_instr_idx = idx;
-                    break;
-                }
-            }
-        }
-
         builder.inject_at(
             table_instr_idx,
             InstrumentationMode::Before,
@@ -804,32 +1028,26 @@ fn synthesize_import_functions(
     // remove unnecessary exports
     module.exports.delete(coreabi_to_bigint64);
     module.exports.delete(coreabi_from_bigint64);
-    module.exports.delete(coreabi_get_import.unwrap());
-    for id in coreabi_sample_ids {
+    module.exports.delete(coreabi_get_import);
+    module
+        .exports
+        .delete(required_export(module, "coreabi_sample_i32_table_index")?);
+    for (id, _) in coreabi_samples {
         module.exports.delete(id);
     }
 
     Ok(())
 }
 
-fn synthesize_export_functions(module: &mut Module, exports: &[(String, CoreFn)]) -> Result<()> {
-    let cabi_realloc = get_export_fid(
-        module,
-        &module
-            .exports
-            .get_export_id_by_name("cabi_realloc".to_string())
-            .unwrap(),
-    );
-    let call_expt = module
-        .exports
-        .get_export_id_by_name("call".to_string())
-        .unwrap();
-    let call = get_export_fid(module, &call_expt);
-    let post_call_expt = module
-        .exports
-        .get_export_id_by_name("post_call".to_string())
-        .unwrap();
-    let post_call = get_export_fid(module, &post_call_expt);
+fn synthesize_export_functions(
+    module: &mut Module,
+    exports: &[(String, CoreFn)],
+) -> Result<(), SpliceError> {
+    let cabi_realloc = get_export_fid(module, &required_export(module, "cabi_realloc")?)?;
+    let call_expt = required_export(module, "call")?;
+    let call = get_export_fid(module, &call_expt)?;
+    let post_call_expt = required_export(module, "post_call")?;
+    let post_call = get_export_fid(module, &post_call_expt)?;
 
     let memory = 0;
     // (2) Export call function synthesis
@@ -953,7 +1171,15 @@ fn synthesize_export_functions(module: &mut Module, exports: &[(String, CoreFn)]
             // Call "call" (returns retptr)
             func.call(call);
 
-            if expt_sig.ret.is_none() {
+            let is_async = expt_name.starts_with(ASYNC_LIFT_PREFIX);
+            if is_async {
+                // async exports deliver their result through task.return
+                // during the call, so there is no post-return and we can
+                // immediately run the bulk deallocation
+                func.drop();
+                func.i32_const(export_num as i32);
+                func.call(post_call);
+            } else if expt_sig.ret.is_none() {
                 func.drop();
             } else if !expt_sig.retptr {
                 // Tee retptr into its local var
@@ -999,6 +1225,10 @@ fn synthesize_export_functions(module: &mut Module, exports: &[(String, CoreFn)]
 
             let fid = func.finish_module(module);
             module.exports.add_export_func((*expt_name).clone(), *fid);
+
+            if is_async {
+                continue;
+            }
         }
 
         // Post export function synthesis
@@ -1034,3 +1264,197 @@ fn synthesize_export_functions(module: &mut Module, exports: &[(String, CoreFn)]
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use wasm_encoder::{Instruction, ValType};
+
+    use super::*;
+    use crate::test_utils::{test_engine, TestModule, TEST_ENGINE_TABLE_SIZE};
+
+    /// An engine exporting a table index marker returning `marker`, and a
+    /// `coreabi_get_import` with the given body
+    fn engine(marker: i32, get_import: &[Instruction<'static>]) -> Vec<u8> {
+        let mut module = TestModule::default();
+        module.export_function(
+            "coreabi_sample_i32_table_index",
+            &[],
+            &[ValType::I32],
+            &[Instruction::I32Const(marker)],
+        );
+        module.export_function(
+            "coreabi_get_import",
+            &[ValType::I32; 3],
+            &[ValType::I32],
+            get_import,
+        );
+        module.finish()
+    }
+
+    fn table_base(wasm: &[u8]) -> Result<(usize, i32), SpliceError> {
+        let module = Module::parse(wasm, false).unwrap();
+        let fid = local_export_fid(&module, "coreabi_get_import")?;
+        find_table_base(&module, fid)
+    }
+
+    #[test]
+    fn finds_the_marked_table_index() {
+        let wasm = engine(
+            7,
+            &[
+                Instruction::I32Const(1),
+                Instruction::Drop,
+                Instruction::I32Const(7),
+            ],
+        );
+        assert_eq!(table_base(&wasm).unwrap(), (2, 7));
+    }
+
+    #[test]
+    fn rejects_a_missing_or_ambiguous_table_index() {
+        let missing = engine(7, &[Instruction::I32Const(1)]);
+        assert!(matches!(
+            table_base(&missing),
+            Err(SpliceError::InvalidEngine(_))
+        ));
+        let ambiguous = engine(
+            7,
+            &[
+                Instruction::I32Const(7),
+                Instruction::Drop,
+                Instruction::I32Const(7),
+            ],
+        );
+        assert!(matches!(
+            table_base(&ambiguous),
+            Err(SpliceError::InvalidEngine(_))
+        ));
+    }
+
+    fn splice_world(
+        engine: Vec<u8>,
+        wit: &str,
+        export_names: Option<Vec<String>>,
+    ) -> Result<SpliceResult, SpliceError> {
+        splice_bindings(
+            engine,
+            vec![],
+            Some(wit.to_string()),
+            None,
+            None,
+            false,
+            SpliceOptions {
+                string_encoding: None,
+                export_names,
+                used_imports: None,
+                embedded_filesystem: None,
+            },
+        )
+    }
+
+    #[test]
+    fn reports_splice_errors_as_variants() {
+        let world = "package local:test; world test { export run: func(); }";
+        assert!(splice_world(test_engine(), world, None).is_ok());
+
+        assert!(matches!(
+            splice_world(test_engine(), "package local:test; world test {", None),
+            Err(SpliceError::InvalidWit(_))
+        ));
+        assert!(matches!(
+            splice_world(b"not wasm".to_vec(), world, None),
+            Err(SpliceError::InvalidEngine(_))
+        ));
+        assert!(matches!(
+            splice_world(engine(7, &[Instruction::I32Const(7)]), world, None),
+            Err(SpliceError::MissingEngineSection(section)) if section == "component-type"
+        ));
+
+        let mut resolve = Resolve::default();
+        let pkg = resolve
+            .push_str("engine.wit", "package local:engine; world engine {}")
+            .unwrap();
+        let engine_world = resolve.select_world(pkg, None).unwrap();
+        let bindings =
+            wit_component::metadata::encode(&resolve, engine_world, StringEncoding::UTF8, None)
+                .unwrap();
+        let mut module = TestModule::default();
+        module
+            .memory(1)
+            .custom_section("component-type:bindings", &bindings);
+        assert!(matches!(
+            splice_world(module.finish(), world, None),
+            Err(SpliceError::MissingEngineExport(_))
+        ));
+
+        assert!(matches!(
+            splice_world(test_engine(), world, Some(vec![])),
+            Err(SpliceError::MissingExports(missing)) if missing.len() == 1
+        ));
+        let colliding = "package local:test;
+            world test {
+              export x-y: func();
+              export x-Y: interface { f: func(); }
+            }";
+        assert!(matches!(
+            splice_world(test_engine(), colliding, None),
+            Err(SpliceError::UnsupportedExport(UnsupportedExport { name, .. })) if name == "xY"
+        ));
+    }
+
+    #[test]
+    fn places_imports_in_their_own_element_segment() {
+        let import = |name: &str| {
+            (
+                "local:test/host".to_string(),
+                name.to_string(),
+                CoreFn {
+                    params: vec![CoreTy::I32],
+                    ret: None,
+                    retptr: false,
+                    retsize: 0,
+                    paramptr: false,
+                },
+                None,
+            )
+        };
+        let wasm = splice(
+            test_engine(),
+            vec![import("a"), import("b")],
+            vec![],
+            vec![],
+            false,
+        )
+        .unwrap();
+
+        let module = Module::parse(&wasm, false).unwrap();
+        assert_eq!(
+            module.tables.get(TableID(0)).unwrap().initial,
+            TEST_ENGINE_TABLE_SIZE + 2
+        );
+        let segments = module
+            .elements
+            .iter()
+            .map(|element| {
+                let ElementKind::Active { offset_expr, .. } = &element.kind else {
+                    panic!("unexpected inactive segment");
+                };
+                let [InitInstr::Value(Value::I32(offset))] = offset_expr.instructions() else {
+                    panic!("unexpected segment offset");
+                };
+                let ElementItems::Functions(funcs) = &element.items else {
+                    panic!("unexpected segment items");
+                };
+                (*offset, funcs.len())
+            })
+            .collect::<Vec<_>>();
+        // the engine segment is left as it was, with the imports following it
+        assert_eq!(
+            segments,
+            [
+                (0, TEST_ENGINE_TABLE_SIZE as usize),
+                (TEST_ENGINE_TABLE_SIZE as i32, 2)
+            ]
+        );
+    }
+}
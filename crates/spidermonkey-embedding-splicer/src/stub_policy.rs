@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Number, Value};
+
+/// How an imported WASI function is treated when stubbing
+#[derive(Debug, Clone, PartialEq)]
+pub enum StubAction {
+    /// Keep the import, even if it would otherwise be stubbed
+    Allow,
+    /// Trap when called
+    Unreachable,
+    /// Return the `err` case of the function's result, with the given error
+    /// code case (or a default such as `unsupported`) when the error type is
+    /// an enum or variant
+    ReturnError { code: Option<String> },
+    /// Return constant core values, ignoring the arguments
+    Custom { results: Vec<Number> },
+}
+
+/// A declarative stubbing policy, mapping interfaces (`wasi:cli/exit`) or
+/// functions (`wasi:cli/exit#exit`) to stub actions.
+///
+/// Interface names may include a version to only apply to that version. The
+/// most specific entry applies, and functions without an entry are stubbed
+/// according to the enabled features.
+///
+/// ```json
+/// {
+///   "wasi:cli/environment": "allow",
+///   "wasi:cli/exit": "unreachable",
+///   "wasi:filesystem/types#[method]descriptor.advise": { "action": "return-error", "code": "not-permitted" },
+///   "wasi:random/random#get-random-u64": { "action": "custom", "results": [4] }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StubPolicy {
+    entries: BTreeMap<String, StubAction>,
+}
+
+impl StubPolicy {
+    /// Parses a JSON stub policy
+    pub fn from_json(source: &str) -> Result<Self> {
+        let value: Value = serde_json::from_str(source).context("invalid stub policy JSON")?;
+        let Value::Object(map) = value else {
+            bail!("stub policy must be an object of interface or function names to actions");
+        };
+        let entries = map
+            .into_iter()
+            .map(|(key, value)| {
+                let action = parse_action(&value)
+                    .with_context(|| format!("invalid stub policy entry for '{key}'"))?;
+                Ok((key, action))
+            })
+            .collect::<Result<_>>()?;
+        Ok(StubPolicy { entries })
+    }
+
+    /// The action for a function of an imported interface, which may be versioned
+    pub fn action(&self, import: &str, name: &str) -> Option<&StubAction> {
+        let unversioned = import.split_once('@').map(|(import, _)| import);
+        [
+            Some(format!("{import}#{name}")),
+            unversioned.map(|import| format!("{import}#{name}")),
+            Some(import.to_string()),
+            unversioned.map(str::to_string),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|key| self.entries.get(&key))
+    }
+
+    /// Whether the function is allowed to remain imported
+    pub fn allows(&self, import: &str, name: &str) -> bool {
+        self.action(import, name) == Some(&StubAction::Allow)
+    }
+}
+
+fn parse_action(value: &Value) -> Result<StubAction> {
+    let (action, options) = match value {
+        Value::String(action) => (action.as_str(), None),
+        Value::Object(options) => match options.get("action") {
+            Some(Value::String(action)) => (action.as_str(), Some(options)),
+            _ => bail!("missing \"action\""),
+        },
+        _ => bail!("expected an action name or object"),
+    };
+    match action {
+        "allow" => Ok(StubAction::Allow),
+        "unreachable" => Ok(StubAction::Unreachable),
+        "return-error" => {
+            let code = match options.and_then(|options| options.get("code")) {
+                None => None,
+                Some(Value::String(code)) => Some(code.clone()),
+                Some(_) => bail!("\"code\" must be a string"),
+            };
+            Ok(StubAction::ReturnError { code })
+        }
+        "custom" => {
+            let Some(Value::Array(results)) = options.and_then(|options| options.get("results"))
+            else {
+                bail!("custom actions require a \"results\" array of core values");
+            };
+            let results = results
+                .iter()
+                .map(|result| match result {
+                    Value::Number(result) => Ok(result.clone()),
+                    _ => bail!("custom results must be numbers"),
+                })
+                .collect::<Result<_>>()?;
+            Ok(StubAction::Custom { results })
+        }
+        _ => bail!("unrecognized stub action [{action}]"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_actions() {
+        let policy = StubPolicy::from_json(
+            r#"{
+                "wasi:cli/environment": "allow",
+                "wasi:cli/exit": "unreachable",
+                "wasi:filesystem/types#[method]descriptor.advise": { "action": "return-error", "code": "not-permitted" },
+                "wasi:filesystem/types#[method]descriptor.sync": { "action": "return-error" },
+                "wasi:random/random#get-random-u64": { "action": "custom", "results": [4] }
+            }"#,
+        )
+        .unwrap();
+        assert!(policy.allows("wasi:cli/environment", "get-environment"));
+        assert_eq!(
+            policy.action("wasi:cli/exit", "exit"),
+            Some(&StubAction::Unreachable)
+        );
+        assert_eq!(
+            policy.action("wasi:filesystem/types", "[method]descriptor.advise"),
+            Some(&StubAction::ReturnError {
+                code: Some("not-permitted".to_string())
+            })
+        );
+        assert_eq!(
+            policy.action("wasi:filesystem/types", "[method]descriptor.sync"),
+            Some(&StubAction::ReturnError { code: None })
+        );
+        assert_eq!(
+            policy.action("wasi:random/random", "get-random-u64"),
+            Some(&StubAction::Custom {
+                results: vec![Number::from(4)]
+            })
+        );
+        assert_eq!(
+            policy.action("wasi:random/random", "get-random-bytes"),
+            None
+        );
+    }
+
+    #[test]
+    fn most_specific_entry_applies() {
+        let policy = StubPolicy::from_json(
+            r#"{
+                "wasi:cli/exit": "unreachable",
+                "wasi:cli/exit@0.2.3": "allow",
+                "wasi:cli/exit#exit-with-code": "return-error",
+                "wasi:cli/exit@0.2.3#exit-with-code": { "action": "custom", "results": [] }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            policy.action("wasi:cli/exit@0.2.0", "exit"),
+            Some(&StubAction::Unreachable)
+        );
+        assert_eq!(
+            policy.action("wasi:cli/exit@0.2.3", "exit"),
+            Some(&StubAction::Allow)
+        );
+        assert_eq!(
+            policy.action("wasi:cli/exit@0.2.0", "exit-with-code"),
+            Some(&StubAction::ReturnError { code: None })
+        );
+        assert_eq!(
+            policy.action("wasi:cli/exit@0.2.3", "exit-with-code"),
+            Some(&StubAction::Custom { results: vec![] })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_policies() {
+        for source in [
+            "[]",
+            "{",
+            r#"{ "wasi:cli/exit": "ignore" }"#,
+            r#"{ "wasi:cli/exit": 1 }"#,
+            r#"{ "wasi:cli/exit": {} }"#,
+            r#"{ "wasi:cli/exit": { "action": "return-error", "code": 1 } }"#,
+            r#"{ "wasi:cli/exit": { "action": "custom" } }"#,
+            r#"{ "wasi:cli/exit": { "action": "custom", "results": ["1"] } }"#,
+        ] {
+            assert!(StubPolicy::from_json(source).is_err(), "{source}");
+        }
+    }
+}
@@ -1,24 +1,296 @@
 use std::collections::HashSet;
+use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use semver::Version;
+use wasm_encoder::{Encode, Section};
 use wasmparser::{MemArg, TypeRef};
 use wirm::ir::function::FunctionBuilder;
-use wirm::ir::id::{FunctionID, LocalID};
+use wirm::ir::id::{FunctionID, ImportsID, LocalID};
 use wirm::ir::module::module_functions::FuncKind;
 use wirm::ir::types::{BlockType, InitExpr, Value};
 use wirm::module_builder::AddLocal;
 use wirm::{DataType, InitInstr, Module, Opcode};
-use wit_parser::Resolve;
+use wit_parser::{
+    Function, Int, Interface, Resolve, SizeAlign, Type, TypeDefKind, WorldId, WorldItem, WorldKey,
+};
 
+use crate::dead_code::eliminate_dead_code;
 use crate::parse_wit;
-use crate::wit::exports::local::spidermonkey_embedding_splicer::splicer::Feature;
+use crate::stub_policy::{StubAction, StubPolicy};
+use crate::wit::exports::local::spidermonkey_embedding_splicer::splicer::{
+    Feature, ImportStatus, RandomSeed, StubBehavior, StubOptions, StubReason, StubReportEntry,
+    StubResult,
+};
 
-const WASI_VERSIONS: [&str; 4] = ["0.2.0", "0.2.1", "0.2.2", "0.2.3"];
+mod embedded_filesystem;
+
+pub(crate) use embedded_filesystem::embed_filesystem;
+
+/// Whether the version is a release of WASI 0.2
+fn is_wasip2_version(version: &Version) -> bool {
+    version.major == 0 && version.minor == 2 && version.pre.is_empty()
+}
+
+/// Whether the version is a release or release candidate of WASI 0.3
+fn is_wasip3_version(version: &Version) -> bool {
+    version.major == 0 && version.minor == 3
+}
+
+/// Whether WASI imports at this version can be stubbed
+fn is_supported_wasi_version(version: &Version) -> bool {
+    is_wasip2_version(version) || is_wasip3_version(version)
+}
+
+/// Splits a versioned interface name such as `wasi:cli/stdout@0.2.3` into
+/// the interface name and its version
+fn parse_versioned_import(import: &str) -> Option<(&str, Result<Version, semver::Error>)> {
+    let (interface, version) = import.split_once('@')?;
+    Some((interface, Version::parse(version)))
+}
+
+/// The module names under which the engine imports a WASI interface at a
+/// supported version, failing when it is only imported at other versions
+fn wasi_import_versions(module: &Module, import: &str) -> Result<Vec<String>> {
+    let mut supported = Vec::new();
+    let mut unsupported = Vec::new();
+    for module_name in module
+        .imports
+        .iter()
+        .map(|import| import.module.to_string())
+    {
+        let Some((interface, version)) = parse_versioned_import(&module_name) else {
+            continue;
+        };
+        if interface != import
+            || supported.contains(&module_name)
+            || unsupported.contains(&module_name)
+        {
+            continue;
+        }
+        match version {
+            Ok(version) if is_supported_wasi_version(&version) => supported.push(module_name),
+            _ => unsupported.push(module_name),
+        }
+    }
+    if supported.is_empty() && !unsupported.is_empty() {
+        bail!(
+            "unable to stub '{}': the engine imports it at a version other than WASI 0.2.x \
+             or 0.3.x, so it would remain imported",
+            unsupported.join("', '")
+        );
+    }
+    Ok(supported)
+}
+
+/// A module being stubbed, which keeps the imports allowed by the stub policy
+/// and reports how each import was treated
+struct StubModule<'a> {
+    module: Module<'a>,
+    policy: StubPolicy,
+    signatures: Option<ImportSignatures>,
+    /// The reason recorded for the imports currently being stubbed
+    reason: StubReason,
+    report: Vec<StubReportEntry>,
+}
+
+impl<'a> StubModule<'a> {
+    fn new(wasm: &'a [u8], policy: StubPolicy, reason: StubReason) -> Self {
+        StubModule {
+            module: Module::parse(wasm, false).unwrap(),
+            policy,
+            signatures: ImportSignatures::from_wasm(wasm),
+            reason,
+            report: Vec::new(),
+        }
+    }
+
+    /// Records how an import was treated, unless it has already been reported
+    fn record(&mut self, import: &str, name: &str, status: ImportStatus) {
+        if self
+            .report
+            .iter()
+            .any(|entry| entry.module == import && entry.name == name)
+        {
+            return;
+        }
+        self.report.push(StubReportEntry {
+            module: import.to_string(),
+            name: name.to_string(),
+            reason: self.reason.clone(),
+            status,
+        });
+    }
+
+    /// Records an import removed as unreachable, which was either kept earlier
+    /// or not covered by any stub
+    fn record_removed(&mut self, import: &str, name: &str) {
+        match self
+            .report
+            .iter_mut()
+            .find(|entry| entry.module == import && entry.name == name)
+        {
+            Some(entry) => entry.status = ImportStatus::Removed,
+            None => self.record(import, name, ImportStatus::Removed),
+        }
+    }
+
+    /// Whether the imports of an interface are stubbed as the target world
+    /// does not import it, recording the interface as the reason
+    fn stubs_world_import(&mut self, world_imports: &HashSet<String>, interface: &str) -> bool {
+        self.reason = StubReason::WorldImport(interface.to_string());
+        !world_imports.contains(interface)
+    }
+
+    /// Reports the function imports which remain after stubbing, with the
+    /// feature or world import they belong to
+    fn report_kept(&mut self, features: &[Feature], world_imports: &HashSet<String>) {
+        let kept = self
+            .functions
+            .iter()
+            .enumerate()
+            .filter(|(fid, _)| !self.functions.is_deleted(FunctionID(*fid as u32)))
+            .filter_map(|(_, func)| match func.kind() {
+                FuncKind::Import(import) => Some(self.imports.get(import.import_id)),
+                FuncKind::Local(_) => None,
+            })
+            .map(|import| (import.module.to_string(), import.name.to_string()))
+            .collect::<Vec<_>>();
+        for (import, name) in kept {
+            self.reason = kept_reason(&self.policy, features, world_imports, &import, &name);
+            self.record(&import, &name, ImportStatus::Kept);
+        }
+    }
+}
+
+/// The reason an import which was not stubbed is kept
+fn kept_reason(
+    policy: &StubPolicy,
+    features: &[Feature],
+    world_imports: &HashSet<String>,
+    import: &str,
+    name: &str,
+) -> StubReason {
+    if policy.allows(import, name) {
+        return StubReason::Policy;
+    }
+    let feature = match parse_versioned_import(import) {
+        Some((interface, _)) if world_imports.contains(interface) => {
+            return StubReason::WorldImport(interface.to_string());
+        }
+        Some((interface, _)) => match interface {
+            _ if interface.starts_with("wasi:random/") => Some(Feature::Random),
+            _ if interface.starts_with("wasi:clocks/") => Some(Feature::Clocks),
+            "wasi:cli/stdin" | "wasi:cli/stdout" | "wasi:cli/stderr" => Some(Feature::Stdio),
+            "wasi:http/types"
+                if features.contains(&Feature::FetchEvent)
+                    && !features.contains(&Feature::Http) =>
+            {
+                Some(Feature::FetchEvent)
+            }
+            _ if interface.starts_with("wasi:http/") => Some(Feature::Http),
+            _ => None,
+        },
+        None if import == PREVIEW1 => match name {
+            "fd_write" | "fd_fdstat_get" => Some(Feature::Stdio),
+            "clock_time_get" | "clock_res_get" => Some(Feature::Clocks),
+            _ => None,
+        },
+        None => None,
+    };
+    feature.map_or(StubReason::Engine, StubReason::Feature)
+}
+
+impl<'a> Deref for StubModule<'a> {
+    type Target = Module<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.module
+    }
+}
+
+impl DerefMut for StubModule<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.module
+    }
+}
+
+/// The WIT signatures of the engine imports, from its component type sections
+struct ImportSignatures {
+    resolve: Resolve,
+    world: WorldId,
+    sizes: SizeAlign,
+}
+
+impl ImportSignatures {
+    fn from_wasm(wasm: &[u8]) -> Option<Self> {
+        let (_, bindgen) = wit_component::metadata::decode(wasm).ok()?;
+        let mut sizes = SizeAlign::default();
+        sizes.fill(&bindgen.resolve);
+        Some(ImportSignatures {
+            resolve: bindgen.resolve,
+            world: bindgen.world,
+            sizes,
+        })
+    }
+
+    /// Whether the function returns a result
+    fn is_fallible(&self, import: &str, name: &str) -> bool {
+        self.function(import, name).is_some_and(|func| {
+            match func.result.map(|ty| dealias(&self.resolve, ty)) {
+                Some(Type::Id(id)) => {
+                    matches!(self.resolve.types[id].kind, TypeDefKind::Result(_))
+                }
+                _ => false,
+            }
+        })
+    }
+
+    fn function(&self, import: &str, name: &str) -> Option<&Function> {
+        self.interface(import)?.functions.get(name)
+    }
+
+    /// The discriminant of a case of an enum defined by an imported interface
+    fn enum_case(&self, import: &str, ty: &str, case: &str) -> Option<i32> {
+        let id = *self.interface(import)?.types.get(ty)?;
+        match &self.resolve.types[id].kind {
+            TypeDefKind::Enum(enum_) => enum_
+                .cases
+                .iter()
+                .position(|enum_case| enum_case.name == case)
+                .map(|index| index as i32),
+            _ => None,
+        }
+    }
+
+    fn interface(&self, import: &str) -> Option<&Interface> {
+        self.resolve.worlds[self.world]
+            .imports
+            .iter()
+            .find_map(|(key, item)| match item {
+                WorldItem::Interface { id, .. } if self.resolve.name_world_key(key) == import => {
+                    Some(&self.resolve.interfaces[*id])
+                }
+                _ => None,
+            })
+    }
+}
+
+/// Follows type aliases, such as the types brought into scope with `use`
+fn dealias(resolve: &Resolve, mut ty: Type) -> Type {
+    while let Type::Id(id) = ty {
+        match resolve.types[id].kind {
+            TypeDefKind::Type(inner) => ty = inner,
+            _ => break,
+        }
+    }
+    ty
+}
 
 fn stub_wasi_imports<StubFn>(
-    module: &mut Module,
+    module: &mut StubModule,
     import: &str,
     name: &str,
     stub: StubFn,
@@ -26,48 +298,70 @@ fn stub_wasi_imports<StubFn>(
 where
     StubFn: Fn(&mut FunctionBuilder) -> Result<Vec<LocalID>>,
 {
-    for wasi_version in WASI_VERSIONS {
-        let full_import = format!("{import}@{wasi_version}");
-        let Some(iid) = module.imports.find(full_import.parse()?, name.parse()?) else {
+    let mut stubbed = None;
+    let mut present = false;
+    for full_import in wasi_import_versions(module, import)? {
+        if !is_imported(module, &full_import, name) {
             continue;
-        };
-
-        let TypeRef::Func(_) = module.imports.get(iid).ty else {
-            bail!("'{full_import}#{name}' is not a function.")
-        };
-        let fid: FunctionID = FunctionID(*iid);
-
-        let f = module.functions.get(fid);
-        let ty_id = match f.kind() {
-            FuncKind::Local(_) => bail!("Can't find type of '{full_import}#{name}'"),
-            FuncKind::Import(i) => i.ty_id,
-        };
-
-        let ty = module.types.get(ty_id).unwrap();
-        let mut builder = FunctionBuilder::new(ty.params().as_slice(), ty.results().as_slice());
-        let _args = stub(&mut builder)?;
-
-        builder.replace_import_in_module(module, iid);
-
-        return Ok(Some(fid));
+        }
+        present = true;
+        stubbed =
+            stub_import(module, &full_import, name, StubBehavior::Emulated, &stub)?.or(stubbed);
+    }
+    if !present {
+        module.record(import, name, ImportStatus::NotPresent);
     }
+    Ok(stubbed)
+}
 
-    Ok(None)
+/// Whether the module imports the function, including imports which have
+/// already been replaced by a stub
+fn is_imported(module: &Module, import: &str, name: &str) -> bool {
+    module
+        .imports
+        .find(import.to_string(), name.to_string())
+        .is_some()
 }
 
 fn stub_import<StubFn>(
-    module: &mut Module,
+    module: &mut StubModule,
     import: &str,
     name: &str,
+    behavior: StubBehavior,
     stub: StubFn,
 ) -> Result<Option<FunctionID>>
 where
     StubFn: Fn(&mut FunctionBuilder) -> Result<Vec<LocalID>>,
 {
     let Some(iid) = module.imports.find(import.parse()?, name.parse()?) else {
+        module.record(import, name, ImportStatus::NotPresent);
         return Ok(None);
     };
 
+    // imports with a policy action have been handled by `apply_stub_policy`
+    if let Some(action) = module.policy.action(import, name) {
+        if *action == StubAction::Allow {
+            let reason = std::mem::replace(&mut module.reason, StubReason::Policy);
+            module.record(import, name, ImportStatus::Kept);
+            module.reason = reason;
+        }
+        return Ok(None);
+    }
+
+    replace_import(module, iid, import, name, behavior, stub).map(Some)
+}
+
+fn replace_import<StubFn>(
+    module: &mut StubModule,
+    iid: ImportsID,
+    import: &str,
+    name: &str,
+    behavior: StubBehavior,
+    stub: StubFn,
+) -> Result<FunctionID>
+where
+    StubFn: Fn(&mut FunctionBuilder) -> Result<Vec<LocalID>>,
+{
     let TypeRef::Func(_) = module.imports.get(iid).ty else {
         bail!("'{import}#{name}' is not a function.")
     };
@@ -85,8 +379,9 @@ where
     let _args = stub(&mut builder)?;
 
     builder.replace_import_in_module(module, iid);
+    module.record(import, name, ImportStatus::Stubbed(behavior));
 
-    Ok(Some(fid))
+    Ok(fid)
 }
 
 fn unreachable_stub(body: &mut FunctionBuilder) -> Result<Vec<LocalID>> {
@@ -94,13 +389,333 @@ fn unreachable_stub(body: &mut FunctionBuilder) -> Result<Vec<LocalID>> {
     Ok(vec![])
 }
 
+/// The core parameter and result types of an imported function
+fn import_type(
+    module: &Module,
+    import: &str,
+    name: &str,
+) -> Option<(Vec<DataType>, Vec<DataType>)> {
+    let iid = module.imports.find(import.to_string(), name.to_string())?;
+    let FuncKind::Import(i) = module.functions.get(FunctionID(*iid)).kind() else {
+        return None;
+    };
+    let ty = module.types.get(i.ty_id)?;
+    Some((ty.params(), ty.results()))
+}
+
+/// Error code cases returned by default from the stubs of fallible functions,
+/// in order of preference, before falling back to the first case without a
+/// payload
+const DEFAULT_ERROR_CODES: [&str; 7] = [
+    "unsupported",
+    "not-supported",
+    "access",
+    "access-denied",
+    "not-permitted",
+    "forbidden",
+    "HTTP-request-denied",
+];
+
+/// Creates a stub returning the `err` case of the result of a fallible
+/// function, with the given error code case, or a default case, when the
+/// error is an enum or a variant.
+///
+/// Results are returned directly when they flatten to a single value, and
+/// otherwise stored through the return pointer passed as the last parameter.
+fn error_stub(
+    module: &Module,
+    signatures: Option<&ImportSignatures>,
+    import: &str,
+    name: &str,
+    code: Option<&str>,
+) -> Result<impl Fn(&mut FunctionBuilder) -> Result<Vec<LocalID>>> {
+    let memory = module.get_memory_id().unwrap();
+    let signatures = signatures.context("the engine has no component type for its imports")?;
+    let func = signatures
+        .function(import, name)
+        .with_context(|| format!("'{import}#{name}' has no WIT signature"))?;
+    let result = match func.result.map(|ty| dealias(&signatures.resolve, ty)) {
+        Some(Type::Id(id)) => match &signatures.resolve.types[id].kind {
+            TypeDefKind::Result(result) => result,
+            _ => bail!("'{import}#{name}' does not return a result"),
+        },
+        _ => bail!("'{import}#{name}' does not return a result"),
+    };
+    let (params, results) = import_type(module, import, name)
+        .with_context(|| format!("'{import}#{name}' is not an imported function"))?;
+
+    // the error case and the integer type of its discriminant
+    let error_case = match result.err.map(|ty| dealias(&signatures.resolve, ty)) {
+        None => None,
+        Some(Type::Id(id)) => {
+            let (cases, tag) = match &signatures.resolve.types[id].kind {
+                TypeDefKind::Enum(enum_) => (
+                    enum_
+                        .cases
+                        .iter()
+                        .map(|case| (case.name.as_str(), true))
+                        .collect::<Vec<_>>(),
+                    enum_.tag(),
+                ),
+                TypeDefKind::Variant(variant) => (
+                    variant
+                        .cases
+                        .iter()
+                        .map(|case| (case.name.as_str(), case.ty.is_none()))
+                        .collect(),
+                    variant.tag(),
+                ),
+                _ => bail!("'{import}#{name}' has an unsupported error type"),
+            };
+            let index = match code {
+                Some(code) => cases
+                    .iter()
+                    .position(|(case, _)| *case == code)
+                    .with_context(|| format!("'{import}#{name}' has no error code '{code}'"))?,
+                None => DEFAULT_ERROR_CODES
+                    .iter()
+                    .find_map(|code| {
+                        cases
+                            .iter()
+                            .position(|(case, unit)| *unit && case.eq_ignore_ascii_case(code))
+                    })
+                    .or_else(|| cases.iter().position(|(_, unit)| *unit))
+                    .with_context(|| {
+                        format!("'{import}#{name}' has no error code without a payload")
+                    })?,
+            };
+            if !cases[index].1 {
+                bail!(
+                    "error code '{}' of '{import}#{name}' has a payload",
+                    cases[index].0
+                );
+            }
+            Some((index as i32, tag))
+        }
+        Some(_) => bail!("'{import}#{name}' has an unsupported error type"),
+    };
+
+    let retptr = match (results.as_slice(), params.len()) {
+        ([DataType::I32], _) if error_case.is_none() => None,
+        ([], len) if len > 0 => Some(LocalID(len as u32 - 1)),
+        _ => bail!("'{import}#{name}' has an unsupported core signature"),
+    };
+    let payload_offset = signatures
+        .sizes
+        .payload_offset(Int::U8, [result.ok.as_ref(), result.err.as_ref()])
+        .size_wasm32() as u64;
+
+    Ok(move |body: &mut FunctionBuilder| {
+        let Some(retptr) = retptr else {
+            body.i32_const(1);
+            return Ok(vec![]);
+        };
+        body.local_get(retptr);
+        body.i32_const(1);
+        body.i32_store8(MemArg {
+            align: 0,
+            max_align: 0,
+            offset: 0,
+            memory: *memory,
+        });
+        if let Some((case, tag)) = error_case {
+            let mem_arg = |align| MemArg {
+                align,
+                max_align: 0,
+                offset: payload_offset,
+                memory: *memory,
+            };
+            body.local_get(retptr);
+            body.i32_const(case);
+            match tag {
+                Int::U8 => body.i32_store8(mem_arg(0)),
+                Int::U16 => body.i32_store16(mem_arg(1)),
+                Int::U32 => body.i32_store(mem_arg(2)),
+                Int::U64 => bail!("unsupported error discriminant"),
+            };
+        }
+        Ok(vec![retptr])
+    })
+}
+
+/// Stubs a WASI function which is not supported, returning an error when its
+/// WIT signature is fallible, so that the error can be handled by the engine,
+/// and trapping otherwise
+fn stub_wasi_unsupported(
+    module: &mut StubModule,
+    import: &str,
+    name: &str,
+) -> Result<Option<FunctionID>> {
+    let mut stubbed = None;
+    let mut present = false;
+    for full_import in wasi_import_versions(module, import)? {
+        if !is_imported(module, &full_import, name) {
+            continue;
+        }
+        present = true;
+        stubbed = stub_unsupported(module, &full_import, name)?.or(stubbed);
+    }
+    if !present {
+        module.record(import, name, ImportStatus::NotPresent);
+    }
+    Ok(stubbed)
+}
+
+/// Stubs all functions imported from the WASI 0.3 versions of an interface as
+/// unsupported, for interfaces whose functions differ from WASI 0.2
+fn stub_wasip3_unsupported(module: &mut StubModule, import: &str) -> Result<()> {
+    let functions = module
+        .imports
+        .iter()
+        .filter(|func| matches!(func.ty, TypeRef::Func(_)))
+        .filter(|func| {
+            parse_versioned_import(&func.module).is_some_and(|(interface, version)| {
+                interface == import && version.is_ok_and(|version| is_wasip3_version(&version))
+            })
+        })
+        .map(|func| (func.module.to_string(), func.name.to_string()))
+        .collect::<Vec<_>>();
+    for (full_import, name) in functions {
+        stub_unsupported(module, &full_import, &name)?;
+    }
+    Ok(())
+}
+
+fn stub_unsupported(
+    module: &mut StubModule,
+    full_import: &str,
+    name: &str,
+) -> Result<Option<FunctionID>> {
+    let fallible = module
+        .signatures
+        .as_ref()
+        .is_some_and(|signatures| signatures.is_fallible(full_import, name));
+    if fallible {
+        let stub = error_stub(module, module.signatures.as_ref(), full_import, name, None)?;
+        stub_import(module, full_import, name, StubBehavior::ReturnError, stub)
+    } else {
+        stub_import(
+            module,
+            full_import,
+            name,
+            StubBehavior::Unreachable,
+            unreachable_stub,
+        )
+    }
+}
+
+/// Creates a stub returning constant core values
+fn custom_stub(
+    module: &Module,
+    import: &str,
+    name: &str,
+    values: &[serde_json::Number],
+) -> Result<impl Fn(&mut FunctionBuilder) -> Result<Vec<LocalID>>> {
+    let (_, results) = import_type(module, import, name)
+        .with_context(|| format!("'{import}#{name}' is not an imported function"))?;
+    if results.len() != values.len() {
+        bail!(
+            "'{import}#{name}' returns {} core values, but {} were given",
+            results.len(),
+            values.len()
+        );
+    }
+    let values = results
+        .iter()
+        .zip(values)
+        .map(|(ty, value)| {
+            let int = value.as_i64().or(value.as_u64().map(|value| value as i64));
+            match ty {
+                DataType::I32 => int.map(|value| Value::I32(value as i32)),
+                DataType::I64 => int.map(Value::I64),
+                DataType::F32 => value.as_f64().map(|value| Value::F32(value as f32)),
+                DataType::F64 => value.as_f64().map(Value::F64),
+                _ => None,
+            }
+            .with_context(|| format!("invalid {ty:?} result {value} for '{import}#{name}'"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(move |body: &mut FunctionBuilder| {
+        for value in &values {
+            match value {
+                Value::I32(value) => body.i32_const(*value),
+                Value::I64(value) => body.i64_const(*value),
+                Value::F32(value) => body.f32_const(*value),
+                Value::F64(value) => body.f64_const(*value),
+                _ => unreachable!(),
+            };
+        }
+        Ok(vec![])
+    })
+}
+
+/// Stubs the imported functions with an action in the stub policy, before the
+/// stubs of the disabled features are applied
+fn apply_stub_policy(module: &mut StubModule) -> Result<()> {
+    module.reason = StubReason::Policy;
+    let imports = module
+        .imports
+        .iter()
+        .enumerate()
+        .filter(|(_, import)| matches!(import.ty, TypeRef::Func(_)))
+        .map(|(idx, import)| {
+            (
+                ImportsID(idx as u32),
+                import.module.to_string(),
+                import.name.to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    for (iid, import, name) in imports {
+        let Some(action) = module.policy.action(&import, &name).cloned() else {
+            continue;
+        };
+        match action {
+            StubAction::Allow => {}
+            StubAction::Unreachable => {
+                replace_import(
+                    module,
+                    iid,
+                    &import,
+                    &name,
+                    StubBehavior::Unreachable,
+                    unreachable_stub,
+                )?;
+            }
+            StubAction::ReturnError { code } => {
+                let stub = error_stub(
+                    module,
+                    module.signatures.as_ref(),
+                    &import,
+                    &name,
+                    code.as_deref(),
+                )?;
+                replace_import(module, iid, &import, &name, StubBehavior::ReturnError, stub)?;
+            }
+            StubAction::Custom { results } => {
+                let stub = custom_stub(module, &import, &name, &results)?;
+                replace_import(module, iid, &import, &name, StubBehavior::Custom, stub)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn stub_wasi(
     wasm: Vec<u8>,
     features: Vec<Feature>,
     wit_source: Option<String>,
     wit_path: Option<String>,
     world_name: Option<String>,
-) -> Result<Vec<u8>> {
+    options: StubOptions,
+) -> Result<StubResult> {
+    let StubOptions {
+        random_seed,
+        clock_epoch,
+        stdio_capture,
+        stub_policy,
+    } = options;
+
     let (resolve, ids) = if let Some(wit_source) = wit_source {
         let mut resolve = Resolve::default();
         let path = PathBuf::from("component.wit");
@@ -117,26 +732,73 @@ pub fn stub_wasi(
     let mut target_world_imports = HashSet::new();
 
     for (key, _) in &target_world.imports {
-        target_world_imports.insert(resolve.name_canonicalized_world_key(key));
+        let name = resolve.name_world_key(key);
+        match parse_versioned_import(&name) {
+            Some((interface, Ok(version))) if is_supported_wasi_version(&version) => {
+                target_world_imports.insert(interface.to_string());
+            }
+            Some((interface, _)) if interface.starts_with("wasi:") => {
+                bail!(
+                    "the target world imports '{name}', but only WASI 0.2.x and 0.3.x \
+                     imports are supported when stubbing"
+                );
+            }
+            _ => {
+                target_world_imports.insert(name);
+            }
+        }
     }
 
-    let mut module = Module::parse(wasm.as_slice(), false).unwrap();
+    let seed = match random_seed {
+        None => 0,
+        Some(RandomSeed::Fixed(seed)) => seed,
+        Some(RandomSeed::ContentHash) => content_hash(&wasm),
+    };
+
+    let policy = stub_policy
+        .map(|policy| StubPolicy::from_json(&policy))
+        .transpose()?
+        .unwrap_or_default();
+    let mut module = StubModule::new(&wasm, policy, StubReason::Policy);
+
+    apply_stub_policy(&mut module)?;
 
+    module.reason = StubReason::Engine;
     stub_preview1(&mut module)?;
 
     stub_filesystem(&mut module, &target_world_imports)?;
     stub_cli(&mut module, &target_world_imports)?;
 
     if !features.contains(&Feature::Random) {
-        stub_random(&mut module)?;
+        module.reason = StubReason::Feature(Feature::Random);
+        stub_random(&mut module, seed)?;
     }
 
+    let has_io = features.contains(&Feature::Clocks)
+        || features.contains(&Feature::Stdio)
+        || features.contains(&Feature::Http)
+        || target_world_requires_io(&target_world_imports);
+
     if !features.contains(&Feature::Clocks) {
-        stub_clocks(&mut module)?;
+        module.reason = StubReason::Feature(Feature::Clocks);
+        stub_clocks(&mut module, clock_epoch, !has_io)?;
     }
 
+    let mut capture_section = None;
     if !features.contains(&Feature::Stdio) {
-        stub_stdio(&mut module)?;
+        module.reason = StubReason::Feature(Feature::Stdio);
+        if let Some(capacity) = stdio_capture {
+            if target_world
+                .exports
+                .keys()
+                .any(|key| matches!(key, WorldKey::Name(name) if name == DRAIN_LOGS))
+            {
+                bail!("the target world already exports '{DRAIN_LOGS}'");
+            }
+            capture_stdio(&mut module, capacity)?;
+            capture_section = Some(capture_stdio_section()?);
+        }
+        stub_stdio(&mut module, capture_section.is_some())?;
     }
 
     match (
@@ -145,6 +807,7 @@ pub fn stub_wasi(
     ) {
         // If both are disabled, then disable all HTTP related imports
         (false, false) => {
+            module.reason = StubReason::Feature(Feature::Http);
             stub_http_types(&mut module)?;
             stub_http_outgoing(&mut module)?;
         }
@@ -154,49 +817,77 @@ pub fn stub_wasi(
         // Note that we cannot *know* that the user will make use of fetch-event, but we must be prepared
         // for it, as the feature is enabled.
         (false, true) => {
+            module.reason = StubReason::Feature(Feature::Http);
             stub_http_outgoing(&mut module)?;
         }
         // For all other cases we can avoid stubbing
         _ => {}
     }
 
-    let has_io = features.contains(&Feature::Clocks)
-        || features.contains(&Feature::Stdio)
-        || features.contains(&Feature::Http)
-        || target_world_requires_io(&target_world_imports);
     if !has_io {
+        module.reason = StubReason::Engine;
         stub_io(&mut module)?;
     }
 
     stub_sockets(&mut module, &target_world_imports)?;
-    Ok(module.encode())
+
+    module.reason = StubReason::Engine;
+    for (import, name) in eliminate_dead_code(&mut module) {
+        module.record_removed(&import, &name);
+    }
+    module.report_kept(&features, &target_world_imports);
+
+    let mut wasm = module.module.encode();
+    if let Some(section) = capture_section {
+        wasm.push(section.id());
+        section.encode(&mut wasm);
+    }
+    Ok(StubResult {
+        wasm,
+        report: module.report,
+    })
 }
 
 fn target_world_requires_io(target_world_imports: &HashSet<String>) -> bool {
-    target_world_imports.contains("wasi:sockets/instance-network@0.2")
-        || target_world_imports.contains("wasi:sockets/udp@0.2")
-        || target_world_imports.contains("wasi:sockets/udp-create-socket@0.2")
-        || target_world_imports.contains("wasi:sockets/tcp@0.2")
-        || target_world_imports.contains("wasi:sockets/tcp-create-socket@0.2")
-        || target_world_imports.contains("wasi:sockets/ip-name-lookup@0.2")
-        || target_world_imports.contains("wasi:sockets/network@0.2")
-        || target_world_imports.contains("wasi:filesystem/types@0.2")
-        || target_world_imports.contains("wasi:filesystem/preopens@0.2")
-        || target_world_imports.contains("wasi:cli/terminal-stdin@0.2")
-        || target_world_imports.contains("wasi:cli/terminal-stdout@0.2")
-        || target_world_imports.contains("wasi:cli/terminal-stderr@0.2")
-        || target_world_imports.contains("wasi:cli/terminal-input@0.2")
-        || target_world_imports.contains("wasi:cli/terminal-output@0.2")
+    target_world_imports.contains("wasi:sockets/instance-network")
+        || target_world_imports.contains("wasi:sockets/udp")
+        || target_world_imports.contains("wasi:sockets/udp-create-socket")
+        || target_world_imports.contains("wasi:sockets/tcp")
+        || target_world_imports.contains("wasi:sockets/tcp-create-socket")
+        || target_world_imports.contains("wasi:sockets/ip-name-lookup")
+        || target_world_imports.contains("wasi:sockets/network")
+        || target_world_imports.contains("wasi:sockets/types")
+        || target_world_imports.contains("wasi:filesystem/types")
+        || target_world_imports.contains("wasi:filesystem/preopens")
+        || target_world_imports.contains("wasi:cli/terminal-stdin")
+        || target_world_imports.contains("wasi:cli/terminal-stdout")
+        || target_world_imports.contains("wasi:cli/terminal-stderr")
+        || target_world_imports.contains("wasi:cli/terminal-input")
+        || target_world_imports.contains("wasi:cli/terminal-output")
 }
 
 const PREVIEW1: &str = "wasi_snapshot_preview1";
-fn stub_preview1(module: &mut Module) -> Result<()> {
+fn stub_preview1(module: &mut StubModule) -> Result<()> {
     // random comes from prevew2 only in StarlingMonkey
-    stub_import(module, PREVIEW1, "random_get", unreachable_stub)?;
+    stub_import(
+        module,
+        PREVIEW1,
+        "random_get",
+        StubBehavior::Unreachable,
+        unreachable_stub,
+    )?;
     Ok(())
 }
 
-fn stub_random(module: &mut Module) -> Result<()> {
+/// FNV-1a hash of the module contents, used to derive a random seed that is
+/// stable for a given build
+fn content_hash(wasm: &[u8]) -> u64 {
+    wasm.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn stub_random(module: &mut StubModule, seed: u64) -> Result<()> {
     let memory = module.get_memory_id().unwrap();
     let realloc = module
         .exports
@@ -204,29 +895,32 @@ fn stub_random(module: &mut Module) -> Result<()> {
         .unwrap();
     // stubbed random implements random with a pseudorandom implementation
     // create a mutable random seed global
-    let seed_val: i64 = 0;
     let seed_global = module.add_global(
-        InitExpr::new(vec![InitInstr::Value(Value::I64(seed_val))]),
+        InitExpr::new(vec![InitInstr::Value(Value::I64(seed as i64))]),
         DataType::I64,
         true,
         false,
     );
 
-    let random_u64 = stub_wasi_imports(module, "wasi:random/random", "get-random-u64", |func| {
-        func.global_get(seed_global);
-        func.i64_const(-0x5F89E29B87429BD1);
-        func.i64_add();
-        func.global_set(seed_global);
-        func.global_get(seed_global);
-        func.global_get(seed_global);
-        func.i64_const(-0x18FC812E5F4BD725);
-        func.i64_xor();
-        func.i64_mul();
+    // the generator shared by the secure and insecure random stubs
+    let mut next_u64 = FunctionBuilder::new(&[], &[DataType::I64]);
+    next_u64.global_get(seed_global);
+    next_u64.i64_const(-0x5F89E29B87429BD1);
+    next_u64.i64_add();
+    next_u64.global_set(seed_global);
+    next_u64.global_get(seed_global);
+    next_u64.global_get(seed_global);
+    next_u64.i64_const(-0x18FC812E5F4BD725);
+    next_u64.i64_xor();
+    next_u64.i64_mul();
+    let next_u64 = next_u64.finish_module(module);
+
+    let random_u64 = |func: &mut FunctionBuilder| {
+        func.call(next_u64);
         Ok(vec![])
-    })?
-    .expect("get-random-u64 not found");
+    };
 
-    stub_wasi_imports(module, "wasi:random/random", "get-random-bytes", |body| {
+    let random_bytes = |body: &mut FunctionBuilder| {
         // let num_bytes = body.add_local(DataType::I64);
         let num_bytes: LocalID = LocalID(0); // First parameter
         let retptr: LocalID = LocalID(1); // Second parametr
@@ -277,7 +971,7 @@ fn stub_random(module: &mut Module) -> Result<()> {
         // list
         body.loop_stmt(BlockType::Empty);
         body.local_get(curptr);
-        body.call(random_u64);
+        body.call(next_u64);
         body.i64_store(MemArg {
             align: 3,
             max_align: 0,
@@ -296,1296 +990,1867 @@ fn stub_random(module: &mut Module) -> Result<()> {
         body.br_if(0);
         body.end(); // This is for the loop
         Ok(vec![num_bytes, retptr])
-    })?;
+    };
 
+    stub_wasi_imports(module, "wasi:random/random", "get-random-u64", random_u64)?;
     stub_wasi_imports(
         module,
-        "wasi:random/insecure",
-        "get-insecure-random-bytes",
-        unreachable_stub,
+        "wasi:random/random",
+        "get-random-bytes",
+        random_bytes,
     )?;
     stub_wasi_imports(
         module,
         "wasi:random/insecure",
         "get-insecure-random-u64",
-        unreachable_stub,
+        random_u64,
+    )?;
+    stub_wasi_imports(
+        module,
+        "wasi:random/insecure",
+        "get-insecure-random-bytes",
+        random_bytes,
     )?;
+
+    // (func (param i32)), storing the tuple<u64, u64> at the retptr
     stub_wasi_imports(
         module,
         "wasi:random/insecure-seed",
         "insecure-seed",
-        unreachable_stub,
+        |body| {
+            let retptr: LocalID = LocalID(0);
+            for offset in [0, 8] {
+                body.local_get(retptr);
+                body.call(next_u64);
+                body.i64_store(MemArg {
+                    align: 3,
+                    max_align: 0,
+                    offset,
+                    memory: *memory,
+                });
+            }
+            Ok(vec![retptr])
+        },
     )?;
     Ok(())
 }
 
-fn stub_clocks(module: &mut Module) -> Result<()> {
+/// Resolution of the virtual clock, by which it advances on every read
+const CLOCK_RESOLUTION_NS: i64 = 1_000_000;
+/// The status returned by an async lowered import which completed without
+/// blocking, the `returned` subtask state in the upper bits with no subtask index
+const SUBTASK_RETURNED: i32 = 3 << 30;
+const NS_PER_SECOND: i64 = 1_000_000_000;
+
+/// Stubs the clocks with a virtual clock, starting at the given epoch in seconds
+/// (or the build time) and advancing by its resolution on every read.
+///
+/// Subscriptions advance the virtual clock and return a pollable which is
+/// immediately ready. These pollables are only valid when the io interfaces are
+/// stubbed as well, otherwise subscriptions remain unreachable. The WASI 0.3
+/// waits advance the virtual clock and return immediately.
+fn stub_clocks(module: &mut StubModule, epoch: Option<u64>, stub_pollables: bool) -> Result<()> {
     let memory = module.get_memory_id().unwrap();
-    stub_import(module, PREVIEW1, "clock_res_get", unreachable_stub)?;
 
-    // stub the time with the current time at build time
-    let time = SystemTime::now();
-    let unix_time = time.duration_since(UNIX_EPOCH)?;
+    let epoch_ns = match epoch {
+        Some(epoch) => i64::try_from(epoch)
+            .ok()
+            .and_then(|epoch| epoch.checked_mul(NS_PER_SECOND))
+            .ok_or_else(|| anyhow::anyhow!("clock epoch {epoch} is out of range"))?,
+        None => i64::try_from(SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos())?,
+    };
+    let time_global = module.add_global(
+        InitExpr::new(vec![InitInstr::Value(Value::I64(epoch_ns))]),
+        DataType::I64,
+        true,
+        false,
+    );
+
+    // advances the virtual clock, returning the new time in nanoseconds
+    let mut tick = FunctionBuilder::new(&[], &[DataType::I64]);
+    tick.global_get(time_global);
+    tick.i64_const(CLOCK_RESOLUTION_NS);
+    tick.i64_add();
+    tick.global_set(time_global);
+    tick.global_get(time_global);
+    let tick = tick.finish_module(module);
+
+    // (func (param i32 i32) (result i32)))
+    stub_import(
+        module,
+        PREVIEW1,
+        "clock_res_get",
+        StubBehavior::Emulated,
+        |body| {
+            let res_ptr: LocalID = LocalID(1); // Second Parameter
+            body.local_get(res_ptr);
+            body.i64_const(CLOCK_RESOLUTION_NS);
+            body.i64_store(MemArg {
+                align: 3,
+                offset: 0,
+                max_align: 0,
+                memory: *memory,
+            });
+            body.i32_const(0);
+            Ok(vec![res_ptr])
+        },
+    )?;
 
     // (func (param i32 i64 i32) (result i32)))
-    stub_import(module, PREVIEW1, "clock_time_get", |body| {
-        let clock_id: LocalID = LocalID(0); // First Parameter
-        let precision: LocalID = LocalID(1); // Second Parameter
-        let time_ptr: LocalID = LocalID(2); // Third Parameter
-        body.local_get(time_ptr);
-        body.local_get(time_ptr);
-        body.i64_const(i64::try_from(unix_time.as_nanos())?);
-        body.i64_store(MemArg {
-            align: 3,
-            offset: 0,
-            max_align: 0,
-            memory: *memory,
-        });
-        Ok(vec![clock_id, precision, time_ptr])
-    })?;
+    stub_import(
+        module,
+        PREVIEW1,
+        "clock_time_get",
+        StubBehavior::Emulated,
+        |body| {
+            let time_ptr: LocalID = LocalID(2); // Third Parameter
+            body.local_get(time_ptr);
+            body.call(tick);
+            body.i64_store(MemArg {
+                align: 3,
+                offset: 0,
+                max_align: 0,
+                memory: *memory,
+            });
+            body.i32_const(0);
+            Ok(vec![time_ptr])
+        },
+    )?;
 
     stub_wasi_imports(module, "wasi:clocks/monotonic-clock", "now", |body| {
-        body.i64_const(i64::try_from(unix_time.as_nanos())?);
+        body.call(tick);
         Ok(vec![])
     })?;
-    stub_wasi_imports(
-        module,
-        "wasi:clocks/monotonic-clock",
-        "subscribe-instant",
-        unreachable_stub,
-    )?;
     stub_wasi_imports(
         module,
         "wasi:clocks/monotonic-clock",
         "resolution",
-        unreachable_stub,
+        |body| {
+            body.i64_const(CLOCK_RESOLUTION_NS);
+            Ok(vec![])
+        },
     )?;
+
+    // (func (param i32)), storing the datetime record at the retptr
+    let store_datetime = |body: &mut FunctionBuilder, retptr: LocalID, ns: LocalID| {
+        body.local_get(retptr);
+        body.local_get(ns);
+        body.i64_const(NS_PER_SECOND);
+        body.i64_div_unsigned();
+        body.i64_store(MemArg {
+            align: 3,
+            offset: 0,
+            max_align: 0,
+            memory: *memory,
+        });
+        body.local_get(retptr);
+        body.local_get(ns);
+        body.i64_const(NS_PER_SECOND);
+        body.i64_rem_unsigned();
+        body.i32_wrap_i64();
+        body.i32_store(MemArg {
+            align: 2,
+            offset: 8,
+            max_align: 0,
+            memory: *memory,
+        });
+    };
+    stub_wasi_imports(module, "wasi:clocks/wall-clock", "now", |body| {
+        let retptr: LocalID = LocalID(0);
+        let ns = body.add_local(DataType::I64);
+        body.call(tick);
+        body.local_set(ns);
+        store_datetime(body, retptr, ns);
+        Ok(vec![retptr])
+    })?;
+    stub_wasi_imports(module, "wasi:clocks/wall-clock", "resolution", |body| {
+        let retptr: LocalID = LocalID(0);
+        let ns = body.add_local(DataType::I64);
+        body.i64_const(CLOCK_RESOLUTION_NS);
+        body.local_set(ns);
+        store_datetime(body, retptr, ns);
+        Ok(vec![retptr])
+    })?;
+
+    // advances the clock by the duration
+    let advance_by = |body: &mut FunctionBuilder, duration: LocalID| {
+        body.global_get(time_global);
+        body.local_get(duration);
+        body.i64_add();
+        body.global_set(time_global);
+    };
+    // advances the clock to the instant, unless it is in the past
+    let advance_to = |body: &mut FunctionBuilder, instant: LocalID| {
+        body.local_get(instant);
+        body.global_get(time_global);
+        body.local_get(instant);
+        body.global_get(time_global);
+        body.i64_gt_unsigned();
+        body.select();
+        body.global_set(time_global);
+    };
+
+    // (func (param i64)) when lowered synchronously, and
+    // (func (param i32 i32) (result i32)) taking a pointer to the parameter and
+    // returning the subtask status when lowered asynchronously
+    for (wait, to_instant) in [("wait-for", false), ("wait-until", true)] {
+        let advance = |body: &mut FunctionBuilder, param: LocalID| match to_instant {
+            false => advance_by(body, param),
+            true => advance_to(body, param),
+        };
+        for name in [wait.to_string(), format!("[async]{wait}")] {
+            stub_wasi_imports(module, "wasi:clocks/monotonic-clock", &name, |body| {
+                let param: LocalID = LocalID(0);
+                advance(body, param);
+                Ok(vec![param])
+            })?;
+            stub_wasi_imports(
+                module,
+                "wasi:clocks/monotonic-clock",
+                &format!("[async-lower]{name}"),
+                |body| {
+                    let params_ptr: LocalID = LocalID(0);
+                    let param = body.add_local(DataType::I64);
+                    body.local_get(params_ptr);
+                    body.i64_load(MemArg {
+                        align: 3,
+                        max_align: 0,
+                        offset: 0,
+                        memory: *memory,
+                    });
+                    body.local_set(param);
+                    advance(body, param);
+                    body.i32_const(SUBTASK_RETURNED);
+                    Ok(vec![params_ptr])
+                },
+            )?;
+        }
+    }
+
+    if !stub_pollables {
+        stub_wasi_unsupported(module, "wasi:clocks/monotonic-clock", "subscribe-instant")?;
+        stub_wasi_unsupported(module, "wasi:clocks/monotonic-clock", "subscribe-duration")?;
+        return Ok(());
+    }
+
+    // (func (param i64) (result i32)), advancing the clock by the duration
     stub_wasi_imports(
         module,
         "wasi:clocks/monotonic-clock",
         "subscribe-duration",
-        unreachable_stub,
+        |body| {
+            let duration: LocalID = LocalID(0);
+            advance_by(body, duration);
+            body.i32_const(0);
+            Ok(vec![duration])
+        },
     )?;
-    stub_wasi_imports(module, "wasi:clocks/wall-clock", "now", unreachable_stub)?;
+    // (func (param i64) (result i32)), advancing the clock to the instant
     stub_wasi_imports(
         module,
-        "wasi:clocks/wall-clock",
-        "resolution",
-        unreachable_stub,
-    )?;
-
-    Ok(())
+        "wasi:clocks/monotonic-clock",
+        "subscribe-instant",
+        |body| {
+            let instant: LocalID = LocalID(0);
+            advance_to(body, instant);
+            body.i32_const(0);
+            Ok(vec![instant])
+        },
+    )?;
+    stub_ready_pollables(module)
 }
 
-fn stub_stdio(module: &mut Module) -> Result<()> {
-    // (func (param i32 i32) (result i32)))
-    stub_import(module, PREVIEW1, "fd_fdstat_get", |body| {
-        body.i32_const(0);
+/// Stubs the pollables of the virtual clock, which are always ready
+fn stub_ready_pollables(module: &mut StubModule) -> Result<()> {
+    let memory = module.get_memory_id().unwrap();
+    let realloc = module
+        .exports
+        .get_func_by_name("cabi_realloc".to_string())
+        .unwrap();
+
+    stub_wasi_imports(module, "wasi:io/poll", "[method]pollable.ready", |body| {
+        body.i32_const(1);
         Ok(vec![])
     })?;
-
-    // (func (param i32 i32 i32 i32) (result i32)))
-    stub_import(module, PREVIEW1, "fd_write", |body| {
-        let len_local: LocalID = LocalID(3); // Index of the last local
-        body.local_get(len_local);
-        Ok(vec![len_local])
+    stub_wasi_imports(module, "wasi:io/poll", "[method]pollable.block", |_| {
+        Ok(vec![])
+    })?;
+    stub_wasi_imports(module, "wasi:io/poll", "[resource-drop]pollable", |_| {
+        Ok(vec![])
     })?;
 
-    stub_wasi_imports(module, "wasi:cli/stdin", "get-stdin", unreachable_stub)?;
-    stub_wasi_imports(module, "wasi:cli/stdout", "get-stdout", unreachable_stub)?;
-    stub_wasi_imports(module, "wasi:cli/stderr", "get-stderr", unreachable_stub)?;
+    // (func (param i32 i32 i32)), returning the indices of all of the pollables
+    stub_wasi_imports(module, "wasi:io/poll", "poll", |body| {
+        let len: LocalID = LocalID(1);
+        let retptr: LocalID = LocalID(2);
+        let outptr = body.add_local(DataType::I32);
+        let idx = body.add_local(DataType::I32);
+
+        // outptr = realloc(0, 0, 4, len * 4)
+        body.i32_const(0);
+        body.i32_const(0);
+        body.i32_const(4);
+        body.local_get(len);
+        body.i32_const(4);
+        body.i32_mul();
+        body.call(realloc);
+        body.local_set(outptr);
+
+        body.block(BlockType::Empty);
+        body.local_get(len);
+        body.i32_eqz();
+        body.br_if(0);
+        body.loop_stmt(BlockType::Empty);
+        // outptr[idx] = idx
+        body.local_get(outptr);
+        body.local_get(idx);
+        body.i32_const(4);
+        body.i32_mul();
+        body.i32_add();
+        body.local_get(idx);
+        body.i32_store(MemArg {
+            align: 2,
+            max_align: 0,
+            offset: 0,
+            memory: *memory,
+        });
+        body.local_get(idx);
+        body.i32_const(1);
+        body.i32_add();
+        body.local_tee(idx);
+        body.local_get(len);
+        body.i32_lt_unsigned();
+        body.br_if(0);
+        body.end(); // This is for the loop
+        body.end(); // This is for the block
+
+        // *retptr = outptr, *retptr + 1 = len
+        body.local_get(retptr);
+        body.local_get(outptr);
+        body.i32_store(MemArg {
+            align: 2,
+            max_align: 0,
+            offset: 0,
+            memory: *memory,
+        });
+        body.local_get(retptr);
+        body.local_get(len);
+        body.i32_store(MemArg {
+            align: 2,
+            max_align: 0,
+            offset: 4,
+            memory: *memory,
+        });
+        Ok(vec![len, retptr])
+    })?;
     Ok(())
 }
 
-fn stub_http_outgoing(module: &mut Module) -> Result<()> {
-    stub_wasi_imports(
-        module,
-        "wasi:http/outgoing-handler",
-        "handle",
-        unreachable_stub,
-    )?;
+/// The world export through which captured stdio is read
+const DRAIN_LOGS: &str = "drain-logs";
+
+/// The preview1 `badf` errno, for writes to descriptors which are not captured
+const ERRNO_BADF: i32 = 8;
+
+/// Captures the output written to stdout and stderr through `fd_write` into a
+/// ring buffer of the given capacity, keeping the most recent output, which is
+/// read and cleared by the exported `drain-logs` function. Writes to other
+/// descriptors fail with `badf`, and the WASI 0.2 `get-stdout` and `get-stderr`
+/// streams are not captured, remaining unsupported.
+///
+/// The ring buffer, the buffer returned by `drain-logs` and its return area
+/// are allocated together on first use.
+fn capture_stdio(module: &mut StubModule, capacity: u32) -> Result<()> {
+    if capacity == 0 {
+        bail!("stdio capture capacity must be greater than zero");
+    }
+    let capacity = i32::try_from(capacity.next_multiple_of(4))?;
+    let memory = module.get_memory_id().unwrap();
+    let realloc = module
+        .exports
+        .get_func_by_name("cabi_realloc".to_string())
+        .unwrap();
+    let mem_arg = |align: u32, offset: u64| MemArg {
+        align: align as u8,
+        max_align: 0,
+        offset,
+        memory: *memory,
+    };
+
+    let new_global = |module: &mut StubModule| {
+        module.add_global(
+            InitExpr::new(vec![InitInstr::Value(Value::I32(0))]),
+            DataType::I32,
+            true,
+            false,
+        )
+    };
+    let buf_global = new_global(module);
+    let head_global = new_global(module);
+    let len_global = new_global(module);
+
+    // returns the capture buffer, allocating it on first use
+    let mut buffer = FunctionBuilder::new(&[], &[DataType::I32]);
+    buffer.global_get(buf_global);
+    buffer.i32_eqz();
+    buffer.if_stmt(BlockType::Empty);
+    buffer.i32_const(0);
+    buffer.i32_const(0);
+    buffer.i32_const(4);
+    buffer.i32_const(capacity * 2 + 8);
+    buffer.call(realloc);
+    buffer.global_set(buf_global);
+    buffer.end();
+    buffer.global_get(buf_global);
+    let buffer = buffer.finish_module(module);
+
+    // (func (param i32 i32 i32 i32) (result i32)))
+    stub_import(
+        module,
+        PREVIEW1,
+        "fd_write",
+        StubBehavior::Emulated,
+        |body| {
+            let fd: LocalID = LocalID(0);
+            let iovs: LocalID = LocalID(1);
+            let iovs_len: LocalID = LocalID(2);
+            let nwritten: LocalID = LocalID(3);
+            let buf = body.add_local(DataType::I32);
+            let iov = body.add_local(DataType::I32);
+            let ptr = body.add_local(DataType::I32);
+            let len = body.add_local(DataType::I32);
+            let first = body.add_local(DataType::I32);
+            let total = body.add_local(DataType::I32);
+
+            // only stdout and stderr are captured
+            body.local_get(fd);
+            body.i32_const(1);
+            body.i32_sub();
+            body.i32_const(1);
+            body.i32_gt_unsigned();
+            body.if_stmt(BlockType::Empty);
+            body.i32_const(ERRNO_BADF);
+            body.return_stmt();
+            body.end();
+
+            body.call(buffer);
+            body.local_set(buf);
+
+            // for each iov
+            body.block(BlockType::Empty);
+            body.loop_stmt(BlockType::Empty);
+            body.local_get(iov);
+            body.local_get(iovs_len);
+            body.i32_gte_unsigned();
+            body.br_if(1);
+
+            // ptr, len = iovs[iov]
+            body.local_get(iovs);
+            body.local_get(iov);
+            body.i32_const(8);
+            body.i32_mul();
+            body.i32_add();
+            body.local_tee(ptr);
+            body.i32_load(mem_arg(2, 4));
+            body.local_tee(len);
+            body.local_get(total);
+            body.i32_add();
+            body.local_set(total);
+            body.local_get(ptr);
+            body.i32_load(mem_arg(2, 0));
+            body.local_set(ptr);
+
+            // only the last capacity bytes of the write are kept
+            body.local_get(len);
+            body.i32_const(capacity);
+            body.i32_gt_unsigned();
+            body.if_stmt(BlockType::Empty);
+            body.local_get(ptr);
+            body.local_get(len);
+            body.i32_add();
+            body.i32_const(capacity);
+            body.i32_sub();
+            body.local_set(ptr);
+            body.i32_const(capacity);
+            body.local_set(len);
+            body.end();
+
+            // first = min(len, capacity - head), the bytes before wrapping
+            body.local_get(len);
+            body.i32_const(capacity);
+            body.global_get(head_global);
+            body.i32_sub();
+            body.local_tee(first);
+            body.local_get(len);
+            body.local_get(first);
+            body.i32_lt_unsigned();
+            body.select();
+            body.local_set(first);
+
+            // copy ptr[..first] to buf[head..], and ptr[first..len] to buf[..]
+            body.local_get(buf);
+            body.global_get(head_global);
+            body.i32_add();
+            body.local_get(ptr);
+            body.local_get(first);
+            body.memory_copy(*memory, *memory);
+            body.local_get(buf);
+            body.local_get(ptr);
+            body.local_get(first);
+            body.i32_add();
+            body.local_get(len);
+            body.local_get(first);
+            body.i32_sub();
+            body.memory_copy(*memory, *memory);
+
+            // head = (head + len) % capacity
+            body.global_get(head_global);
+            body.local_get(len);
+            body.i32_add();
+            body.i32_const(capacity);
+            body.i32_rem_unsigned();
+            body.global_set(head_global);
+            // buffered = min(buffered + len, capacity)
+            body.global_get(len_global);
+            body.local_get(len);
+            body.i32_add();
+            body.local_tee(len);
+            body.i32_const(capacity);
+            body.local_get(len);
+            body.i32_const(capacity);
+            body.i32_lt_unsigned();
+            body.select();
+            body.global_set(len_global);
+
+            body.local_get(iov);
+            body.i32_const(1);
+            body.i32_add();
+            body.local_set(iov);
+            body.br(0);
+            body.end(); // This is for the loop
+            body.end(); // This is for the block
+
+            // *nwritten = total
+            body.local_get(nwritten);
+            body.local_get(total);
+            body.i32_store(mem_arg(2, 0));
+            body.i32_const(0);
+            Ok(vec![fd, iovs, iovs_len, nwritten])
+        },
+    )?;
+
+    // drain-logs: func() -> list<u8>
+    let mut drain = FunctionBuilder::new(&[], &[DataType::I32]);
+    let buf = drain.add_local(DataType::I32);
+    let start = drain.add_local(DataType::I32);
+    let first = drain.add_local(DataType::I32);
+    drain.call(buffer);
+    drain.local_set(buf);
+
+    // start = (head - len + capacity) % capacity
+    drain.global_get(head_global);
+    drain.global_get(len_global);
+    drain.i32_sub();
+    drain.i32_const(capacity);
+    drain.i32_add();
+    drain.i32_const(capacity);
+    drain.i32_rem_unsigned();
+    drain.local_set(start);
+
+    // first = min(len, capacity - start), the bytes before wrapping
+    drain.global_get(len_global);
+    drain.i32_const(capacity);
+    drain.local_get(start);
+    drain.i32_sub();
+    drain.local_tee(first);
+    drain.global_get(len_global);
+    drain.local_get(first);
+    drain.i32_lt_unsigned();
+    drain.select();
+    drain.local_set(first);
+
+    // copy buf[start..start + first] and buf[..len - first] to the output
+    // buffer after the ring buffer
+    drain.local_get(buf);
+    drain.i32_const(capacity);
+    drain.i32_add();
+    drain.local_get(buf);
+    drain.local_get(start);
+    drain.i32_add();
+    drain.local_get(first);
+    drain.memory_copy(*memory, *memory);
+    drain.local_get(buf);
+    drain.i32_const(capacity);
+    drain.i32_add();
+    drain.local_get(first);
+    drain.i32_add();
+    drain.local_get(buf);
+    drain.global_get(len_global);
+    drain.local_get(first);
+    drain.i32_sub();
+    drain.memory_copy(*memory, *memory);
+
+    // the return area holds the list pointer and length
+    drain.local_get(buf);
+    drain.local_get(buf);
+    drain.i32_const(capacity);
+    drain.i32_add();
+    drain.i32_store(mem_arg(2, capacity as u64 * 2));
+    drain.local_get(buf);
+    drain.global_get(len_global);
+    drain.i32_store(mem_arg(2, capacity as u64 * 2 + 4));
+    drain.i32_const(0);
+    drain.global_set(len_global);
+    drain.local_get(buf);
+    drain.i32_const(capacity * 2);
+    drain.i32_add();
+    let drain = drain.finish_module(module);
+    module
+        .exports
+        .add_export_func(DRAIN_LOGS.to_string(), *drain);
     Ok(())
 }
 
-fn stub_http_types(module: &mut Module) -> Result<()> {
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "http-error-code",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[static]fields.from-list",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]fields.has",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+/// The component type section adding the `drain-logs` export to the world
+fn capture_stdio_section() -> Result<wasm_encoder::CustomSection<'static>> {
+    let mut resolve = Resolve::default();
+    let pkg = resolve.push_str(
+        "stdio-capture.wit",
+        &format!(
+            "package local:stdio-capture;\n\nworld stdio-capture {{\n  export {DRAIN_LOGS}: func() -> list<u8>;\n}}\n"
+        ),
+    )?;
+    let world = resolve.select_world(pkg, None)?;
+    let encoded = wit_component::metadata::encode(
+        &resolve,
+        world,
+        wit_component::StringEncoding::UTF8,
+        None,
+    )?;
+    Ok(wasm_encoder::CustomSection {
+        name: "component-type:stdio-capture".into(),
+        data: encoded.into(),
+    })
+}
+
+/// Stubs stdio, leaving `fd_write` to the stdio capture when it is enabled
+fn stub_stdio(module: &mut StubModule, captured: bool) -> Result<()> {
+    // (func (param i32 i32) (result i32)))
+    stub_import(
         module,
-        "wasi:http/types",
-        "[method]outgoing-request.method",
-        unreachable_stub,
+        PREVIEW1,
+        "fd_fdstat_get",
+        StubBehavior::Emulated,
+        |body| {
+            body.i32_const(0);
+            Ok(vec![])
+        },
     )?;
-    stub_wasi_imports(
+
+    // (func (param i32 i32 i32 i32) (result i32)))
+    if !captured {
+        stub_import(
+            module,
+            PREVIEW1,
+            "fd_write",
+            StubBehavior::Emulated,
+            |body| {
+                let len_local: LocalID = LocalID(3); // Index of the last local
+                body.local_get(len_local);
+                Ok(vec![len_local])
+            },
+        )?;
+    }
+
+    stub_wasi_unsupported(module, "wasi:cli/stdin", "get-stdin")?;
+    stub_wasi_unsupported(module, "wasi:cli/stdout", "get-stdout")?;
+    stub_wasi_unsupported(module, "wasi:cli/stderr", "get-stderr")?;
+    stub_wasip3_unsupported(module, "wasi:cli/stdin")?;
+    stub_wasip3_unsupported(module, "wasi:cli/stdout")?;
+    stub_wasip3_unsupported(module, "wasi:cli/stderr")?;
+    Ok(())
+}
+
+fn stub_http_outgoing(module: &mut StubModule) -> Result<()> {
+    stub_wasi_unsupported(module, "wasi:http/outgoing-handler", "handle")?;
+    // WASI 0.3 sends requests through an imported handler or client
+    stub_wasip3_unsupported(module, "wasi:http/handler")?;
+    stub_wasip3_unsupported(module, "wasi:http/client")?;
+    Ok(())
+}
+
+fn stub_http_types(module: &mut StubModule) -> Result<()> {
+    stub_wasip3_unsupported(module, "wasi:http/types")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "http-error-code")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[static]fields.from-list")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]fields.has")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]outgoing-request.method")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]outgoing-request.path-with-query",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]outgoing-request.scheme",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]outgoing-request.scheme")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]outgoing-request.authority",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]outgoing-request.headers",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[constructor]request-options",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[constructor]request-options")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]request-options.connect-timeout",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]request-options.set-connect-timeout",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]request-options.first-byte-timeout",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]request-options.set-first-byte-timeout",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]request-options.between-bytes-timeout",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]request-options.set-between-bytes-timeout",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[static]incoming-body.finish",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[static]incoming-body.finish")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]future-trailers.subscribe",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]future-trailers.get",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]future-trailers.get")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]outgoing-response.status-code",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[resource-drop]incoming-request",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[resource-drop]outgoing-request",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[resource-drop]request-options",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[resource-drop]incoming-request")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[resource-drop]outgoing-request")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[resource-drop]request-options")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[resource-drop]response-outparam",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[resource-drop]incoming-response",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[resource-drop]incoming-body",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[resource-drop]future-trailers",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[resource-drop]incoming-body")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[resource-drop]future-trailers")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[resource-drop]outgoing-response",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[resource-drop]outgoing-body",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[resource-drop]outgoing-body")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[resource-drop]future-incoming-response",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[resource-drop]fields",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[constructor]fields",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]fields.get",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]fields.set",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]fields.delete",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]fields.append",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]fields.entries",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]fields.clone",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]incoming-request.method",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[resource-drop]fields")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[constructor]fields")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]fields.get")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]fields.set")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]fields.delete")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]fields.append")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]fields.entries")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]fields.clone")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]incoming-request.method")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]incoming-request.path-with-query",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]incoming-request.scheme",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]incoming-request.scheme")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]incoming-request.authority",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]incoming-request.headers",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]incoming-request.consume",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[constructor]outgoing-request",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]outgoing-request.body",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[constructor]outgoing-request")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]outgoing-request.body")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]outgoing-request.set-method",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]outgoing-request.set-path-with-query",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]outgoing-request.set-scheme",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]outgoing-request.set-authority",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[static]response-outparam.set",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[static]response-outparam.set")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]incoming-response.status",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]incoming-response.headers",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]incoming-response.consume",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]incoming-body.stream",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[constructor]outgoing-response",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]incoming-body.stream")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[constructor]outgoing-response")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]outgoing-response.set-status-code",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]outgoing-response.headers",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]outgoing-response.body",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[method]outgoing-body.write",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:http/types",
-        "[static]outgoing-body.finish",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]outgoing-response.body")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[method]outgoing-body.write")?;
+    stub_wasi_unsupported(module, "wasi:http/types", "[static]outgoing-body.finish")?;
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]future-incoming-response.subscribe",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:http/types",
         "[method]future-incoming-response.get",
-        unreachable_stub,
     )?;
     Ok(())
 }
 
-fn stub_io(module: &mut Module) -> Result<()> {
-    stub_wasi_imports(
-        module,
-        "wasi:io/poll",
-        "[method]pollable.ready",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/poll",
-        "[method]pollable.block",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/error",
-        "[method]error.to-debug-string",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+/// Stubs io, other than `wasi:io/poll`, which the clocks stub always emulates
+/// as ready when io is stubbed
+fn stub_io(module: &mut StubModule) -> Result<()> {
+    stub_wasi_unsupported(module, "wasi:io/error", "[method]error.to-debug-string")?;
+    stub_wasi_unsupported(
         module,
         "wasi:io/streams",
         "[method]input-stream.blocking-read",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/streams",
-        "[method]input-stream.skip",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:io/streams", "[method]input-stream.skip")?;
+    stub_wasi_unsupported(
         module,
         "wasi:io/streams",
         "[method]input-stream.blocking-skip",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:io/streams",
         "[method]output-stream.blocking-write-and-flush",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/streams",
-        "[method]output-stream.flush",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:io/streams", "[method]output-stream.flush")?;
+    stub_wasi_unsupported(
         module,
         "wasi:io/streams",
         "[method]output-stream.write-zeroes",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(
         module,
         "wasi:io/streams",
         "[method]output-stream.blocking-write-zeroes-and-flush",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/streams",
-        "[method]output-stream.splice",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:io/streams", "[method]output-stream.splice")?;
+    stub_wasi_unsupported(
         module,
         "wasi:io/streams",
         "[method]output-stream.blocking-splice",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/error",
-        "[resource-drop]error",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/poll",
-        "[resource-drop]pollable",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/streams",
-        "[resource-drop]input-stream",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/streams",
-        "[resource-drop]output-stream",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(module, "wasi:io/poll", "poll", unreachable_stub)?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/streams",
-        "[method]input-stream.read",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/streams",
-        "[method]input-stream.subscribe",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:io/error", "[resource-drop]error")?;
+    stub_wasi_unsupported(module, "wasi:io/streams", "[resource-drop]input-stream")?;
+    stub_wasi_unsupported(module, "wasi:io/streams", "[resource-drop]output-stream")?;
+    stub_wasi_unsupported(module, "wasi:io/streams", "[method]input-stream.read")?;
+    stub_wasi_unsupported(module, "wasi:io/streams", "[method]input-stream.subscribe")?;
+    stub_wasi_unsupported(
         module,
         "wasi:io/streams",
         "[method]output-stream.check-write",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/streams",
-        "[method]output-stream.write",
-        unreachable_stub,
     )?;
-    stub_wasi_imports(
+    stub_wasi_unsupported(module, "wasi:io/streams", "[method]output-stream.write")?;
+    stub_wasi_unsupported(
         module,
         "wasi:io/streams",
-        "[method]output-stream.blocking-flush",
-        unreachable_stub,
-    )?;
-    stub_wasi_imports(
-        module,
-        "wasi:io/streams",
-        "[method]output-stream.subscribe",
-        unreachable_stub,
-    )?;
-    Ok(())
-}
-
-fn stub_sockets(module: &mut Module, world_imports: &HashSet<String>) -> Result<()> {
-    if !world_imports.contains("wasi:sockets/instance-network@0.2") {
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/instance-network",
-            "instance-network",
-            unreachable_stub,
-        )?;
-    }
-
-    if !world_imports.contains("wasi:sockets/udp@0.2") {
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/udp",
-            "[method]udp-socket.start-bind",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/udp",
-            "[method]udp-socket.finish-bind",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/udp",
-            "[method]udp-socket.stream",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
+        "[method]output-stream.blocking-flush",
+    )?;
+    stub_wasi_unsupported(module, "wasi:io/streams", "[method]output-stream.subscribe")?;
+    Ok(())
+}
+
+fn stub_sockets(module: &mut StubModule, world_imports: &HashSet<String>) -> Result<()> {
+    if module.stubs_world_import(world_imports, "wasi:sockets/instance-network") {
+        stub_wasi_unsupported(module, "wasi:sockets/instance-network", "instance-network")?;
+    }
+
+    if module.stubs_world_import(world_imports, "wasi:sockets/udp") {
+        stub_wasi_unsupported(module, "wasi:sockets/udp", "[method]udp-socket.start-bind")?;
+        stub_wasi_unsupported(module, "wasi:sockets/udp", "[method]udp-socket.finish-bind")?;
+        stub_wasi_unsupported(module, "wasi:sockets/udp", "[method]udp-socket.stream")?;
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]udp-socket.local-address",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]udp-socket.remote-address",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]udp-socket.address-family",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]udp-socket.unicast-hop-limit",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]udp-socket.set-unicast-hop-limit",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]udp-socket.receive-buffer-size",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]udp-socket.set-receive-buffer-size",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]udp-socket.send-buffer-size",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]udp-socket.set-send-buffer-size",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/udp",
-            "[method]udp-socket.subscribe",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(module, "wasi:sockets/udp", "[method]udp-socket.subscribe")?;
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]incoming-datagram-stream.receive",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]incoming-datagram-stream.subscribe",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]outgoing-datagram-stream.check-send",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]outgoing-datagram-stream.send",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[method]outgoing-datagram-stream.subscribe",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/udp",
-            "[resource-drop]udp-socket",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(module, "wasi:sockets/udp", "[resource-drop]udp-socket")?;
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[resource-drop]incoming-datagram-stream",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp",
             "[resource-drop]outgoing-datagram-stream",
-            unreachable_stub,
         )?;
     }
 
-    if !world_imports.contains("wasi:sockets/udp-create-socket@0.2") {
-        stub_wasi_imports(
+    if module.stubs_world_import(world_imports, "wasi:sockets/udp-create-socket") {
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/udp-create-socket",
             "create-udp-socket",
-            unreachable_stub,
         )?;
     }
 
-    if !world_imports.contains("wasi:sockets/tcp@0.2") {
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/tcp",
-            "[method]tcp-socket.start-bind",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/tcp",
-            "[method]tcp-socket.finish-bind",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
+    if module.stubs_world_import(world_imports, "wasi:sockets/tcp") {
+        stub_wasi_unsupported(module, "wasi:sockets/tcp", "[method]tcp-socket.start-bind")?;
+        stub_wasi_unsupported(module, "wasi:sockets/tcp", "[method]tcp-socket.finish-bind")?;
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.start-connect",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.finish-connect",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.start-listen",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.finish-listen",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/tcp",
-            "[method]tcp-socket.accept",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(module, "wasi:sockets/tcp", "[method]tcp-socket.accept")?;
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.local-address",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.remote-address",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.is-listening",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.address-family",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.set-listen-backlog-size",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.keep-alive-enabled",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.set-keep-alive-enabled",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.keep-alive-idle-time",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.set-keep-alive-idle-time",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.keep-alive-interval",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.set-keep-alive-interval",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.keep-alive-count",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.set-keep-alive-count",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/tcp",
-            "[method]tcp-socket.hop-limit",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(module, "wasi:sockets/tcp", "[method]tcp-socket.hop-limit")?;
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.set-hop-limit",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.receive-buffer-size",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.set-receive-buffer-size",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.send-buffer-size",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp",
             "[method]tcp-socket.set-send-buffer-size",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/tcp",
-            "[method]tcp-socket.subscribe",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/tcp",
-            "[method]tcp-socket.shutdown",
-            unreachable_stub,
         )?;
+        stub_wasi_unsupported(module, "wasi:sockets/tcp", "[method]tcp-socket.subscribe")?;
+        stub_wasi_unsupported(module, "wasi:sockets/tcp", "[method]tcp-socket.shutdown")?;
 
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/tcp",
-            "[resource-drop]tcp-socket",
-            unreachable_stub,
-        )?;
+        stub_wasi_unsupported(module, "wasi:sockets/tcp", "[resource-drop]tcp-socket")?;
     }
 
-    if !world_imports.contains("wasi:sockets/tcp-create-socket@0.2") {
-        stub_wasi_imports(
+    if module.stubs_world_import(world_imports, "wasi:sockets/tcp-create-socket") {
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/tcp-create-socket",
             "create-tcp-socket",
-            unreachable_stub,
         )?;
     }
 
-    if !world_imports.contains("wasi:sockets/ip-name-lookup@0.2") {
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/ip-name-lookup",
-            "resolve-addresses",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
+    if module.stubs_world_import(world_imports, "wasi:sockets/ip-name-lookup") {
+        stub_wasip3_unsupported(module, "wasi:sockets/ip-name-lookup")?;
+        stub_wasi_unsupported(module, "wasi:sockets/ip-name-lookup", "resolve-addresses")?;
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/ip-name-lookup",
             "[method]resolve-address-stream.resolve-next-address",
-            unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/ip-name-lookup",
             "[method]resolve-address-stream.subscribe",
-            unreachable_stub,
         )?;
 
-        stub_wasi_imports(
+        stub_wasi_unsupported(
             module,
             "wasi:sockets/ip-name-lookup",
             "[resource-drop]resolve-address-stream",
-            unreachable_stub,
         )?;
     }
 
-    if !world_imports.contains("wasi:sockets/network@0.2") {
-        stub_wasi_imports(
-            module,
-            "wasi:sockets/network",
-            "[resource-drop]network",
-            unreachable_stub,
-        )?;
+    if module.stubs_world_import(world_imports, "wasi:sockets/network") {
+        stub_wasi_unsupported(module, "wasi:sockets/network", "[resource-drop]network")?;
+    }
+
+    if module.stubs_world_import(world_imports, "wasi:sockets/types") {
+        stub_wasip3_unsupported(module, "wasi:sockets/types")?;
     }
 
     Ok(())
 }
 
-fn stub_filesystem(module: &mut Module, world_imports: &HashSet<String>) -> Result<()> {
-    if !world_imports.contains("wasi:filesystem/types@0.2") {
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
+/// Stubs the filesystem imports when the target world does not import them.
+/// The functions emulated by an embedded filesystem are no longer imported.
+fn stub_filesystem(module: &mut StubModule, world_imports: &HashSet<String>) -> Result<()> {
+    if module.stubs_world_import(world_imports, "wasi:filesystem/types") {
+        stub_wasip3_unsupported(module, "wasi:filesystem/types")?;
+        for name in [
             "filesystem-error-code",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.read-via-stream",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.write-via-stream",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.append-via-stream",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.advise",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.sync-data",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.get-flags",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.get-type",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.set-size",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.set-times",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.read",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.write",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.sync",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.create-directory-at",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.stat",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.stat-at",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.set-times-at",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.link-at",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.open-at",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.readlink-at",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.remove-directory-at",
-            unreachable_stub,
-        )?;
-        stub_wasi_imports(
-            module,
-            "wasi:filesystem/types",
             "[method]descriptor.rename-at",
+            "[method]descriptor.symlink-at",
+            "[method]descriptor.unlink-file-at",
+            "[method]descriptor.is-same-object",
+            "[method]descriptor.metadata-hash",
+            "[method]descriptor.metadata-hash-at",
+            "[method]directory-entry-stream.read-directory-entry",
+            "[method]descriptor.read-directory",
+            "[resource-drop]descriptor",
+            "[resource-drop]directory-entry-stream",
+        ] {
+            stub_wasi_unsupported(module, "wasi:filesystem/types", name)?;
+        }
+
+        stub_import(
+            module,
+            PREVIEW1,
+            "fd_close",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:filesystem/types",
-            "[method]descriptor.symlink-at",
+            PREVIEW1,
+            "fd_fdstat_set_flags",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:filesystem/types",
-            "[method]descriptor.unlink-file-at",
+            PREVIEW1,
+            "fd_prestat_get",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:filesystem/types",
-            "[method]descriptor.is-same-object",
+            PREVIEW1,
+            "fd_readdir",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:filesystem/types",
-            "[method]descriptor.metadata-hash",
+            PREVIEW1,
+            "fd_prestat_dir_name",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:filesystem/types",
-            "[method]descriptor.metadata-hash-at",
+            PREVIEW1,
+            "fd_read",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:filesystem/types",
-            "[method]directory-entry-stream.read-directory-entry",
+            PREVIEW1,
+            "fd_seek",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:filesystem/types",
-            "[method]descriptor.read-directory",
+            PREVIEW1,
+            "path_open",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:filesystem/types",
-            "[resource-drop]descriptor",
+            PREVIEW1,
+            "path_filestat_get",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:filesystem/types",
-            "[resource-drop]directory-entry-stream",
+            PREVIEW1,
+            "path_remove_directory",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-
-        stub_import(module, PREVIEW1, "fd_close", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "fd_fdstat_set_flags", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "fd_prestat_get", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "fd_readdir", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "fd_prestat_dir_name", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "fd_read", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "fd_seek", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "path_open", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "path_filestat_get", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "path_remove_directory", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "path_unlink_file", unreachable_stub)?;
-    }
-
-    if !world_imports.contains("wasi:filesystem/preopens@0.2") {
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:filesystem/preopens",
-            "get-directories",
+            PREVIEW1,
+            "path_unlink_file",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
     }
 
+    if module.stubs_world_import(world_imports, "wasi:filesystem/preopens") {
+        stub_wasi_unsupported(module, "wasi:filesystem/preopens", "get-directories")?;
+        stub_wasip3_unsupported(module, "wasi:filesystem/preopens")?;
+    }
+
     Ok(())
 }
 
-fn stub_cli(module: &mut Module, world_imports: &HashSet<String>) -> Result<()> {
-    if !world_imports.contains("wasi:cli/environment@0.2") {
-        stub_wasi_imports(
+fn stub_cli(module: &mut StubModule, world_imports: &HashSet<String>) -> Result<()> {
+    if module.stubs_world_import(world_imports, "wasi:cli/environment") {
+        stub_wasi_unsupported(module, "wasi:cli/environment", "get-environment")?;
+
+        stub_wasi_unsupported(module, "wasi:cli/environment", "get-arguments")?;
+        stub_wasi_unsupported(module, "wasi:cli/environment", "initial-cwd")?;
+
+        stub_import(
             module,
-            "wasi:cli/environment",
-            "get-environment",
+            PREVIEW1,
+            "args_get",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:cli/environment",
-            "get-arguments",
+            PREVIEW1,
+            "args_sizes_get",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:cli/environment",
-            "initial-cwd",
+            PREVIEW1,
+            "environ_get",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
-
-        stub_import(module, PREVIEW1, "args_get", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "args_sizes_get", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "environ_get", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "environ_sizes_get", unreachable_stub)?;
-    }
-
-    if !world_imports.contains("wasi:cli/exit@0.2") {
-        stub_wasi_imports(module, "wasi:cli/exit", "exit", unreachable_stub)?;
-        stub_import(module, PREVIEW1, "proc_exit", unreachable_stub)?;
-    }
-
-    if !world_imports.contains("wasi:cli/terminal-stdin@0.2") {
-        stub_wasi_imports(
+        stub_import(
             module,
-            "wasi:cli/terminal-stdin",
-            "get-terminal-stdin",
+            PREVIEW1,
+            "environ_sizes_get",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
     }
 
-    if !world_imports.contains("wasi:cli/terminal-stdout@0.2") {
-        stub_wasi_imports(
+    if module.stubs_world_import(world_imports, "wasi:cli/exit") {
+        stub_wasi_unsupported(module, "wasi:cli/exit", "exit")?;
+        stub_import(
             module,
-            "wasi:cli/terminal-stdout",
-            "get-terminal-stdout",
+            PREVIEW1,
+            "proc_exit",
+            StubBehavior::Unreachable,
             unreachable_stub,
         )?;
     }
 
-    if !world_imports.contains("wasi:cli/terminal-stderr@0.2") {
-        stub_wasi_imports(
-            module,
-            "wasi:cli/terminal-stderr",
-            "get-terminal-stderr",
-            unreachable_stub,
-        )?;
+    if module.stubs_world_import(world_imports, "wasi:cli/terminal-stdin") {
+        stub_wasi_unsupported(module, "wasi:cli/terminal-stdin", "get-terminal-stdin")?;
+    }
+
+    if module.stubs_world_import(world_imports, "wasi:cli/terminal-stdout") {
+        stub_wasi_unsupported(module, "wasi:cli/terminal-stdout", "get-terminal-stdout")?;
     }
 
-    if !world_imports.contains("wasi:cli/terminal-input@0.2") {
-        stub_wasi_imports(
+    if module.stubs_world_import(world_imports, "wasi:cli/terminal-stderr") {
+        stub_wasi_unsupported(module, "wasi:cli/terminal-stderr", "get-terminal-stderr")?;
+    }
+
+    if module.stubs_world_import(world_imports, "wasi:cli/terminal-input") {
+        stub_wasi_unsupported(
             module,
             "wasi:cli/terminal-input",
             "[resource-drop]terminal-input",
-            unreachable_stub,
         )?;
     }
 
-    if !world_imports.contains("wasi:cli/terminal-output@0.2") {
-        stub_wasi_imports(
+    if module.stubs_world_import(world_imports, "wasi:cli/terminal-output") {
+        stub_wasi_unsupported(
             module,
             "wasi:cli/terminal-output",
             "[resource-drop]terminal-output",
-            unreachable_stub,
         )?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use wasm_encoder::ValType;
+
+    use super::*;
+    use crate::test_utils::{
+        importing_module, instantiate, resolved_world_module, world_module, TestModule,
+    };
+
+    fn stub_module(wasm: &[u8], stub: impl FnOnce(&mut StubModule) -> Result<()>) -> Vec<u8> {
+        let mut module = StubModule::new(wasm, StubPolicy::default(), StubReason::Engine);
+        stub(&mut module).unwrap();
+        module.module.encode()
+    }
+
+    #[test]
+    fn async_lowered_waits_validate() {
+        let clock = "wasi:clocks/monotonic-clock@0.3.0";
+        let wasm = importing_module(&[
+            (clock, "wait-for", &[ValType::I64], &[]),
+            (
+                clock,
+                "[async-lower][async]wait-for",
+                &[ValType::I32; 2],
+                &[ValType::I32],
+            ),
+            (
+                clock,
+                "[async-lower][async]wait-until",
+                &[ValType::I32; 2],
+                &[ValType::I32],
+            ),
+        ]);
+        let stubbed = stub_module(&wasm, |module| stub_clocks(module, Some(0), false));
+
+        wasmparser::Validator::new().validate_all(&stubbed).unwrap();
+        let module = Module::parse(&stubbed, false).unwrap();
+        assert_eq!(module.imports.iter().count(), 0);
+    }
+
+    fn fd_write_module(wasm: &[u8]) -> StubModule<'_> {
+        StubModule::new(
+            wasm,
+            StubPolicy::default(),
+            StubReason::Feature(Feature::Stdio),
+        )
+    }
+
+    fn fd_write_wasm() -> Vec<u8> {
+        importing_module(&[(PREVIEW1, "fd_write", &[ValType::I32; 4], &[ValType::I32])])
+    }
+
+    #[test]
+    fn stubbing_a_stubbed_import_fails() {
+        let wasm = fd_write_wasm();
+        let mut module = fd_write_module(&wasm);
+        stub_stdio(&mut module, false).unwrap();
+        assert!(stub_stdio(&mut module, false).is_err());
+    }
+
+    #[test]
+    fn stub_policy_takes_precedence() {
+        let wasm = fd_write_wasm();
+        let mut module = fd_write_module(&wasm);
+        module.policy =
+            StubPolicy::from_json(r#"{ "wasi_snapshot_preview1#fd_write": "unreachable" }"#)
+                .unwrap();
+        apply_stub_policy(&mut module).unwrap();
+        module.reason = StubReason::Feature(Feature::Stdio);
+        stub_stdio(&mut module, false).unwrap();
+
+        let entry = module
+            .report
+            .iter()
+            .find(|entry| entry.name == "fd_write")
+            .unwrap();
+        assert!(matches!(entry.reason, StubReason::Policy));
+        assert!(matches!(
+            entry.status,
+            ImportStatus::Stubbed(StubBehavior::Unreachable)
+        ));
+    }
+
+    #[test]
+    fn stubs_all_wasi_features() {
+        let mut resolve = Resolve::default();
+        let (pkg, _) = resolve
+            .push_path(concat!(env!("CARGO_MANIFEST_DIR"), "/../../test/wit"))
+            .unwrap();
+        let world = resolve.select_world(pkg, Some("test1")).unwrap();
+        let wasm = resolved_world_module(&resolve, world);
+
+        let result = stub_wasi(
+            wasm,
+            vec![],
+            Some("package local:target; world target {}".to_string()),
+            None,
+            None,
+            StubOptions {
+                random_seed: None,
+                clock_epoch: Some(0),
+                stdio_capture: None,
+                stub_policy: None,
+            },
+        )
+        .unwrap();
+
+        wasmparser::Validator::new()
+            .validate_all(&result.wasm)
+            .unwrap();
+        let module = Module::parse(&result.wasm, false).unwrap();
+        let imports = module
+            .imports
+            .iter()
+            .map(|import| format!("{}#{}", import.module, import.name))
+            .filter(|import| import.starts_with("wasi:"))
+            .collect::<Vec<_>>();
+        assert_eq!(imports, Vec::<String>::new());
+        assert!(result.report.iter().any(|entry| matches!(
+            entry.status,
+            ImportStatus::Stubbed(StubBehavior::ReturnError)
+        )));
+    }
+
+    /// Stubs the imports of a module of the given world with the stub policy,
+    /// exporting the stubs by their import names
+    fn policy_stubbed_instance(wit: &str, policy: &str) -> (wasmi::Store<()>, wasmi::Instance) {
+        let wasm = world_module(wit, None);
+        let mut module = StubModule::new(
+            &wasm,
+            StubPolicy::from_json(policy).unwrap(),
+            StubReason::Policy,
+        );
+        let names = module
+            .imports
+            .iter()
+            .filter(|import| matches!(import.ty, TypeRef::Func(_)))
+            .map(|import| import.name.to_string())
+            .collect::<Vec<_>>();
+        apply_stub_policy(&mut module).unwrap();
+        for (fid, name) in names.into_iter().enumerate() {
+            module.exports.add_export_func(name, fid as u32);
+        }
+        instantiate(&module.module.encode())
+    }
+
+    #[test]
+    fn error_stubs_return_the_error_code() {
+        let (mut store, instance) = policy_stubbed_instance(
+            "package local:files;
+            interface files {
+              enum error-code { access, busy, unsupported }
+              open: func(path: string) -> result<u32, error-code>;
+              remove: func(path: string) -> result<_, error-code>;
+              ping: func() -> result;
+            }
+            world files-user {
+              import files;
+            }",
+            r#"{
+                "local:files/files#open": { "action": "return-error", "code": "busy" },
+                "local:files/files": "return-error"
+            }"#,
+        );
+        // the dummy module's memory is empty
+        let memory = instance.get_memory(&store, "memory").unwrap();
+        memory.grow(&mut store, 1).unwrap();
+        let retptr = 64;
+
+        // the error case and code are stored through the return pointer,
+        // with the code at the aligned payload offset
+        for (name, offset, code) in [("open", 4, 1), ("remove", 1, 2)] {
+            instance
+                .get_typed_func::<(i32, i32, i32), ()>(&store, name)
+                .unwrap()
+                .call(&mut store, (0, 0, retptr))
+                .unwrap();
+            let data = &memory.data(&store)[retptr as usize..];
+            assert_eq!(data[0], 1, "{name}");
+            assert_eq!(data[offset], code, "{name}");
+        }
+
+        // results flattening to a single value are returned directly
+        let ping = instance
+            .get_typed_func::<(), i32>(&store, "ping")
+            .unwrap()
+            .call(&mut store, ())
+            .unwrap();
+        assert_eq!(ping, 1);
+    }
+
+    #[test]
+    fn async_lowered_status_is_returned() {
+        let clock = "wasi:clocks/monotonic-clock@0.3.0";
+        let wasm = importing_module(&[
+            (clock, "now", &[], &[ValType::I64]),
+            (
+                clock,
+                "[async-lower][async]wait-for",
+                &[ValType::I32; 2],
+                &[ValType::I32],
+            ),
+        ]);
+        let mut module = StubModule::new(
+            &wasm,
+            StubPolicy::default(),
+            StubReason::Feature(Feature::Clocks),
+        );
+        stub_clocks(&mut module, Some(0), false).unwrap();
+        module.exports.add_export_func("now".to_string(), 0);
+        module.exports.add_export_func("wait-for".to_string(), 1);
+        let (mut store, instance) = instantiate(&module.module.encode());
+
+        // the duration is passed through the parameter pointer
+        let memory = instance.get_memory(&store, "memory").unwrap();
+        memory
+            .write(&mut store, 8, &5_000i64.to_le_bytes())
+            .unwrap();
+        let status = instance
+            .get_typed_func::<(i32, i32), i32>(&store, "wait-for")
+            .unwrap()
+            .call(&mut store, (8, 0))
+            .unwrap();
+        assert_eq!(status, SUBTASK_RETURNED);
+
+        let now = instance
+            .get_typed_func::<(), i64>(&store, "now")
+            .unwrap()
+            .call(&mut store, ())
+            .unwrap();
+        // reading the clock ticks it by its resolution
+        assert_eq!(now, 5_000 + CLOCK_RESOLUTION_NS);
+    }
+
+    /// The first values of the secure and insecure random stubs with the seed,
+    /// which share a single generator
+    fn random_values(seed: u64) -> [i64; 3] {
+        let mut test_module = TestModule::default();
+        test_module.import(
+            "wasi:random/random@0.2.3",
+            "get-random-u64",
+            &[],
+            &[ValType::I64],
+        );
+        test_module.import(
+            "wasi:random/insecure@0.2.3",
+            "get-insecure-random-u64",
+            &[],
+            &[ValType::I64],
+        );
+        test_module.memory(1).export_function(
+            "cabi_realloc",
+            &[ValType::I32; 4],
+            &[ValType::I32],
+            &[wasm_encoder::Instruction::I32Const(0)],
+        );
+        let wasm = test_module.finish();
+        let mut module = StubModule::new(
+            &wasm,
+            StubPolicy::default(),
+            StubReason::Feature(Feature::Random),
+        );
+        stub_random(&mut module, seed).unwrap();
+        module.exports.add_export_func("random".to_string(), 0);
+        module.exports.add_export_func("insecure".to_string(), 1);
+        let (mut store, instance) = instantiate(&module.module.encode());
+
+        let mut next = |name| {
+            instance
+                .get_typed_func::<(), i64>(&store, name)
+                .unwrap()
+                .call(&mut store, ())
+                .unwrap()
+        };
+        [next("random"), next("insecure"), next("random")]
+    }
+
+    #[test]
+    fn random_stubs_are_seeded() {
+        let values = random_values(42);
+        assert_eq!(values, random_values(42));
+        assert_ne!(values, random_values(43));
+        assert_ne!(values[0], values[1]);
+        assert_ne!(values[1], values[2]);
+
+        // each value advances the seed by a fixed increment, and mixes it
+        let mut state = 42u64;
+        for value in values {
+            state = state.wrapping_add(0xA0761D6478BD642F);
+            assert_eq!(value as u64, state.wrapping_mul(state ^ 0xE7037ED1A0B428DB));
+        }
+    }
+
+    #[test]
+    fn content_hash_is_stable() {
+        // the FNV-1a test vectors
+        assert_eq!(content_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(content_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(
+            content_hash(&fd_write_wasm()),
+            content_hash(&fd_write_wasm())
+        );
+    }
+
+    /// A module importing `fd_write`, with a `cabi_realloc` allocating from 1024
+    fn stdio_wasm() -> Vec<u8> {
+        let mut module = TestModule::default();
+        module.import(PREVIEW1, "fd_write", &[ValType::I32; 4], &[ValType::I32]);
+        module.memory(1).export_function(
+            "cabi_realloc",
+            &[ValType::I32; 4],
+            &[ValType::I32],
+            &[wasm_encoder::Instruction::I32Const(1024)],
+        );
+        module.finish()
+    }
+
+    #[test]
+    fn captures_stdio_into_a_ring_buffer() {
+        let wasm = stdio_wasm();
+        let mut module = fd_write_module(&wasm);
+        capture_stdio(&mut module, 8).unwrap();
+        module.exports.add_export_func("fd_write".to_string(), 0);
+        let (mut store, instance) = instantiate(&module.module.encode());
+        let memory = instance.get_memory(&store, "memory").unwrap();
+        let fd_write = instance
+            .get_typed_func::<(i32, i32, i32, i32), i32>(&store, "fd_write")
+            .unwrap();
+        let drain_logs = instance
+            .get_typed_func::<(), i32>(&store, DRAIN_LOGS)
+            .unwrap();
+
+        // writes the strings as iovs at 0 with their contents from 256,
+        // returning the errno and the number of bytes written
+        let write = |store: &mut wasmi::Store<()>, fd: i32, strings: &[&str]| {
+            let mut contents = 256;
+            for (index, string) in strings.iter().enumerate() {
+                let iov = [contents, string.len() as u32];
+                memory
+                    .write(&mut *store, index * 8, &iov[0].to_le_bytes())
+                    .unwrap();
+                memory
+                    .write(&mut *store, index * 8 + 4, &iov[1].to_le_bytes())
+                    .unwrap();
+                memory
+                    .write(&mut *store, contents as usize, string.as_bytes())
+                    .unwrap();
+                contents += string.len() as u32;
+            }
+            let errno = fd_write
+                .call(&mut *store, (fd, 0, strings.len() as i32, 128))
+                .unwrap();
+            let mut nwritten = [0; 4];
+            memory.read(&*store, 128, &mut nwritten).unwrap();
+            (errno, u32::from_le_bytes(nwritten))
+        };
+        let drain = |store: &mut wasmi::Store<()>| {
+            let retptr = drain_logs.call(&mut *store, ()).unwrap() as usize;
+            let data = memory.data(&*store);
+            let word = |offset: usize| {
+                u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize
+            };
+            let (ptr, len) = (word(retptr), word(retptr + 4));
+            String::from_utf8(data[ptr..ptr + len].to_vec()).unwrap()
+        };
+
+        assert_eq!(write(&mut store, 1, &["hello"]), (0, 5));
+        assert_eq!(drain(&mut store), "hello");
+        assert_eq!(drain(&mut store), "");
+
+        // only the most recent output is kept, wrapping around the buffer
+        assert_eq!(write(&mut store, 2, &["abc", "defghij"]), (0, 10));
+        assert_eq!(drain(&mut store), "cdefghij");
+        assert_eq!(write(&mut store, 1, &["12", "345"]), (0, 5));
+        assert_eq!(drain(&mut store), "12345");
+
+        // other descriptors are not captured
+        assert_eq!(write(&mut store, 3, &["ignored"]).0, ERRNO_BADF);
+        assert_eq!(drain(&mut store), "");
+    }
+
+    #[test]
+    fn drain_logs_is_added_to_the_world() {
+        let stub = |world: &str| {
+            stub_wasi(
+                stdio_wasm(),
+                vec![Feature::Random, Feature::Clocks, Feature::Http],
+                Some(world.to_string()),
+                None,
+                None,
+                StubOptions {
+                    random_seed: None,
+                    clock_epoch: Some(0),
+                    stdio_capture: Some(64),
+                    stub_policy: None,
+                },
+            )
+        };
+
+        let result = stub("package local:target; world target {}").unwrap();
+        let module = Module::parse(&result.wasm, false).unwrap();
+        assert!(module
+            .exports
+            .get_func_by_name(DRAIN_LOGS.to_string())
+            .is_some());
+        let (_, bindings) = wit_component::metadata::decode(&result.wasm).unwrap();
+        let world = &bindings.resolve.worlds[bindings.world];
+        assert!(world
+            .exports
+            .contains_key(&WorldKey::Name(DRAIN_LOGS.to_string())));
+
+        let err =
+            stub("package local:target; world target { export drain-logs: func() -> list<u8>; }")
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the target world already exports 'drain-logs'"
+        );
+    }
+
+    fn import_versions(modules: &[&str]) -> Result<Vec<String>> {
+        let imports = modules
+            .iter()
+            .map(|module| (*module, "get-stdout", &[][..], &[ValType::I32][..]))
+            .collect::<Vec<_>>();
+        let wasm = importing_module(&imports);
+        wasi_import_versions(&Module::parse(&wasm, false).unwrap(), "wasi:cli/stdout")
+    }
+
+    #[test]
+    fn wasi_imports_match_any_supported_version() {
+        assert_eq!(
+            import_versions(&[
+                "wasi:cli/stdout@0.2.0",
+                "wasi:cli/stdout@0.2.10",
+                "wasi:cli/stdout@0.3.0-rc-2025-09-16",
+                "wasi:cli/stdout@0.2.0",
+                "wasi:cli/stderr@0.2.3",
+                "wasi:cli/stdout-extra@0.2.3",
+            ])
+            .unwrap(),
+            [
+                "wasi:cli/stdout@0.2.0",
+                "wasi:cli/stdout@0.2.10",
+                "wasi:cli/stdout@0.3.0-rc-2025-09-16",
+            ]
+        );
+        assert!(import_versions(&["wasi:cli/stderr@0.2.3"])
+            .unwrap()
+            .is_empty());
+
+        // unsupported versions are only ignored beside supported versions
+        assert_eq!(
+            import_versions(&["wasi:cli/stdout@0.2.3", "wasi:cli/stdout@0.2.0-rc-1"]).unwrap(),
+            ["wasi:cli/stdout@0.2.3"]
+        );
+        for unsupported in ["wasi:cli/stdout@0.2.0-rc-1", "wasi:cli/stdout@1.0.0"] {
+            let err = import_versions(&[unsupported]).unwrap_err();
+            assert!(err.to_string().contains(unsupported), "{err}");
+        }
+    }
+}
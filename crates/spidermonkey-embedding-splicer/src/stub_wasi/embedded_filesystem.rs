@@ -0,0 +1,1520 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use anyhow::{bail, Context, Result};
+use wasmparser::MemArg;
+use wirm::ir::function::FunctionBuilder;
+use wirm::ir::id::{FunctionID, GlobalID, LocalID, MemoryID};
+use wirm::ir::types::{BlockType, DataSegment, DataSegmentKind, InitExpr, Value};
+use wirm::module_builder::AddLocal;
+use wirm::{DataType, InitInstr, Opcode};
+
+use super::{
+    error_stub, import_type, is_imported, is_wasip2_version, parse_versioned_import, stub_import,
+    stub_wasi_imports, wasi_import_versions, StubModule,
+};
+use crate::stub_policy::StubPolicy;
+use crate::wit::exports::local::spidermonkey_embedding_splicer::splicer::{
+    EmbeddedFilesystem, ImportStatus, StubBehavior, StubReason,
+};
+
+const TYPES: &str = "wasi:filesystem/types";
+const PREOPENS: &str = "wasi:filesystem/preopens";
+
+const PAGE_SIZE: u64 = 65536;
+
+/// Each node of the tree is a record of little-endian u32 fields, with the
+/// entries of a directory stored as consecutive nodes
+const NODE_SIZE: i32 = 24;
+/// The `descriptor-type` of the node
+const NODE_TYPE: u64 = 0;
+/// The index of the parent directory, or -1 for the root
+const NODE_PARENT: u64 = 4;
+/// The address of the name of the node
+const NODE_NAME: u64 = 8;
+const NODE_NAME_LEN: u64 = 12;
+/// The index of the first entry of a directory, or the address of the
+/// contents of a file
+const NODE_START: u64 = 16;
+/// The number of entries of a directory, or the size of a file
+const NODE_LEN: u64 = 20;
+
+/// Each `directory-entry-stream` is a record allocated when reading a
+/// directory, which is kept in a free list once dropped
+const STREAM_SIZE: i32 = 8;
+/// The address of the node of the directory, or of the next free stream
+const STREAM_NODE: u64 = 0;
+/// The index of the next entry returned by the stream
+const STREAM_CURSOR: u64 = 4;
+
+// `open-flags` and `descriptor-flags` which would modify the tree
+const OPEN_CREATE: i32 = 1 << 0;
+const OPEN_DIRECTORY: i32 = 1 << 1;
+const OPEN_TRUNCATE: i32 = 1 << 3;
+const DESCRIPTOR_WRITE: i32 = 1 << 1;
+const DESCRIPTOR_MUTATE_DIRECTORY: i32 = 1 << 5;
+
+/// Methods of `descriptor` which modify the filesystem, and return a
+/// `read-only` error
+const WRITE_METHODS: [&str; 12] = [
+    "[method]descriptor.write-via-stream",
+    "[method]descriptor.append-via-stream",
+    "[method]descriptor.set-size",
+    "[method]descriptor.set-times",
+    "[method]descriptor.write",
+    "[method]descriptor.create-directory-at",
+    "[method]descriptor.set-times-at",
+    "[method]descriptor.link-at",
+    "[method]descriptor.remove-directory-at",
+    "[method]descriptor.rename-at",
+    "[method]descriptor.symlink-at",
+    "[method]descriptor.unlink-file-at",
+];
+
+/// Embeds a directory tree into the engine as a read-only filesystem, served
+/// to its WASI 0.2 `wasi:filesystem` imports.
+///
+/// The imports which open, read, stat and list files are emulated from the
+/// tree, and those which modify it return `read-only` errors, while the other
+/// filesystem imports are left to be stubbed with the disabled features.
+/// Embedding happens before the engine is initialized, so the files are
+/// served both during initialization and once the component runs. Engines
+/// which do not import `wasi:filesystem/types` are returned unchanged.
+pub(crate) fn embed_filesystem(wasm: &[u8], filesystem: &EmbeddedFilesystem) -> Result<Vec<u8>> {
+    let mut module = StubModule::new(wasm, StubPolicy::default(), StubReason::Engine);
+    embed(&mut module, filesystem)?;
+    Ok(module.module.encode())
+}
+
+fn embed(module: &mut StubModule, filesystem: &EmbeddedFilesystem) -> Result<()> {
+    let Some(cases) = Cases::resolve(module)? else {
+        // the tree is still checked, as the files are read while initializing
+        build_tree(filesystem)?;
+        return Ok(());
+    };
+    let image = EmbeddedImage::add(module, filesystem, cases)?;
+    image.stub_types(module)?;
+    image.stub_preopens(module)
+}
+
+/// The `descriptor-type` and `error-code` cases used by the stubs, resolved
+/// from the WIT signatures of the engine imports
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cases {
+    directory: i32,
+    regular_file: i32,
+    is_directory: i32,
+    no_entry: i32,
+    not_directory: i32,
+    not_permitted: i32,
+    read_only: i32,
+}
+
+impl Cases {
+    /// Resolves the cases of the versions of `wasi:filesystem/types` imported
+    /// by the engine, if any
+    fn resolve(module: &StubModule) -> Result<Option<Self>> {
+        let versions = wasip2_import_versions(module, TYPES)?;
+        if versions.is_empty() {
+            return Ok(None);
+        }
+        let signatures = module
+            .signatures
+            .as_ref()
+            .context("the engine has no component type for its imports")?;
+        let mut resolved = None;
+        for full_import in versions {
+            let case = |ty: &str, case: &str| {
+                signatures
+                    .enum_case(&full_import, ty, case)
+                    .with_context(|| format!("'{full_import}' has no {ty} '{case}'"))
+            };
+            let cases = Cases {
+                directory: case("descriptor-type", "directory")?,
+                regular_file: case("descriptor-type", "regular-file")?,
+                is_directory: case("error-code", "is-directory")?,
+                no_entry: case("error-code", "no-entry")?,
+                not_directory: case("error-code", "not-directory")?,
+                not_permitted: case("error-code", "not-permitted")?,
+                read_only: case("error-code", "read-only")?,
+            };
+            if resolved.is_some_and(|resolved| resolved != cases) {
+                bail!("the versions of '{TYPES}' imported by the engine have different cases");
+            }
+            resolved = Some(cases);
+        }
+        Ok(resolved)
+    }
+}
+
+enum Node {
+    File(Vec<u8>),
+    Directory(BTreeMap<String, Node>),
+}
+
+/// Builds the directory tree from the paths of the embedded files
+fn build_tree(filesystem: &EmbeddedFilesystem) -> Result<BTreeMap<String, Node>> {
+    let mut root = BTreeMap::new();
+    for file in &filesystem.files {
+        let components = file
+            .path
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .collect::<Vec<_>>();
+        let Some((name, parents)) = components.split_last() else {
+            bail!("invalid embedded file path '{}'", file.path);
+        };
+        if components.contains(&"..") {
+            bail!("embedded file path '{}' contains '..'", file.path);
+        }
+        let mut directory = &mut root;
+        for parent in parents {
+            let node = directory
+                .entry(parent.to_string())
+                .or_insert_with(|| Node::Directory(BTreeMap::new()));
+            let Node::Directory(entries) = node else {
+                bail!(
+                    "embedded file '{parent}' is also a directory of '{}'",
+                    file.path
+                );
+            };
+            directory = entries;
+        }
+        if directory
+            .insert(name.to_string(), Node::File(file.contents.clone()))
+            .is_some()
+        {
+            bail!("embedded file path '{}' is not unique", file.path);
+        }
+    }
+    Ok(root)
+}
+
+/// The image of the tree at its address in memory, which is the node records
+/// followed by the preopen, the names and the contents of the files
+struct Layout {
+    image: Vec<u8>,
+    preopen: (i32, i32),
+}
+
+fn layout(filesystem: &EmbeddedFilesystem, base: u64, cases: &Cases) -> Result<Layout> {
+    if filesystem.preopen.is_empty() {
+        bail!("the embedded filesystem preopen must not be empty");
+    }
+    let root = build_tree(filesystem)?;
+
+    // nodes are [type, parent, name, start, len] with the start of files
+    // relative to the contents, so addresses are patched once the sizes of
+    // the nodes and names are known
+    let mut nodes: Vec<[u32; 5]> = vec![[cases.directory as u32, u32::MAX, 0, 0, 0]];
+    let mut names: Vec<&str> = vec![""];
+    let mut contents = Vec::new();
+    let mut directories = VecDeque::from([(0, &root)]);
+    while let Some((index, entries)) = directories.pop_front() {
+        nodes[index][3] = nodes.len() as u32;
+        nodes[index][4] = entries.len() as u32;
+        for (name, node) in entries {
+            let record = match node {
+                Node::File(data) => {
+                    let start = contents.len() as u32;
+                    contents.extend_from_slice(data);
+                    [
+                        cases.regular_file as u32,
+                        index as u32,
+                        0,
+                        start,
+                        data.len() as u32,
+                    ]
+                }
+                Node::Directory(entries) => {
+                    directories.push_back((nodes.len(), entries));
+                    [cases.directory as u32, index as u32, 0, 0, 0]
+                }
+            };
+            nodes.push(record);
+            names.push(name);
+        }
+    }
+
+    let preopen = base + (nodes.len() * NODE_SIZE as usize) as u64;
+    let names_len = names.iter().map(|name| name.len()).sum::<usize>();
+    let contents_start = preopen + (filesystem.preopen.len() + names_len) as u64;
+    if contents_start + contents.len() as u64 > u32::MAX as u64 {
+        bail!("the embedded filesystem is too large");
+    }
+
+    let mut image = Vec::new();
+    let mut name = preopen + filesystem.preopen.len() as u64;
+    for (node, node_name) in nodes.iter().zip(&names) {
+        let [ty, parent, _, start, len] = *node;
+        let start = match ty as i32 == cases.regular_file {
+            true => contents_start as u32 + start,
+            false => start,
+        };
+        for field in [ty, parent, name as u32, node_name.len() as u32, start, len] {
+            image.extend_from_slice(&field.to_le_bytes());
+        }
+        name += node_name.len() as u64;
+    }
+    image.extend_from_slice(filesystem.preopen.as_bytes());
+    for name in names {
+        image.extend_from_slice(name.as_bytes());
+    }
+    image.extend_from_slice(&contents);
+    Ok(Layout {
+        image,
+        preopen: (preopen as i32, filesystem.preopen.len() as i32),
+    })
+}
+
+/// A read-only directory tree embedded in the module, which implements the
+/// WASI 0.2 filesystem functions used to read files and list directories.
+///
+/// The tree is stored by an active data segment at the end of the initial
+/// memory of the engine, which is grown to contain it. Its allocator only
+/// claims the memory it grows itself, so the tree is never overwritten.
+/// Descriptors are the node index plus one, so they are never freed, while
+/// each `directory-entry-stream` keeps its own position in the directory.
+struct EmbeddedImage {
+    memory: MemoryID,
+    realloc: FunctionID,
+    cases: Cases,
+    /// The address of the first node
+    base: i32,
+    preopen: (i32, i32),
+    /// Returns the address of the node of a descriptor
+    node: FunctionID,
+    /// Resolves a path relative to a directory node to the address of its
+    /// node, or returns zero and sets the error global
+    lookup: FunctionID,
+    /// Stores the `descriptor-stat` of a node
+    stat: FunctionID,
+    error: GlobalID,
+    /// The most recently dropped stream, which is reused by the next
+    /// directory read
+    free_streams: GlobalID,
+}
+
+impl EmbeddedImage {
+    fn add(module: &mut StubModule, filesystem: &EmbeddedFilesystem, cases: Cases) -> Result<Self> {
+        let memory = module.get_memory_id().context("the engine has no memory")?;
+        if module.memories.is_import(memory) {
+            bail!("the engine imports its memory, which cannot contain the embedded filesystem");
+        }
+        let realloc = module
+            .exports
+            .get_func_by_name("cabi_realloc".to_string())
+            .context("the engine does not export cabi_realloc")?;
+
+        let ty = &mut module.memories.get_mut(memory).unwrap().ty;
+        if ty.memory64 || ty.page_size_log2.is_some() {
+            bail!("the engine memory must be a 32-bit memory of 64KiB pages");
+        }
+        let base = ty.initial * PAGE_SIZE;
+        let Layout { image, preopen } = layout(filesystem, base, &cases)?;
+        let pages = (base + image.len() as u64).div_ceil(PAGE_SIZE);
+        if ty.maximum.is_some_and(|maximum| maximum < pages) {
+            bail!("the embedded filesystem does not fit in the maximum memory of the engine");
+        }
+        ty.initial = pages;
+        let base = base as i32;
+        module.add_data(DataSegment {
+            kind: DataSegmentKind::Active {
+                memory_index: *memory,
+                offset_expr: InitExpr::new(vec![InitInstr::Value(Value::I32(base))]),
+            },
+            data: image,
+            tag: None,
+        });
+
+        let new_global = |module: &mut StubModule| {
+            module.add_global(
+                InitExpr::new(vec![InitInstr::Value(Value::I32(0))]),
+                DataType::I32,
+                true,
+                false,
+            )
+        };
+        let error = new_global(module);
+        let free_streams = new_global(module);
+        let mem_arg = |align: u32, offset: u64| MemArg {
+            align: align as u8,
+            max_align: 0,
+            offset,
+            memory: *memory,
+        };
+
+        let mut node = FunctionBuilder::new(&[DataType::I32], &[DataType::I32]);
+        node.i32_const(base);
+        node.local_get(LocalID(0));
+        node.i32_const(1);
+        node.i32_sub();
+        node.i32_const(NODE_SIZE);
+        node.i32_mul();
+        node.i32_add();
+        let node = node.finish_module(module);
+
+        // (func (param $a i32) (param $b i32) (param $len i32) (result i32))
+        let mut bytes_eq = FunctionBuilder::new(
+            &[DataType::I32, DataType::I32, DataType::I32],
+            &[DataType::I32],
+        );
+        let idx = bytes_eq.add_local(DataType::I32);
+        bytes_eq.block(BlockType::Empty);
+        bytes_eq.loop_stmt(BlockType::Empty);
+        bytes_eq.local_get(idx);
+        bytes_eq.local_get(LocalID(2));
+        bytes_eq.i32_gte_unsigned();
+        bytes_eq.br_if(1);
+        bytes_eq.local_get(LocalID(0));
+        bytes_eq.local_get(idx);
+        bytes_eq.i32_add();
+        bytes_eq.i32_load8_u(mem_arg(0, 0));
+        bytes_eq.local_get(LocalID(1));
+        bytes_eq.local_get(idx);
+        bytes_eq.i32_add();
+        bytes_eq.i32_load8_u(mem_arg(0, 0));
+        bytes_eq.i32_ne();
+        bytes_eq.if_stmt(BlockType::Empty);
+        bytes_eq.i32_const(0);
+        bytes_eq.return_stmt();
+        bytes_eq.end();
+        bytes_eq.local_get(idx);
+        bytes_eq.i32_const(1);
+        bytes_eq.i32_add();
+        bytes_eq.local_set(idx);
+        bytes_eq.br(0);
+        bytes_eq.end(); // This is for the loop
+        bytes_eq.end(); // This is for the block
+        bytes_eq.i32_const(1);
+        let bytes_eq = bytes_eq.finish_module(module);
+
+        let lookup = Self::add_lookup(module, base, &cases, bytes_eq, error, mem_arg);
+
+        // (func (param $node i32) (param $retptr i32))
+        let mut stat = FunctionBuilder::new(&[DataType::I32, DataType::I32], &[]);
+        let (node_addr, retptr) = (LocalID(0), LocalID(1));
+        // result<descriptor-stat, error-code> with the record at offset 8
+        stat.local_get(retptr);
+        stat.i32_const(0);
+        stat.i32_store8(mem_arg(0, 0));
+        stat.local_get(retptr);
+        stat.local_get(node_addr);
+        stat.i32_load(mem_arg(2, NODE_TYPE));
+        stat.i32_store8(mem_arg(0, 8));
+        stat.local_get(retptr);
+        stat.i64_const(1);
+        stat.i64_store(mem_arg(3, 16));
+        // directories have a size of zero
+        stat.local_get(retptr);
+        stat.local_get(node_addr);
+        stat.i32_load(mem_arg(2, NODE_LEN));
+        stat.i32_const(0);
+        stat.local_get(node_addr);
+        stat.i32_load(mem_arg(2, NODE_TYPE));
+        stat.i32_const(cases.regular_file);
+        stat.i32_eq();
+        stat.select();
+        stat.i64_extend_i32u();
+        stat.i64_store(mem_arg(3, 24));
+        // no timestamps
+        for offset in [32, 56, 80] {
+            stat.local_get(retptr);
+            stat.i32_const(0);
+            stat.i32_store8(mem_arg(0, offset));
+        }
+        let stat = stat.finish_module(module);
+
+        Ok(EmbeddedImage {
+            memory,
+            realloc,
+            cases,
+            base,
+            preopen,
+            node,
+            lookup,
+            stat,
+            error,
+            free_streams,
+        })
+    }
+
+    // (func (param $dir i32) (param $path i32) (param $len i32) (result i32))
+    fn add_lookup(
+        module: &mut StubModule,
+        base: i32,
+        cases: &Cases,
+        bytes_eq: FunctionID,
+        error: GlobalID,
+        mem_arg: impl Fn(u32, u64) -> MemArg,
+    ) -> FunctionID {
+        let (dir, path, len) = (LocalID(0), LocalID(1), LocalID(2));
+        let mut lookup = FunctionBuilder::new(
+            &[DataType::I32, DataType::I32, DataType::I32],
+            &[DataType::I32],
+        );
+        let node = lookup.add_local(DataType::I32);
+        let idx = lookup.add_local(DataType::I32);
+        let start = lookup.add_local(DataType::I32);
+        let component_len = lookup.add_local(DataType::I32);
+        let entry = lookup.add_local(DataType::I32);
+        let last = lookup.add_local(DataType::I32);
+        let child = lookup.add_local(DataType::I32);
+        let fail = |lookup: &mut FunctionBuilder, code: i32| {
+            lookup.i32_const(code);
+            lookup.global_set(error);
+            lookup.i32_const(0);
+            lookup.return_stmt();
+        };
+        // path[offset] == byte
+        let byte_is = |lookup: &mut FunctionBuilder, offset: u64, byte: u8| {
+            lookup.local_get(path);
+            lookup.local_get(start);
+            lookup.i32_add();
+            lookup.i32_load8_u(mem_arg(0, offset));
+            lookup.i32_const(byte as i32);
+            lookup.i32_eq();
+        };
+
+        lookup.local_get(dir);
+        lookup.local_set(node);
+
+        // absolute paths are not permitted
+        lookup.local_get(len);
+        lookup.if_stmt(BlockType::Empty);
+        byte_is(&mut lookup, 0, b'/');
+        lookup.if_stmt(BlockType::Empty);
+        fail(&mut lookup, cases.not_permitted);
+        lookup.end();
+        lookup.end();
+
+        // for each component
+        lookup.block(BlockType::Empty);
+        lookup.loop_stmt(BlockType::Empty);
+        lookup.local_get(idx);
+        lookup.local_get(len);
+        lookup.i32_gte_unsigned();
+        lookup.br_if(1);
+        lookup.local_get(idx);
+        lookup.local_set(start);
+
+        // the component ends at the next '/'
+        lookup.block(BlockType::Empty);
+        lookup.loop_stmt(BlockType::Empty);
+        lookup.local_get(idx);
+        lookup.local_get(len);
+        lookup.i32_gte_unsigned();
+        lookup.br_if(1);
+        lookup.local_get(path);
+        lookup.local_get(idx);
+        lookup.i32_add();
+        lookup.i32_load8_u(mem_arg(0, 0));
+        lookup.i32_const(b'/' as i32);
+        lookup.i32_eq();
+        lookup.br_if(1);
+        lookup.local_get(idx);
+        lookup.i32_const(1);
+        lookup.i32_add();
+        lookup.local_set(idx);
+        lookup.br(0);
+        lookup.end(); // This is for the loop
+        lookup.end(); // This is for the block
+        lookup.local_get(idx);
+        lookup.local_get(start);
+        lookup.i32_sub();
+        lookup.local_set(component_len);
+        lookup.local_get(idx);
+        lookup.i32_const(1);
+        lookup.i32_add();
+        lookup.local_set(idx);
+
+        // empty and "." components refer to the same directory
+        lookup.local_get(component_len);
+        lookup.i32_eqz();
+        lookup.br_if(0);
+        lookup.local_get(component_len);
+        lookup.i32_const(1);
+        lookup.i32_eq();
+        lookup.if_stmt(BlockType::Empty);
+        byte_is(&mut lookup, 0, b'.');
+        lookup.br_if(1);
+        lookup.end();
+
+        lookup.local_get(node);
+        lookup.i32_load(mem_arg(2, NODE_TYPE));
+        lookup.i32_const(cases.directory);
+        lookup.i32_ne();
+        lookup.if_stmt(BlockType::Empty);
+        fail(&mut lookup, cases.not_directory);
+        lookup.end();
+
+        // ".." refers to the parent, which the root does not have
+        lookup.local_get(component_len);
+        lookup.i32_const(2);
+        lookup.i32_eq();
+        lookup.if_stmt(BlockType::Empty);
+        byte_is(&mut lookup, 0, b'.');
+        byte_is(&mut lookup, 1, b'.');
+        lookup.i32_and();
+        lookup.if_stmt(BlockType::Empty);
+        lookup.local_get(node);
+        lookup.i32_load(mem_arg(2, NODE_PARENT));
+        lookup.local_tee(child);
+        lookup.i32_const(-1);
+        lookup.i32_eq();
+        lookup.if_stmt(BlockType::Empty);
+        fail(&mut lookup, cases.not_permitted);
+        lookup.end();
+        lookup.i32_const(base);
+        lookup.local_get(child);
+        lookup.i32_const(NODE_SIZE);
+        lookup.i32_mul();
+        lookup.i32_add();
+        lookup.local_set(node);
+        lookup.br(2);
+        lookup.end();
+        lookup.end();
+
+        // for each entry of the directory
+        lookup.local_get(node);
+        lookup.i32_load(mem_arg(2, NODE_START));
+        lookup.local_tee(entry);
+        lookup.local_get(node);
+        lookup.i32_load(mem_arg(2, NODE_LEN));
+        lookup.i32_add();
+        lookup.local_set(last);
+        lookup.block(BlockType::Empty);
+        lookup.loop_stmt(BlockType::Empty);
+        lookup.local_get(entry);
+        lookup.local_get(last);
+        lookup.i32_gte_unsigned();
+        lookup.if_stmt(BlockType::Empty);
+        fail(&mut lookup, cases.no_entry);
+        lookup.end();
+        lookup.i32_const(base);
+        lookup.local_get(entry);
+        lookup.i32_const(NODE_SIZE);
+        lookup.i32_mul();
+        lookup.i32_add();
+        lookup.local_tee(child);
+        lookup.i32_load(mem_arg(2, NODE_NAME_LEN));
+        lookup.local_get(component_len);
+        lookup.i32_eq();
+        lookup.if_stmt(BlockType::Empty);
+        lookup.local_get(child);
+        lookup.i32_load(mem_arg(2, NODE_NAME));
+        lookup.local_get(path);
+        lookup.local_get(start);
+        lookup.i32_add();
+        lookup.local_get(component_len);
+        lookup.call(bytes_eq);
+        lookup.if_stmt(BlockType::Empty);
+        lookup.local_get(child);
+        lookup.local_set(node);
+        lookup.br(3);
+        lookup.end();
+        lookup.end();
+        lookup.local_get(entry);
+        lookup.i32_const(1);
+        lookup.i32_add();
+        lookup.local_set(entry);
+        lookup.br(0);
+        lookup.end(); // This is for the loop
+        lookup.end(); // This is for the block
+
+        lookup.br(0);
+        lookup.end(); // This is for the loop
+        lookup.end(); // This is for the block
+        lookup.local_get(node);
+        lookup.finish_module(module)
+    }
+
+    fn mem_arg(&self, align: u32, offset: u64) -> MemArg {
+        MemArg {
+            align: align as u8,
+            max_align: 0,
+            offset,
+            memory: *self.memory,
+        }
+    }
+
+    /// Stores the `err` case of a result with the code at the payload offset
+    fn error(&self, body: &mut FunctionBuilder, retptr: LocalID, offset: u64, code: i32) {
+        body.local_get(retptr);
+        body.i32_const(1);
+        body.i32_store8(self.mem_arg(0, 0));
+        body.local_get(retptr);
+        body.i32_const(code);
+        body.i32_store8(self.mem_arg(0, offset));
+    }
+
+    /// Stores the `err` case of a result with the code of a failed lookup
+    fn lookup_error(&self, body: &mut FunctionBuilder, retptr: LocalID, offset: u64) {
+        body.local_get(retptr);
+        body.i32_const(1);
+        body.i32_store8(self.mem_arg(0, 0));
+        body.local_get(retptr);
+        body.global_get(self.error);
+        body.i32_store8(self.mem_arg(0, offset));
+    }
+
+    /// Pushes the descriptor of the node at the address
+    fn handle(&self, body: &mut FunctionBuilder, node: LocalID) {
+        body.local_get(node);
+        body.i32_const(self.base);
+        body.i32_sub();
+        body.i32_const(NODE_SIZE);
+        body.i32_div_unsigned();
+        body.i32_const(1);
+        body.i32_add();
+    }
+
+    /// Allocates `len` bytes with the given alignment
+    fn alloc(&self, body: &mut FunctionBuilder, align: i32, len: LocalID) {
+        body.i32_const(0);
+        body.i32_const(0);
+        body.i32_const(align);
+        body.local_get(len);
+        body.call(self.realloc);
+    }
+
+    /// Stubs the `wasi:filesystem/types` functions which read the tree, and
+    /// those which modify it with `read-only` errors
+    fn stub_types(&self, module: &mut StubModule) -> Result<()> {
+        use DataType::{I32, I64};
+        let cases = self.cases;
+
+        // (func (param i32 i32 i32 i32 i32 i32 i32))
+        stub_embedded(
+            module,
+            TYPES,
+            "[method]descriptor.open-at",
+            &[I32, I32, I32, I32, I32, I32, I32],
+            |body| {
+                let (descriptor, path, len) = (LocalID(0), LocalID(2), LocalID(3));
+                let (open_flags, flags, retptr) = (LocalID(4), LocalID(5), LocalID(6));
+                let node = body.add_local(I32);
+
+                body.local_get(open_flags);
+                body.i32_const(OPEN_CREATE | OPEN_TRUNCATE);
+                body.i32_and();
+                body.local_get(flags);
+                body.i32_const(DESCRIPTOR_WRITE | DESCRIPTOR_MUTATE_DIRECTORY);
+                body.i32_and();
+                body.i32_or();
+                body.if_stmt(BlockType::Empty);
+                self.error(body, retptr, 4, cases.read_only);
+                body.return_stmt();
+                body.end();
+
+                body.local_get(descriptor);
+                body.call(self.node);
+                body.local_get(path);
+                body.local_get(len);
+                body.call(self.lookup);
+                body.local_tee(node);
+                body.i32_eqz();
+                body.if_stmt(BlockType::Empty);
+                self.lookup_error(body, retptr, 4);
+                body.return_stmt();
+                body.end();
+
+                body.local_get(open_flags);
+                body.i32_const(OPEN_DIRECTORY);
+                body.i32_and();
+                body.i32_const(0);
+                body.i32_ne();
+                body.local_get(node);
+                body.i32_load(self.mem_arg(2, NODE_TYPE));
+                body.i32_const(cases.directory);
+                body.i32_ne();
+                body.i32_and();
+                body.if_stmt(BlockType::Empty);
+                self.error(body, retptr, 4, cases.not_directory);
+                body.return_stmt();
+                body.end();
+
+                body.local_get(retptr);
+                body.i32_const(0);
+                body.i32_store8(self.mem_arg(0, 0));
+                body.local_get(retptr);
+                self.handle(body, node);
+                body.i32_store(self.mem_arg(2, 4));
+                Ok(vec![descriptor, path, len, open_flags, flags, retptr])
+            },
+        )?;
+
+        // (func (param i32 i64 i64 i32))
+        stub_embedded(
+            module,
+            TYPES,
+            "[method]descriptor.read",
+            &[I32, I64, I64, I32],
+            |body| {
+                let (descriptor, length, offset, retptr) =
+                    (LocalID(0), LocalID(1), LocalID(2), LocalID(3));
+                let node = body.add_local(I32);
+                let size = body.add_local(I64);
+                let len = body.add_local(I32);
+                let ptr = body.add_local(I32);
+
+                body.local_get(descriptor);
+                body.call(self.node);
+                body.local_tee(node);
+                body.i32_load(self.mem_arg(2, NODE_TYPE));
+                body.i32_const(cases.directory);
+                body.i32_eq();
+                body.if_stmt(BlockType::Empty);
+                self.error(body, retptr, 4, cases.is_directory);
+                body.return_stmt();
+                body.end();
+
+                // len = min(length, size - offset) when offset < size
+                body.local_get(node);
+                body.i32_load(self.mem_arg(2, NODE_LEN));
+                body.i64_extend_i32u();
+                body.local_set(size);
+                body.local_get(offset);
+                body.local_get(size);
+                body.i64_lt_unsigned();
+                body.if_stmt(BlockType::Empty);
+                body.local_get(length);
+                body.local_get(size);
+                body.local_get(offset);
+                body.i64_sub();
+                body.local_get(length);
+                body.local_get(size);
+                body.local_get(offset);
+                body.i64_sub();
+                body.i64_lt_unsigned();
+                body.select();
+                body.i32_wrap_i64();
+                body.local_set(len);
+                body.end();
+
+                // the contents are owned by the caller
+                body.local_get(len);
+                body.if_stmt(BlockType::Empty);
+                self.alloc(body, 1, len);
+                body.local_tee(ptr);
+                body.local_get(node);
+                body.i32_load(self.mem_arg(2, NODE_START));
+                body.local_get(offset);
+                body.i32_wrap_i64();
+                body.i32_add();
+                body.local_get(len);
+                body.memory_copy(*self.memory, *self.memory);
+                body.end();
+
+                // result<tuple<list<u8>, bool>, error-code>
+                body.local_get(retptr);
+                body.i32_const(0);
+                body.i32_store8(self.mem_arg(0, 0));
+                body.local_get(retptr);
+                body.local_get(ptr);
+                body.i32_store(self.mem_arg(2, 4));
+                body.local_get(retptr);
+                body.local_get(len);
+                body.i32_store(self.mem_arg(2, 8));
+                body.local_get(retptr);
+                body.local_get(offset);
+                body.local_get(len);
+                body.i64_extend_i32u();
+                body.i64_add();
+                body.local_get(size);
+                body.i64_gte_unsigned();
+                body.i32_store8(self.mem_arg(0, 12));
+                Ok(vec![descriptor, length, offset, retptr])
+            },
+        )?;
+
+        // (func (param i32 i32))
+        stub_embedded(
+            module,
+            TYPES,
+            "[method]descriptor.stat",
+            &[I32, I32],
+            |body| {
+                let (descriptor, retptr) = (LocalID(0), LocalID(1));
+                body.local_get(descriptor);
+                body.call(self.node);
+                body.local_get(retptr);
+                body.call(self.stat);
+                Ok(vec![descriptor, retptr])
+            },
+        )?;
+
+        // (func (param i32 i32 i32 i32 i32))
+        stub_embedded(
+            module,
+            TYPES,
+            "[method]descriptor.stat-at",
+            &[I32, I32, I32, I32, I32],
+            |body| {
+                let (descriptor, path, len, retptr) =
+                    (LocalID(0), LocalID(2), LocalID(3), LocalID(4));
+                let node = body.add_local(I32);
+                body.local_get(descriptor);
+                body.call(self.node);
+                body.local_get(path);
+                body.local_get(len);
+                body.call(self.lookup);
+                body.local_tee(node);
+                body.i32_eqz();
+                body.if_stmt(BlockType::Empty);
+                self.lookup_error(body, retptr, 8);
+                body.return_stmt();
+                body.end();
+                body.local_get(node);
+                body.local_get(retptr);
+                body.call(self.stat);
+                Ok(vec![descriptor, path, len, retptr])
+            },
+        )?;
+
+        // (func (param i32 i32))
+        stub_embedded(
+            module,
+            TYPES,
+            "[method]descriptor.get-type",
+            &[I32, I32],
+            |body| {
+                let (descriptor, retptr) = (LocalID(0), LocalID(1));
+                body.local_get(retptr);
+                body.i32_const(0);
+                body.i32_store8(self.mem_arg(0, 0));
+                body.local_get(retptr);
+                body.local_get(descriptor);
+                body.call(self.node);
+                body.i32_load(self.mem_arg(2, NODE_TYPE));
+                body.i32_store8(self.mem_arg(0, 1));
+                Ok(vec![descriptor, retptr])
+            },
+        )?;
+
+        // directory-entry-streams are the address of their record, reusing
+        // the most recently dropped one
+        // (func (param i32 i32))
+        stub_embedded(
+            module,
+            TYPES,
+            "[method]descriptor.read-directory",
+            &[I32, I32],
+            |body| {
+                let (descriptor, retptr) = (LocalID(0), LocalID(1));
+                let node = body.add_local(I32);
+                let stream = body.add_local(I32);
+                let size = body.add_local(I32);
+                body.local_get(descriptor);
+                body.call(self.node);
+                body.local_tee(node);
+                body.i32_load(self.mem_arg(2, NODE_TYPE));
+                body.i32_const(cases.directory);
+                body.i32_ne();
+                body.if_stmt(BlockType::Empty);
+                self.error(body, retptr, 4, cases.not_directory);
+                body.return_stmt();
+                body.end();
+
+                body.global_get(self.free_streams);
+                body.local_tee(stream);
+                body.if_stmt(BlockType::Empty);
+                body.local_get(stream);
+                body.i32_load(self.mem_arg(2, STREAM_NODE));
+                body.global_set(self.free_streams);
+                body.else_stmt();
+                body.i32_const(STREAM_SIZE);
+                body.local_set(size);
+                self.alloc(body, 4, size);
+                body.local_set(stream);
+                body.end();
+                body.local_get(stream);
+                body.local_get(node);
+                body.i32_store(self.mem_arg(2, STREAM_NODE));
+                body.local_get(stream);
+                body.i32_const(0);
+                body.i32_store(self.mem_arg(2, STREAM_CURSOR));
+
+                body.local_get(retptr);
+                body.i32_const(0);
+                body.i32_store8(self.mem_arg(0, 0));
+                body.local_get(retptr);
+                body.local_get(stream);
+                body.i32_store(self.mem_arg(2, 4));
+                Ok(vec![descriptor, retptr])
+            },
+        )?;
+
+        // (func (param i32 i32))
+        stub_embedded(
+            module,
+            TYPES,
+            "[method]directory-entry-stream.read-directory-entry",
+            &[I32, I32],
+            |body| {
+                let (stream, retptr) = (LocalID(0), LocalID(1));
+                let node = body.add_local(I32);
+                let cursor = body.add_local(I32);
+                let child = body.add_local(I32);
+                let len = body.add_local(I32);
+                let ptr = body.add_local(I32);
+
+                // result<option<directory-entry>, error-code>
+                body.local_get(retptr);
+                body.i32_const(0);
+                body.i32_store8(self.mem_arg(0, 0));
+
+                body.local_get(stream);
+                body.i32_load(self.mem_arg(2, STREAM_CURSOR));
+                body.local_tee(cursor);
+                body.local_get(stream);
+                body.i32_load(self.mem_arg(2, STREAM_NODE));
+                body.local_tee(node);
+                body.i32_load(self.mem_arg(2, NODE_LEN));
+                body.i32_gte_unsigned();
+                body.if_stmt(BlockType::Empty);
+                body.local_get(retptr);
+                body.i32_const(0);
+                body.i32_store8(self.mem_arg(0, 4));
+                body.return_stmt();
+                body.end();
+
+                body.local_get(stream);
+                body.local_get(cursor);
+                body.i32_const(1);
+                body.i32_add();
+                body.i32_store(self.mem_arg(2, STREAM_CURSOR));
+                body.i32_const(self.base);
+                body.local_get(node);
+                body.i32_load(self.mem_arg(2, NODE_START));
+                body.local_get(cursor);
+                body.i32_add();
+                body.i32_const(NODE_SIZE);
+                body.i32_mul();
+                body.i32_add();
+                body.local_tee(child);
+                body.i32_load(self.mem_arg(2, NODE_NAME_LEN));
+                body.local_set(len);
+
+                // the name is owned by the caller
+                self.alloc(body, 1, len);
+                body.local_tee(ptr);
+                body.local_get(child);
+                body.i32_load(self.mem_arg(2, NODE_NAME));
+                body.local_get(len);
+                body.memory_copy(*self.memory, *self.memory);
+
+                body.local_get(retptr);
+                body.i32_const(1);
+                body.i32_store8(self.mem_arg(0, 4));
+                body.local_get(retptr);
+                body.local_get(child);
+                body.i32_load(self.mem_arg(2, NODE_TYPE));
+                body.i32_store8(self.mem_arg(0, 8));
+                body.local_get(retptr);
+                body.local_get(ptr);
+                body.i32_store(self.mem_arg(2, 12));
+                body.local_get(retptr);
+                body.local_get(len);
+                body.i32_store(self.mem_arg(2, 16));
+                Ok(vec![stream, retptr])
+            },
+        )?;
+
+        // (func (param i32))
+        stub_embedded(module, TYPES, "[resource-drop]descriptor", &[I32], |_| {
+            Ok(vec![LocalID(0)])
+        })?;
+        stub_embedded(
+            module,
+            TYPES,
+            "[resource-drop]directory-entry-stream",
+            &[I32],
+            |body| {
+                let stream = LocalID(0);
+                body.local_get(stream);
+                body.global_get(self.free_streams);
+                body.i32_store(self.mem_arg(2, STREAM_NODE));
+                body.local_get(stream);
+                body.global_set(self.free_streams);
+                Ok(vec![stream])
+            },
+        )?;
+
+        for name in WRITE_METHODS {
+            stub_read_only(module, name)?;
+        }
+        Ok(())
+    }
+
+    /// Stubs `wasi:filesystem/preopens#get-directories` to return the root
+    /// of the tree as the only preopened directory
+    fn stub_preopens(&self, module: &mut StubModule) -> Result<()> {
+        // (func (param i32))
+        stub_embedded(
+            module,
+            PREOPENS,
+            "get-directories",
+            &[DataType::I32],
+            |body| {
+                let retptr = LocalID(0);
+                let list = body.add_local(DataType::I32);
+                let name = body.add_local(DataType::I32);
+                let len = body.add_local(DataType::I32);
+                let (preopen, preopen_len) = self.preopen;
+
+                body.i32_const(preopen_len);
+                body.local_set(len);
+                self.alloc(body, 1, len);
+                body.local_tee(name);
+                body.i32_const(preopen);
+                body.local_get(len);
+                body.memory_copy(*self.memory, *self.memory);
+
+                // list<tuple<own<descriptor>, string>> with the root descriptor
+                body.i32_const(12);
+                body.local_set(list);
+                self.alloc(body, 4, list);
+                body.local_tee(list);
+                body.i32_const(1);
+                body.i32_store(self.mem_arg(2, 0));
+                body.local_get(list);
+                body.local_get(name);
+                body.i32_store(self.mem_arg(2, 4));
+                body.local_get(list);
+                body.local_get(len);
+                body.i32_store(self.mem_arg(2, 8));
+
+                body.local_get(retptr);
+                body.local_get(list);
+                body.i32_store(self.mem_arg(2, 0));
+                body.local_get(retptr);
+                body.i32_const(1);
+                body.i32_store(self.mem_arg(2, 4));
+                Ok(vec![retptr])
+            },
+        )
+    }
+}
+
+/// Stubs a function of the WASI 0.2 versions of an interface, after checking
+/// that its core signature matches the stub
+fn stub_embedded<StubFn>(
+    module: &mut StubModule,
+    import: &str,
+    name: &str,
+    params: &[DataType],
+    stub: StubFn,
+) -> Result<()>
+where
+    StubFn: Fn(&mut FunctionBuilder) -> Result<Vec<LocalID>>,
+{
+    for full_import in wasip2_import_versions(module, import)? {
+        if let Some((actual, results)) = import_type(module, &full_import, name) {
+            if actual != params || !results.is_empty() {
+                bail!("'{full_import}#{name}' has an unexpected core signature");
+            }
+        }
+    }
+    stub_wasi_imports(module, import, name, stub)?;
+    Ok(())
+}
+
+/// Stubs a method of `descriptor` which modifies the filesystem to return a
+/// `read-only` error
+fn stub_read_only(module: &mut StubModule, name: &str) -> Result<()> {
+    let mut present = false;
+    for full_import in wasip2_import_versions(module, TYPES)? {
+        if !is_imported(module, &full_import, name) {
+            continue;
+        }
+        present = true;
+        let stub = error_stub(
+            module,
+            module.signatures.as_ref(),
+            &full_import,
+            name,
+            Some("read-only"),
+        )?;
+        stub_import(module, &full_import, name, StubBehavior::ReturnError, stub)?;
+    }
+    if !present {
+        module.record(TYPES, name, ImportStatus::NotPresent);
+    }
+    Ok(())
+}
+
+fn wasip2_import_versions(module: &StubModule, import: &str) -> Result<Vec<String>> {
+    Ok(wasi_import_versions(module, import)?
+        .into_iter()
+        .filter(|full_import| {
+            parse_versioned_import(full_import).is_some_and(|(_, version)| {
+                version.is_ok_and(|version| is_wasip2_version(&version))
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_encoder::{Instruction, ValType};
+    use wasmi::Val;
+    use wirm::Module;
+    use wit_parser::{Resolve, TypeDefKind};
+
+    use super::*;
+    use crate::test_utils::{instantiate, resolved_world_module, TestModule};
+    use crate::wit::exports::local::spidermonkey_embedding_splicer::splicer::EmbeddedFile;
+
+    const PATH: i32 = 64;
+    const RETPTR: i32 = 128;
+
+    /// An engine importing the emulated filesystem functions, with the files
+    /// embedded and the stubs exported by their import names
+    struct Embedded {
+        store: wasmi::Store<()>,
+        instance: wasmi::Instance,
+        error_codes: Vec<String>,
+    }
+
+    impl Embedded {
+        fn new(files: &[(&str, &str)]) -> Self {
+            let mut resolve = Resolve::default();
+            resolve
+                .push_path(concat!(env!("CARGO_MANIFEST_DIR"), "/../../test/wit"))
+                .unwrap();
+            let pkg = resolve
+                .push_str(
+                    "engine.wit",
+                    "package local:engine;
+                    world engine {
+                      import wasi:filesystem/types@0.2.3;
+                      import wasi:filesystem/preopens@0.2.3;
+                    }",
+                )
+                .unwrap();
+            let world = resolve.select_world(pkg, None).unwrap();
+            let error_codes = resolve
+                .types
+                .iter()
+                .find_map(|(_, ty)| match &ty.kind {
+                    TypeDefKind::Enum(enum_) if ty.name.as_deref() == Some("error-code") => Some(
+                        enum_
+                            .cases
+                            .iter()
+                            .map(|case| case.name.clone())
+                            .collect::<Vec<_>>(),
+                    ),
+                    _ => None,
+                })
+                .unwrap();
+
+            // the imports of the world emulated from the tree
+            let dummy = resolved_world_module(&resolve, world);
+            let dummy = Module::parse(&dummy, false).unwrap();
+            let mut engine = TestModule::default();
+            let mut names = Vec::new();
+            for import in dummy.imports.iter() {
+                let name = import.name.to_string();
+                if !import.module.starts_with("wasi:filesystem/") {
+                    continue;
+                }
+                if name != "get-directories"
+                    && !name.starts_with("[resource-drop]")
+                    && !WRITE_METHODS.contains(&name.as_str())
+                    && ![
+                        "[method]descriptor.open-at",
+                        "[method]descriptor.read",
+                        "[method]descriptor.stat",
+                        "[method]descriptor.stat-at",
+                        "[method]descriptor.get-type",
+                        "[method]descriptor.read-directory",
+                        "[method]directory-entry-stream.read-directory-entry",
+                    ]
+                    .contains(&name.as_str())
+                {
+                    continue;
+                }
+                let (params, results) = import_type(&dummy, &import.module, &name).unwrap();
+                let val_type = |ty: &DataType| match ty {
+                    DataType::I64 => ValType::I64,
+                    _ => ValType::I32,
+                };
+                engine.import(
+                    &import.module,
+                    &name,
+                    &params.iter().map(val_type).collect::<Vec<_>>(),
+                    &results.iter().map(val_type).collect::<Vec<_>>(),
+                );
+                names.push(name);
+            }
+            engine.memory(1);
+            let heap = engine.global(1024);
+            engine.export_function(
+                "cabi_realloc",
+                &[ValType::I32; 4],
+                &[ValType::I32],
+                &[
+                    Instruction::GlobalGet(heap),
+                    Instruction::GlobalGet(heap),
+                    Instruction::LocalGet(3),
+                    Instruction::I32Add,
+                    Instruction::GlobalSet(heap),
+                ],
+            );
+            let bindings = wit_component::metadata::encode(
+                &resolve,
+                world,
+                wit_component::StringEncoding::UTF8,
+                None,
+            )
+            .unwrap();
+            engine.custom_section("component-type:engine", &bindings);
+            let wasm = engine.finish();
+
+            let mut module = StubModule::new(&wasm, StubPolicy::default(), StubReason::Engine);
+            let filesystem = EmbeddedFilesystem {
+                preopen: "/data".to_string(),
+                files: files
+                    .iter()
+                    .map(|(path, contents)| EmbeddedFile {
+                        path: path.to_string(),
+                        contents: contents.as_bytes().to_vec(),
+                    })
+                    .collect(),
+            };
+            embed(&mut module, &filesystem).unwrap();
+            for (fid, name) in names.into_iter().enumerate() {
+                module.exports.add_export_func(name, fid as u32);
+            }
+            let (store, instance) = instantiate(&module.module.encode());
+            Embedded {
+                store,
+                instance,
+                error_codes,
+            }
+        }
+
+        fn call(&mut self, name: &str, args: &[Val]) {
+            self.instance
+                .get_func(&self.store, name)
+                .unwrap()
+                .call(&mut self.store, args, &mut [])
+                .unwrap();
+        }
+
+        fn memory(&self) -> &[u8] {
+            self.instance
+                .get_memory(&self.store, "memory")
+                .unwrap()
+                .data(&self.store)
+        }
+
+        fn u32_at(&self, addr: i32) -> u32 {
+            let addr = addr as usize;
+            u32::from_le_bytes(self.memory()[addr..addr + 4].try_into().unwrap())
+        }
+
+        fn bytes_at(&self, addr: u32, len: u32) -> String {
+            String::from_utf8(self.memory()[addr as usize..(addr + len) as usize].to_vec()).unwrap()
+        }
+
+        fn error_code(&self, code: &str) -> u8 {
+            self.error_codes
+                .iter()
+                .position(|case| case == code)
+                .unwrap() as u8
+        }
+
+        /// Returns the descriptor and path of the preopened directory
+        fn preopen(&mut self) -> (i32, String) {
+            self.call("get-directories", &[Val::I32(RETPTR)]);
+            assert_eq!(self.u32_at(RETPTR + 4), 1);
+            let list = self.u32_at(RETPTR) as i32;
+            let path = self.bytes_at(self.u32_at(list + 4), self.u32_at(list + 8));
+            (self.u32_at(list) as i32, path)
+        }
+
+        /// Opens a path relative to a directory, returning the result
+        /// discriminant and the descriptor or error code
+        fn open_at(&mut self, descriptor: i32, path: &str, open_flags: i32) -> (u8, u32) {
+            let memory = self.instance.get_memory(&self.store, "memory").unwrap();
+            memory
+                .write(&mut self.store, PATH as usize, path.as_bytes())
+                .unwrap();
+            self.call(
+                "[method]descriptor.open-at",
+                &[
+                    Val::I32(descriptor),
+                    Val::I32(0),
+                    Val::I32(PATH),
+                    Val::I32(path.len() as i32),
+                    Val::I32(open_flags),
+                    Val::I32(0),
+                    Val::I32(RETPTR),
+                ],
+            );
+            match self.memory()[RETPTR as usize] {
+                0 => (0, self.u32_at(RETPTR + 4)),
+                status => (status, self.memory()[RETPTR as usize + 4] as u32),
+            }
+        }
+
+        /// Reads a file, returning its contents and whether the end of the
+        /// file was reached
+        fn read(&mut self, descriptor: i32, length: i64, offset: i64) -> (String, bool) {
+            self.call(
+                "[method]descriptor.read",
+                &[
+                    Val::I32(descriptor),
+                    Val::I64(length),
+                    Val::I64(offset),
+                    Val::I32(RETPTR),
+                ],
+            );
+            assert_eq!(self.memory()[RETPTR as usize], 0);
+            let contents = self.bytes_at(self.u32_at(RETPTR + 4), self.u32_at(RETPTR + 8));
+            (contents, self.memory()[RETPTR as usize + 12] == 1)
+        }
+
+        fn read_directory(&mut self, descriptor: i32) -> i32 {
+            self.call(
+                "[method]descriptor.read-directory",
+                &[Val::I32(descriptor), Val::I32(RETPTR)],
+            );
+            assert_eq!(self.memory()[RETPTR as usize], 0);
+            self.u32_at(RETPTR + 4) as i32
+        }
+
+        /// Reads the name of the next entry of a stream
+        fn read_directory_entry(&mut self, stream: i32) -> Option<String> {
+            self.call(
+                "[method]directory-entry-stream.read-directory-entry",
+                &[Val::I32(stream), Val::I32(RETPTR)],
+            );
+            assert_eq!(self.memory()[RETPTR as usize], 0);
+            (self.memory()[RETPTR as usize + 4] == 1)
+                .then(|| self.bytes_at(self.u32_at(RETPTR + 12), self.u32_at(RETPTR + 16)))
+        }
+    }
+
+    const FILES: [(&str, &str); 3] = [
+        ("config.json", "{\"debug\":true}"),
+        ("templates/page.html", "<p>page</p>"),
+        ("templates/nested/empty.txt", ""),
+    ];
+
+    #[test]
+    fn opens_and_reads_files() {
+        let mut embedded = Embedded::new(&FILES);
+        let (root, path) = embedded.preopen();
+        assert_eq!(path, "/data");
+
+        let (status, config) = embedded.open_at(root, "config.json", 0);
+        assert_eq!(status, 0);
+        assert_eq!(
+            embedded.read(config as i32, 1024, 0),
+            ("{\"debug\":true}".to_string(), true)
+        );
+        assert_eq!(
+            embedded.read(config as i32, 5, 1),
+            ("\"debu".to_string(), false)
+        );
+        assert_eq!(embedded.read(config as i32, 5, 100), (String::new(), true));
+
+        // paths are resolved through directories, "." and ".."
+        let (status, templates) = embedded.open_at(root, "templates", OPEN_DIRECTORY);
+        assert_eq!(status, 0);
+        let (status, page) = embedded.open_at(templates as i32, "./nested/../page.html", 0);
+        assert_eq!(status, 0);
+        assert_eq!(
+            embedded.read(page as i32, 1024, 0),
+            ("<p>page</p>".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn lists_directories_per_stream() {
+        let mut embedded = Embedded::new(&FILES);
+        let (root, _) = embedded.preopen();
+        let (_, templates) = embedded.open_at(root, "templates", OPEN_DIRECTORY);
+
+        // streams of the same directory are read independently
+        let first = embedded.read_directory(templates as i32);
+        let second = embedded.read_directory(templates as i32);
+        assert_ne!(first, second);
+        assert_eq!(
+            embedded.read_directory_entry(first),
+            Some("nested".to_string())
+        );
+        assert_eq!(
+            embedded.read_directory_entry(second),
+            Some("nested".to_string())
+        );
+        assert_eq!(
+            embedded.read_directory_entry(first),
+            Some("page.html".to_string())
+        );
+        assert_eq!(embedded.read_directory_entry(first), None);
+        assert_eq!(
+            embedded.read_directory_entry(second),
+            Some("page.html".to_string())
+        );
+
+        // dropped streams are reused from the start of the directory
+        embedded.call("[resource-drop]directory-entry-stream", &[Val::I32(first)]);
+        let third = embedded.read_directory(root);
+        assert_eq!(third, first);
+        assert_eq!(
+            embedded.read_directory_entry(third),
+            Some("config.json".to_string())
+        );
+        assert_eq!(embedded.read_directory_entry(second), None);
+    }
+
+    #[test]
+    fn returns_error_codes() {
+        let mut embedded = Embedded::new(&FILES);
+        let (root, _) = embedded.preopen();
+        for (path, open_flags, code) in [
+            ("missing.txt", 0, "no-entry"),
+            ("config.json/page.html", 0, "not-directory"),
+            ("config.json", OPEN_DIRECTORY, "not-directory"),
+            ("/config.json", 0, "not-permitted"),
+            ("../config.json", 0, "not-permitted"),
+            ("config.json", OPEN_CREATE, "read-only"),
+        ] {
+            assert_eq!(
+                embedded.open_at(root, path, open_flags),
+                (1, embedded.error_code(code) as u32),
+                "{path}"
+            );
+        }
+
+        // directories cannot be read as files
+        embedded.call(
+            "[method]descriptor.read",
+            &[Val::I32(root), Val::I64(1), Val::I64(0), Val::I32(RETPTR)],
+        );
+        assert_eq!(embedded.memory()[RETPTR as usize], 1);
+        assert_eq!(
+            embedded.memory()[RETPTR as usize + 4],
+            embedded.error_code("is-directory")
+        );
+
+        // writes return read-only errors
+        embedded.call(
+            "[method]descriptor.create-directory-at",
+            &[
+                Val::I32(root),
+                Val::I32(PATH),
+                Val::I32(1),
+                Val::I32(RETPTR),
+            ],
+        );
+        assert_eq!(embedded.memory()[RETPTR as usize], 1);
+        assert_eq!(
+            embedded.memory()[RETPTR as usize + 1],
+            embedded.error_code("read-only")
+        );
+    }
+}
@@ -0,0 +1,18 @@
+//! Helpers shared by the unit tests
+
+mod wasm;
+
+pub(crate) use wasm::*;
+
+/// Instantiates a module without imports
+pub(crate) fn instantiate(wasm: &[u8]) -> (wasmi::Store<()>, wasmi::Instance) {
+    let engine = wasmi::Engine::default();
+    let module = wasmi::Module::new(&engine, wasm).unwrap();
+    let mut store = wasmi::Store::new(&engine, ());
+    let instance = wasmi::Linker::new(&engine)
+        .instantiate(&mut store, &module)
+        .unwrap()
+        .start(&mut store)
+        .unwrap();
+    (store, instance)
+}
@@ -0,0 +1,339 @@
+//! Core modules built for the tests, which only depend on external crates so
+//! that the integration tests can include them as well
+
+use wasm_encoder::{
+    CodeSection, ConstExpr, CustomSection, ElementSection, Elements, EntityType, ExportKind,
+    ExportSection, Function, FunctionSection, GlobalSection, GlobalType, ImportSection,
+    Instruction, MemorySection, MemoryType, RefType, TableSection, TableType, TypeSection, ValType,
+};
+use wit_parser::{Resolve, WorldId};
+
+/// A core module built up from imported and local functions. Imports must be
+/// added before the local functions, so that function indices are stable.
+#[derive(Default)]
+pub(crate) struct TestModule {
+    types: Vec<(Vec<ValType>, Vec<ValType>)>,
+    imports: Vec<(String, String, u32)>,
+    functions: Vec<(u32, Vec<ValType>, Vec<Instruction<'static>>)>,
+    memory: Option<u64>,
+    table: Option<u64>,
+    globals: Vec<i32>,
+    elements: Vec<(i32, Vec<u32>)>,
+    exports: Vec<(String, ExportKind, u32)>,
+    custom_sections: Vec<(String, Vec<u8>)>,
+}
+
+impl TestModule {
+    fn ty(&mut self, params: &[ValType], results: &[ValType]) -> u32 {
+        let ty = (params.to_vec(), results.to_vec());
+        match self.types.iter().position(|existing| *existing == ty) {
+            Some(index) => index as u32,
+            None => {
+                self.types.push(ty);
+                self.types.len() as u32 - 1
+            }
+        }
+    }
+
+    /// Imports a function, returning its function index
+    pub(crate) fn import(
+        &mut self,
+        module: &str,
+        name: &str,
+        params: &[ValType],
+        results: &[ValType],
+    ) -> u32 {
+        assert!(
+            self.functions.is_empty(),
+            "imports must be added before local functions"
+        );
+        let ty = self.ty(params, results);
+        self.imports
+            .push((module.to_string(), name.to_string(), ty));
+        self.imports.len() as u32 - 1
+    }
+
+    /// Defines a function with the given locals and body, which is ended
+    /// automatically, returning its function index
+    pub(crate) fn function(
+        &mut self,
+        params: &[ValType],
+        results: &[ValType],
+        locals: &[ValType],
+        body: &[Instruction<'static>],
+    ) -> u32 {
+        let ty = self.ty(params, results);
+        self.functions.push((ty, locals.to_vec(), body.to_vec()));
+        (self.imports.len() + self.functions.len()) as u32 - 1
+    }
+
+    /// Defines a function without locals and exports it
+    pub(crate) fn export_function(
+        &mut self,
+        name: &str,
+        params: &[ValType],
+        results: &[ValType],
+        body: &[Instruction<'static>],
+    ) -> u32 {
+        let index = self.function(params, results, &[], body);
+        self.export(name, ExportKind::Func, index);
+        index
+    }
+
+    pub(crate) fn export(&mut self, name: &str, kind: ExportKind, index: u32) -> &mut Self {
+        self.exports.push((name.to_string(), kind, index));
+        self
+    }
+
+    /// Adds a memory of the given number of pages, exported as `memory`
+    pub(crate) fn memory(&mut self, pages: u64) -> &mut Self {
+        self.memory = Some(pages);
+        self.export("memory", ExportKind::Memory, 0)
+    }
+
+    /// Adds a function table of the given initial size
+    pub(crate) fn table(&mut self, minimum: u64) -> &mut Self {
+        self.table = Some(minimum);
+        self
+    }
+
+    /// Adds a mutable i32 global, returning its global index
+    pub(crate) fn global(&mut self, value: i32) -> u32 {
+        self.globals.push(value);
+        self.globals.len() as u32 - 1
+    }
+
+    /// Places the functions in the table from the offset, with an active
+    /// element segment
+    pub(crate) fn element(&mut self, offset: i32, functions: &[u32]) -> &mut Self {
+        self.elements.push((offset, functions.to_vec()));
+        self
+    }
+
+    pub(crate) fn custom_section(&mut self, name: &str, data: &[u8]) -> &mut Self {
+        self.custom_sections.push((name.to_string(), data.to_vec()));
+        self
+    }
+
+    pub(crate) fn finish(&self) -> Vec<u8> {
+        let mut types = TypeSection::new();
+        for (params, results) in &self.types {
+            types
+                .ty()
+                .function(params.iter().copied(), results.iter().copied());
+        }
+        let mut imports = ImportSection::new();
+        for (module, name, ty) in &self.imports {
+            imports.import(module, name, EntityType::Function(*ty));
+        }
+        let mut functions = FunctionSection::new();
+        let mut code = CodeSection::new();
+        for (ty, locals, body) in &self.functions {
+            functions.function(*ty);
+            let mut function = Function::new_with_locals_types(locals.iter().copied());
+            for instruction in body {
+                function.instruction(instruction);
+            }
+            function.instruction(&Instruction::End);
+            code.function(&function);
+        }
+        let mut tables = TableSection::new();
+        if let Some(minimum) = self.table {
+            tables.table(TableType {
+                element_type: RefType::FUNCREF,
+                table64: false,
+                minimum,
+                maximum: None,
+                shared: false,
+            });
+        }
+        let mut memories = MemorySection::new();
+        if let Some(minimum) = self.memory {
+            memories.memory(MemoryType {
+                minimum,
+                maximum: None,
+                memory64: false,
+                shared: false,
+                page_size_log2: None,
+            });
+        }
+        let mut globals = GlobalSection::new();
+        for value in &self.globals {
+            globals.global(
+                GlobalType {
+                    val_type: ValType::I32,
+                    mutable: true,
+                    shared: false,
+                },
+                &ConstExpr::i32_const(*value),
+            );
+        }
+        let mut exports = ExportSection::new();
+        for (name, kind, index) in &self.exports {
+            exports.export(name, *kind, *index);
+        }
+        let mut elements = ElementSection::new();
+        for (offset, functions) in &self.elements {
+            elements.active(
+                None,
+                &ConstExpr::i32_const(*offset),
+                Elements::Functions(functions.as_slice().into()),
+            );
+        }
+
+        let mut module = wasm_encoder::Module::new();
+        module.section(&types);
+        if !self.imports.is_empty() {
+            module.section(&imports);
+        }
+        module.section(&functions);
+        if self.table.is_some() {
+            module.section(&tables);
+        }
+        if self.memory.is_some() {
+            module.section(&memories);
+        }
+        if !self.globals.is_empty() {
+            module.section(&globals);
+        }
+        module.section(&exports);
+        if !self.elements.is_empty() {
+            module.section(&elements);
+        }
+        module.section(&code);
+        for (name, data) in &self.custom_sections {
+            module.section(&CustomSection {
+                name: name.into(),
+                data: data.into(),
+            });
+        }
+        module.finish()
+    }
+}
+
+/// The table index of `coreabi_sample_i32` in [`test_engine`]
+pub(crate) const TEST_ENGINE_SAMPLE_INDEX: i32 = 1;
+
+/// The size of the function table of [`test_engine`]
+pub(crate) const TEST_ENGINE_TABLE_SIZE: u64 = 2;
+
+/// An engine providing the exports used when splicing with trivial bodies, for
+/// an engine world importing nothing and exporting nothing.
+///
+/// `coreabi_get_import` returns its index added to the table base, and
+/// `cabi_realloc` is a bump allocator. The `componentize.wizer` initializer
+/// and `check_init` do nothing and succeed.
+pub(crate) fn test_engine() -> Vec<u8> {
+    use ValType::{I32, I64};
+
+    let mut module = TestModule::default();
+    module.memory(1).table(TEST_ENGINE_TABLE_SIZE);
+    let heap = module.global(1024);
+    let post_call = module.export_function("post_call", &[I32], &[], &[]);
+    let mut samples = Vec::new();
+    for name in [
+        "coreabi_sample_i32",
+        "coreabi_sample_i64",
+        "coreabi_sample_f32",
+        "coreabi_sample_f64",
+    ] {
+        samples.push(module.export_function(name, &[I32; 3], &[I32], &[Instruction::I32Const(1)]));
+    }
+    module.export_function(
+        "coreabi_sample_i32_table_index",
+        &[],
+        &[I32],
+        &[Instruction::I32Const(TEST_ENGINE_SAMPLE_INDEX)],
+    );
+    module.export_function(
+        "coreabi_get_import",
+        &[I32; 3],
+        &[I32],
+        &[Instruction::I32Const(TEST_ENGINE_SAMPLE_INDEX)],
+    );
+    module.export_function(
+        "coreabi_to_bigint64",
+        &[I32, I64],
+        &[I32],
+        &[Instruction::I32Const(0)],
+    );
+    module.export_function(
+        "coreabi_from_bigint64",
+        &[I32],
+        &[I64],
+        &[Instruction::I64Const(0)],
+    );
+    module.export_function(
+        "cabi_realloc",
+        &[I32; 4],
+        &[I32],
+        &[
+            Instruction::GlobalGet(heap),
+            Instruction::GlobalGet(heap),
+            Instruction::LocalGet(3),
+            Instruction::I32Add,
+            Instruction::GlobalSet(heap),
+        ],
+    );
+    module.export_function("call", &[I32, I32], &[I32], &[Instruction::I32Const(0)]);
+    module.export_function("componentize.wizer", &[], &[], &[]);
+    module.export_function("check_init", &[], &[I32], &[Instruction::I32Const(0)]);
+    module.element(0, &[post_call, samples[0]]);
+
+    let mut resolve = Resolve::default();
+    let pkg = resolve
+        .push_str("engine.wit", "package local:engine; world engine {}")
+        .unwrap();
+    let world = resolve.select_world(pkg, None).unwrap();
+    let bindings =
+        wit_component::metadata::encode(&resolve, world, wit_component::StringEncoding::UTF8, None)
+            .unwrap();
+    module.custom_section("component-type:bindings", &bindings);
+    module.finish()
+}
+
+/// A module importing the given functions, and exporting its memory and a
+/// `run` function calling each of them so that none are unreachable
+pub(crate) fn importing_module(imports: &[(&str, &str, &[ValType], &[ValType])]) -> Vec<u8> {
+    let mut module = TestModule::default();
+    let mut calls = Vec::new();
+    for (import, name, params, results) in imports {
+        let index = module.import(import, name, params, results);
+        for param in params.iter() {
+            calls.push(match param {
+                ValType::I64 => Instruction::I64Const(0),
+                _ => Instruction::I32Const(0),
+            });
+        }
+        calls.push(Instruction::Call(index));
+        calls.extend(results.iter().map(|_| Instruction::Drop));
+    }
+    module.memory(1).export_function("run", &[], &[], &calls);
+    module.finish()
+}
+
+/// A module of the world with the component type of the world, as built by
+/// `wit_component::dummy_module` with the legacy name mangling
+pub(crate) fn world_module(wit: &str, world: Option<&str>) -> Vec<u8> {
+    let mut resolve = Resolve::default();
+    let pkg = resolve.push_str("world.wit", wit).unwrap();
+    let world = resolve.select_world(pkg, world).unwrap();
+    resolved_world_module(&resolve, world)
+}
+
+/// [`world_module`] for a world that has already been resolved
+pub(crate) fn resolved_world_module(resolve: &Resolve, world: WorldId) -> Vec<u8> {
+    let mut wasm = wit_component::dummy_module(
+        resolve,
+        world,
+        wit_parser::ManglingAndAbi::Legacy(wit_parser::LiftLowerAbi::Sync),
+    );
+    wit_component::embed_component_metadata(
+        &mut wasm,
+        resolve,
+        world,
+        wit_component::StringEncoding::UTF8,
+    )
+    .unwrap();
+    wasm
+}
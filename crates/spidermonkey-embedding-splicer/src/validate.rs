@@ -0,0 +1,218 @@
+use wirm::ir::module::Module;
+use wirm::DataType;
+
+use crate::splice::{engine_function_table, find_table_base, get_export_fid, required_export};
+use crate::wit::exports::local::spidermonkey_embedding_splicer::splicer::{
+    EngineCheck, SpliceError,
+};
+
+/// The function exports of the engine used when splicing, with their
+/// expected core signatures
+const ENGINE_EXPORTS: &[(&str, &[DataType], &[DataType])] = &[
+    (
+        "coreabi_sample_i32",
+        &[DataType::I32, DataType::I32, DataType::I32],
+        &[DataType::I32],
+    ),
+    (
+        "coreabi_sample_i64",
+        &[DataType::I32, DataType::I32, DataType::I32],
+        &[DataType::I32],
+    ),
+    (
+        "coreabi_sample_f32",
+        &[DataType::I32, DataType::I32, DataType::I32],
+        &[DataType::I32],
+    ),
+    (
+        "coreabi_sample_f64",
+        &[DataType::I32, DataType::I32, DataType::I32],
+        &[DataType::I32],
+    ),
+    ("coreabi_sample_i32_table_index", &[], &[DataType::I32]),
+    (
+        "coreabi_get_import",
+        &[DataType::I32, DataType::I32, DataType::I32],
+        &[DataType::I32],
+    ),
+    (
+        "coreabi_to_bigint64",
+        &[DataType::I32, DataType::I64],
+        &[DataType::I32],
+    ),
+    ("coreabi_from_bigint64", &[DataType::I32], &[DataType::I64]),
+    (
+        "cabi_realloc",
+        &[DataType::I32, DataType::I32, DataType::I32, DataType::I32],
+        &[DataType::I32],
+    ),
+    ("call", &[DataType::I32, DataType::I32], &[DataType::I32]),
+    ("post_call", &[DataType::I32], &[]),
+];
+
+/// Checks an engine build against everything that splicing depends on,
+/// reporting on each check rather than stopping at the first failure
+pub fn validate_engine(engine: Vec<u8>) -> Result<Vec<EngineCheck>, SpliceError> {
+    let module =
+        Module::parse(&engine, false).map_err(|e| SpliceError::InvalidEngine(format!("{e:#}")))?;
+
+    let mut checks = ENGINE_EXPORTS
+        .iter()
+        .map(|(name, params, results)| check_export(&module, name, params, results))
+        .collect::<Vec<_>>();
+
+    let has_component_type = module
+        .custom_sections
+        .iter()
+        .any(|section| section.name == "component-type:bindings");
+    checks.push(check(
+        "component-type:bindings",
+        has_component_type,
+        if has_component_type {
+            "found component type section".to_string()
+        } else {
+            "missing component type section describing the engine world".to_string()
+        },
+    ));
+
+    let table = engine_function_table(&module).map(|tid| (tid, module.tables.get(tid).unwrap()));
+    checks.push(check(
+        "table",
+        table.is_ok(),
+        match &table {
+            Ok((tid, table)) => format!(
+                "found function table {} of initial size {}",
+                **tid, table.initial
+            ),
+            Err(SpliceError::MissingEngineSection(section)) if section == "element" => {
+                "coreabi_sample_i32 is not placed in a function table by an active element segment"
+                    .to_string()
+            }
+            Err(_) => "missing function table for the import functions".to_string(),
+        },
+    ));
+
+    checks.push(check_table_base(&module));
+
+    Ok(checks)
+}
+
+fn check(name: &str, ok: bool, message: String) -> EngineCheck {
+    EngineCheck {
+        name: name.to_string(),
+        ok,
+        message,
+    }
+}
+
+fn check_export(
+    module: &Module,
+    name: &str,
+    params: &[DataType],
+    results: &[DataType],
+) -> EngineCheck {
+    let fid = match required_export(module, name).and_then(|id| get_export_fid(module, &id)) {
+        Ok(fid) => fid,
+        Err(SpliceError::InvalidEngine(message)) => return check(name, false, message),
+        Err(_) => return check(name, false, "missing export".to_string()),
+    };
+    if !module.functions.is_local(fid) {
+        return check(
+            name,
+            false,
+            "export is not a locally defined function".to_string(),
+        );
+    }
+    let ty = module.types.get(module.functions.get_type_id(fid));
+    let (found_params, found_results) = match ty {
+        Some(ty) => (ty.params(), ty.results()),
+        None => return check(name, false, "function type not found".to_string()),
+    };
+    if found_params != params || found_results != results {
+        return check(
+            name,
+            false,
+            format!(
+                "expected signature {params:?} -> {results:?}, found {found_params:?} -> {found_results:?}"
+            ),
+        );
+    }
+    check(name, true, format!("{params:?} -> {results:?}"))
+}
+
+/// The table index of `coreabi_sample_i32`, as returned by
+/// `coreabi_sample_i32_table_index`, must be referenced by a single `I32Const`
+/// in `coreabi_get_import`, which is patched when splicing
+fn check_table_base(module: &Module) -> EngineCheck {
+    const NAME: &str = "coreabi_get_import table base";
+    let Ok(fid) =
+        required_export(module, "coreabi_get_import").and_then(|id| get_export_fid(module, &id))
+    else {
+        return check(NAME, false, "missing coreabi_get_import".to_string());
+    };
+    if !module.functions.is_local(fid) {
+        return check(NAME, false, "missing coreabi_get_import body".to_string());
+    }
+    match find_table_base(module, fid) {
+        Ok((_, value)) => check(
+            NAME,
+            true,
+            format!("found coreabi_sample_i32 table index {value}"),
+        ),
+        Err(SpliceError::InvalidEngine(message)) => check(NAME, false, message),
+        Err(_) => check(
+            NAME,
+            false,
+            "missing coreabi_sample_i32_table_index".to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_encoder::{Instruction, ValType};
+
+    use super::*;
+    use crate::test_utils::{test_engine, TestModule, TEST_ENGINE_SAMPLE_INDEX};
+
+    #[test]
+    fn accepts_an_engine_providing_the_splice_abi() {
+        let checks = validate_engine(test_engine()).unwrap();
+        assert_eq!(checks.len(), ENGINE_EXPORTS.len() + 3);
+        for check in &checks {
+            assert!(check.ok, "{}: {}", check.name, check.message);
+        }
+        let table_base = checks.last().unwrap();
+        assert_eq!(
+            table_base.message,
+            format!("found coreabi_sample_i32 table index {TEST_ENGINE_SAMPLE_INDEX}")
+        );
+    }
+
+    #[test]
+    fn reports_every_failed_check() {
+        let mut module = TestModule::default();
+        module.export_function("call", &[ValType::I32], &[], &[Instruction::Nop]);
+        let checks = validate_engine(module.finish()).unwrap();
+
+        let failed = checks
+            .iter()
+            .filter(|check| !check.ok)
+            .map(|check| (check.name.as_str(), check.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(failed.len(), checks.len());
+        assert!(failed.contains(&("coreabi_sample_i32", "missing export")));
+        assert!(failed.contains(&(
+            "call",
+            "expected signature [I32, I32] -> [I32], found [I32] -> []"
+        )));
+        assert!(failed.contains(&(
+            "component-type:bindings",
+            "missing component type section describing the engine world"
+        )));
+        assert!(failed.contains(&(
+            "coreabi_get_import table base",
+            "missing coreabi_get_import"
+        )));
+    }
+}
@@ -5,7 +5,9 @@ wit_bindgen::generate!({
     pub_export_macro: true
 });
 
-use crate::wit::exports::local::spidermonkey_embedding_splicer::splicer::Feature;
+use crate::wit::exports::local::spidermonkey_embedding_splicer::splicer::{
+    CoreTy, Feature, ImportStatus, RandomSeed, StringEncoding, StubBehavior, StubReason,
+};
 
 impl std::str::FromStr for Feature {
     type Err = anyhow::Error;
@@ -21,3 +23,96 @@ impl std::str::FromStr for Feature {
         }
     }
 }
+
+impl std::str::FromStr for RandomSeed {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "content-hash" => Ok(RandomSeed::ContentHash),
+            _ => match s.parse() {
+                Ok(seed) => Ok(RandomSeed::Fixed(seed)),
+                Err(_) => bail!("unrecognized random seed [{s}]"),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Feature::Stdio => "stdio",
+            Feature::Clocks => "clocks",
+            Feature::Random => "random",
+            Feature::Http => "http",
+            Feature::FetchEvent => "fetch-event",
+        })
+    }
+}
+
+impl std::fmt::Display for StubReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StubReason::Feature(feature) => write!(f, "feature {feature}"),
+            StubReason::WorldImport(interface) => write!(f, "world import {interface}"),
+            StubReason::Policy => f.write_str("stub policy"),
+            StubReason::Engine => f.write_str("engine"),
+        }
+    }
+}
+
+impl std::fmt::Display for StubBehavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StubBehavior::Unreachable => "unreachable",
+            StubBehavior::ReturnError => "return-error",
+            StubBehavior::Emulated => "emulated",
+            StubBehavior::Custom => "custom",
+        })
+    }
+}
+
+impl std::fmt::Display for ImportStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportStatus::Stubbed(behavior) => write!(f, "stubbed ({behavior})"),
+            ImportStatus::Kept => f.write_str("kept"),
+            ImportStatus::Removed => f.write_str("removed"),
+            ImportStatus::NotPresent => f.write_str("not present"),
+        }
+    }
+}
+
+impl std::fmt::Display for CoreTy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CoreTy::I32 => "i32",
+            CoreTy::I64 => "i64",
+            CoreTy::F32 => "f32",
+            CoreTy::F64 => "f64",
+        })
+    }
+}
+
+impl std::str::FromStr for StringEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "utf8" => Ok(StringEncoding::Utf8),
+            "utf16" => Ok(StringEncoding::Utf16),
+            "compact-utf16" => Ok(StringEncoding::CompactUtf16),
+            _ => bail!("unrecognized string encoding [{s}]"),
+        }
+    }
+}
+
+impl From<StringEncoding> for wit_component::StringEncoding {
+    fn from(encoding: StringEncoding) -> Self {
+        match encoding {
+            StringEncoding::Utf8 => wit_component::StringEncoding::UTF8,
+            StringEncoding::Utf16 => wit_component::StringEncoding::UTF16,
+            StringEncoding::CompactUtf16 => wit_component::StringEncoding::CompactUTF16,
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Runs the subcommands of the splicer binary on the test engine
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+#[allow(dead_code)]
+#[path = "../src/test_utils/wasm.rs"]
+mod wasm;
+
+fn splicer(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_splicer"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn write_engine(dir: &Path, engine: &[u8]) -> String {
+    let path = dir.join("engine.wasm");
+    fs::write(&path, engine).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+#[test]
+fn validate_engine_reports_the_checks() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = write_engine(dir.path(), &wasm::test_engine());
+    let output = splicer(&["validate-engine", "--input", &engine]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("[ok] cabi_realloc: [I32, I32, I32, I32] -> [I32]"));
+    assert!(stdout.contains(&format!(
+        "{engine} provides the engine ABI required for splicing"
+    )));
+
+    let mut module = wasm::TestModule::default();
+    module.memory(1);
+    let engine = write_engine(dir.path(), &module.finish());
+    let output = splicer(&["validate-engine", "--input", &engine]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("[FAILED] call: missing export"));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains(&format!(
+        "{engine} does not provide the engine ABI required for splicing (14 checks failed)"
+    )));
+}
+
+#[test]
+fn componentize_builds_a_component() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = write_engine(dir.path(), &wasm::test_engine());
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+    fs::write(
+        path("world.wit"),
+        "package local:hello; world hello { export hello: func() -> u32; }",
+    )
+    .unwrap();
+    fs::write(path("source.js"), "export function hello () { return 42; }").unwrap();
+    fs::write(
+        path("adapter.wasm"),
+        wasm::world_module("package wasi:adapter; world adapter {}", None),
+    )
+    .unwrap();
+
+    let output = splicer(&[
+        "componentize",
+        "--input",
+        &engine,
+        "--js-source",
+        &path("source.js"),
+        "--wit-path",
+        &path("world.wit"),
+        "--adapter",
+        &path("adapter.wasm"),
+        "--output",
+        &path("component.wasm"),
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+
+    let component = fs::read(path("component.wasm")).unwrap();
+    wasmparser::Validator::new()
+        .validate_all(&component)
+        .unwrap();
+    let wit_component::DecodedWasm::Component(resolve, world) =
+        wit_component::decode(&component).unwrap()
+    else {
+        panic!("expected a component");
+    };
+    let exports = resolve.worlds[world]
+        .exports
+        .keys()
+        .map(|key| resolve.name_world_key(key))
+        .collect::<Vec<_>>();
+    assert_eq!(exports, ["hello"]);
+    // wizer removes the initializer export once it has run
+    let init_func = b"componentize.wizer";
+    assert!(!component
+        .windows(init_func.len())
+        .any(|name| name == init_func));
+}
+
+#[test]
+fn splice_bindings_writes_a_manifest() {
+    let dir = tempfile::tempdir().unwrap();
+    let engine = write_engine(dir.path(), &wasm::test_engine());
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+    fs::write(
+        path("world.wit"),
+        "package local:hello;
+        world hello {
+          import get: func(key: u32) -> u32;
+          export hello: func(name: string) -> u32;
+        }",
+    )
+    .unwrap();
+
+    let output = splicer(&[
+        "splice-bindings",
+        "--input",
+        &engine,
+        "--out-dir",
+        &path("out"),
+        "--wit-path",
+        &path("world.wit"),
+        "--features",
+        "stdio",
+    ]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(path("out/manifest.json")).unwrap()).unwrap();
+    assert_eq!(
+        manifest,
+        serde_json::json!({
+            "exports": [{
+                "name": "export_hello",
+                "params": ["i32", "i32"],
+                "ret": "i32",
+                "retptr": false,
+                "retsize": 0,
+                "paramptr": false,
+            }],
+            "imports": [{ "specifier": "$root", "name": "get", "arity": 1 }],
+            "features": ["stdio"],
+        })
+    );
+    for output in ["component.wasm", "initializer.js", "guest-types.d.ts"] {
+        assert!(dir.path().join("out").join(output).exists(), "{output}");
+    }
+}
@@ -1,7 +1,9 @@
 use spidermonkey_embedding_splicer::stub_wasi::stub_wasi;
 use spidermonkey_embedding_splicer::wit::{self, export};
-use spidermonkey_embedding_splicer::wit::exports::local::spidermonkey_embedding_splicer::splicer::{Feature, Guest, SpliceResult};
-use spidermonkey_embedding_splicer::splice;
+use spidermonkey_embedding_splicer::wit::exports::local::spidermonkey_embedding_splicer::splicer::{
+    EngineCheck, Feature, Guest, SpliceError, SpliceOptions, SpliceResult, StubOptions, StubResult,
+};
+use spidermonkey_embedding_splicer::{splice, validate};
 
 struct SpidermonkeyEmbeddingSplicerComponent;
 
@@ -12,8 +14,10 @@ impl Guest for SpidermonkeyEmbeddingSplicerComponent {
         wit_source: Option<String>,
         wit_path: Option<String>,
         world_name: Option<String>,
-    ) -> Result<Vec<u8>, String> {
-        stub_wasi(wasm, features, wit_source, wit_path, world_name).map_err(|e| e.to_string())
+        options: StubOptions,
+    ) -> Result<StubResult, String> {
+        stub_wasi(wasm, features, wit_source, wit_path, world_name, options)
+            .map_err(|e| e.to_string())
     }
 
     fn splice_bindings(
@@ -23,8 +27,15 @@ impl Guest for SpidermonkeyEmbeddingSplicerComponent {
         wit_path: Option<String>,
         world_name: Option<String>,
         debug: bool,
-    ) -> Result<SpliceResult, String> {
-        splice::splice_bindings(engine, features, wit_source, wit_path, world_name, debug)
+        options: SpliceOptions,
+    ) -> Result<SpliceResult, SpliceError> {
+        splice::splice_bindings(
+            engine, features, wit_source, wit_path, world_name, debug, options,
+        )
+    }
+
+    fn validate_engine(engine: Vec<u8>) -> Result<Vec<EngineCheck>, SpliceError> {
+        validate::validate_engine(engine)
     }
 }
 
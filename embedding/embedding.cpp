@@ -119,12 +119,21 @@ CoreAbiSampleF64(JSContext *cx, unsigned argc, JS::Value *vp) {
   return true;
 }
 
-// Allocation functions for the splicer
+// The table index of CoreAbiSampleI32, read by the splicer to find the single
+// constant referencing it in coreabi_get_import
+__attribute__((export_name("coreabi_sample_i32_table_index"))) int32_t
+coreabi_sample_i32_table_index() {
+  return static_cast<int32_t>(reinterpret_cast<uintptr_t>(&CoreAbiSampleI32));
+}
+
+// The splicer rewrites the table index of CoreAbiSampleI32 into the table
+// index of the idx-th generated import, so this must remain optnone.
 __attribute__((optnone, export_name("coreabi_get_import"))) JSFunction *
 coreabi_get_import(int32_t idx, int32_t argcnt, const char *name) {
   return JS_NewFunction(Runtime.cx, CoreAbiSampleI32, argcnt, 0, name);
 }
 
+// Allocation functions for the splicer
 __attribute__((export_name("cabi_realloc_adapter"))) void *
 cabi_realloc_adapter(void *ptr, size_t orig_size, size_t org_align,
                      size_t new_size) {
@@ -469,6 +478,17 @@ static bool ReallocFn(JSContext *cx, unsigned argc, JS::Value *vp) {
   return true;
 }
 
+// Frees an allocation of the bindings before the end of the current call,
+// for buffers which are only needed until their contents have been lifted
+static bool FreeFn(JSContext *cx, unsigned argc, JS::Value *vp) {
+  JS::CallArgs args = JS::CallArgsFromVp(argc, vp);
+  void *ptr = (void *)args[0].toInt32();
+  Runtime.free_list_remove(ptr);
+  cabi_free(ptr);
+  args.rval().setUndefined();
+  return true;
+}
+
 void cabi_free(void *ptr) {
   LOG("(cabi_free) %d", (uint32_t)ptr);
   JS_free(Runtime.cx, ptr);
@@ -555,6 +575,9 @@ bool install(api::Engine *engine) {
     return false;
   }
   JS::RootedObject function_obj(Runtime.cx, JS_GetFunctionObject(realloc_fn));
+  if (!JS_DefineFunction(Runtime.cx, function_obj, "free", FreeFn, 1, 0)) {
+    return false;
+  }
   JS_SetElement(Runtime.cx, import_bindings, 1, function_obj);
 
   LOG("(wizer) create the %d import JS functions", import_cnt);
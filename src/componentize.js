@@ -6,7 +6,7 @@ import { cwd, stdout, platform } from 'node:process';
 import { spawnSync } from 'node:child_process';
 import { tmpdir } from 'node:os';
 import { resolve, join, dirname, relative } from 'node:path';
-import { readFile, writeFile, mkdir, rm, stat } from 'node:fs/promises';
+import { readFile, readdir, writeFile, mkdir, rm, stat } from 'node:fs/promises';
 import { rmSync, existsSync } from 'node:fs';
 import { createHash } from 'node:crypto';
 
@@ -96,6 +96,13 @@ export async function componentize(
     worldName,
     disableFeatures = [],
     enableFeatures = [],
+    stringEncoding = 'utf8',
+    randomSeed,
+    clockEpoch = process.env.SOURCE_DATE_EPOCH,
+    stdioCapture,
+    stubPolicy,
+    embedDir,
+    embedPreopen = '/',
 
     debug = { ...DEFAULT_DEBUG_SETTINGS },
     debugBuild = false,
@@ -124,9 +131,11 @@ export async function componentize(
   if (!jsSource && sourcePath) {
     jsSource = await readFile(sourcePath, 'utf8');
   }
-  const detectedExports = await detectKnownSourceExportNames(
-    sourceName,
-    jsSource,
+  const sourceModule = await parseSourceModule(sourceName, jsSource);
+  const detectedExports = detectKnownSourceExportNames(sourceModule);
+  const usedImports = await detectUsedImports(
+    sourceModule,
+    useOriginalSourceFile ? sourcePath : undefined,
   );
 
   // If there is an export of incomingHandler, there is likely to be a
@@ -142,14 +151,35 @@ export async function componentize(
   }
 
   // Splice the bindigns for the given WIT world into the engine WASM
-  let { wasm, jsBindings, exports, imports } = splicer.spliceBindings(
-    await readFile(engine),
-    [...features],
-    witWorld,
-    maybeWindowsPath(witPath),
-    worldName,
-    false,
-  );
+  let splicedBindings;
+  try {
+    splicedBindings = splicer.spliceBindings(
+      await readFile(engine),
+      [...features],
+      witWorld,
+      maybeWindowsPath(witPath),
+      worldName,
+      false,
+      {
+        stringEncoding,
+        // star re-exports have no static name, so the exports cannot be checked
+        exportNames: detectedExports.has(null)
+          ? undefined
+          : [...detectedExports],
+        usedImports,
+        embeddedFilesystem: embedDir && {
+          preopen: embedPreopen,
+          files: await readEmbeddedFiles(embedDir),
+        },
+      },
+    );
+  } catch (err) {
+    throw new Error(
+      spliceErrorMessage(err.payload ?? err, engine, sourceName),
+      { cause: err },
+    );
+  }
+  let { wasm, jsBindings, exports, imports } = splicedBindings;
 
   const inputWasmPath = join(workDir, 'in.wasm');
   const outputWasmPath = join(workDir, 'out.wasm');
@@ -261,11 +291,23 @@ export async function componentize(
       sourcePath = relative(workspacePrefix, sourcePath);
   }
 
+  // The embedded directory is preopened for the JS source to read while
+  // initializing, in which case the workspace is only reachable at its own
+  // path, as the embedded files may be preopened at `/`
+  if (embedDir) {
+    sourcePath = `${workspacePrefix}/${sourcePath}`;
+  }
+
   let args = `--initializer-script-path ${initializerPath} --strip-path-prefix ${workspacePrefix}/ ${sourcePath}`;
   runtimeArgs = runtimeArgs ? `${runtimeArgs} ${args}` : args;
 
   let preopens = [`--dir=${sourcesDir}`];
-  preopens.push(`--mapdir=/::${workspacePrefix}`);
+  if (embedDir) {
+    preopens.push(`--dir=${workspacePrefix}`);
+    preopens.push(`--mapdir=${embedPreopen}::${maybeWindowsPath(embedDir)}`);
+  } else {
+    preopens.push(`--mapdir=/::${workspacePrefix}`);
+  }
 
   let postProcess;
 
@@ -326,12 +368,26 @@ export async function componentize(
   );
 
   // After wizening, stub out the wasi imports depending on what features are enabled
-  const finalBin = splicer.stubWasi(
+  const { wasm: finalBin, report: stubReport } = splicer.stubWasi(
     bin,
     [...features],
     witWorld,
     maybeWindowsPath(witPath),
     worldName,
+    {
+      randomSeed:
+        randomSeed === undefined
+          ? undefined
+          : randomSeed === 'content-hash'
+            ? { tag: 'content-hash' }
+            : { tag: 'fixed', val: BigInt(randomSeed) },
+      clockEpoch: clockEpoch === undefined ? undefined : BigInt(clockEpoch),
+      stdioCapture,
+      stubPolicy:
+        typeof stubPolicy === 'string'
+          ? await readFile(stubPolicy, 'utf8')
+          : stubPolicy && JSON.stringify(stubPolicy),
+    },
   );
 
   if (debugBindings) {
@@ -371,6 +427,7 @@ export async function componentize(
   return {
     component,
     imports,
+    stubReport,
     debug: debugOutput,
   };
 }
@@ -418,6 +475,37 @@ function parseWizerStderr(stderr) {
   return `${output.substring(0, causeStart)}${output.substring(causeEnd)}`.trim();
 }
 
+/**
+ * Describe a splice-error returned when splicing the bindings into the engine
+ *
+ * @param {{ tag: string, val: any } | Error} err
+ * @param {string} engine - path to the engine that was spliced
+ * @param {string} sourceName - name of the guest module
+ * @returns {string} String that can be printed to describe the error
+ */
+function spliceErrorMessage(err, engine, sourceName) {
+  switch (err?.tag) {
+    case 'invalid-wit':
+      return `Invalid WIT world: ${err.val}`;
+    case 'invalid-engine':
+      return `Invalid engine build at ${engine}: ${err.val}`;
+    case 'missing-engine-export':
+      return `Engine build at ${engine} is missing the required export "${err.val}"`;
+    case 'missing-engine-section':
+      return `Engine build at ${engine} is missing the required "${err.val}" section`;
+    case 'bindgen':
+      return `Unable to generate bindings: ${err.val}`;
+    case 'unsupported-export':
+      return `Unable to bind the export "${err.val.name}": ${err.val.message}`;
+    case 'missing-exports':
+      return err.val.map((msg) => `Error: "${sourceName}" ${msg}`).join('\n');
+    case 'invalid-embedded-filesystem':
+      return `Unable to embed the filesystem: ${err.val}`;
+    default:
+      return `${err?.message ?? err}`;
+  }
+}
+
 /**
  * Check whether a value is numeric (including BigInt)
  *
@@ -464,6 +552,29 @@ async function prepWorkDir() {
   return { baseDir, sourcesDir };
 }
 
+/**
+ * Read the files of a directory to embed as a read-only filesystem
+ *
+ * @param {string} dir - directory to embed
+ * @param {string} prefix - path of the directory relative to the embedded root
+ */
+async function readEmbeddedFiles(dir, prefix = '') {
+  const files = [];
+  const entries = await readdir(dir, { withFileTypes: true });
+  entries.sort((a, b) => (a.name < b.name ? -1 : a.name > b.name ? 1 : 0));
+  for (const entry of entries) {
+    const path = `${prefix}${entry.name}`;
+    if (entry.isDirectory()) {
+      files.push(
+        ...(await readEmbeddedFiles(join(dir, entry.name), `${path}/`)),
+      );
+    } else {
+      files.push({ path, contents: await readFile(join(dir, entry.name)) });
+    }
+  }
+  return files;
+}
+
 /**
  * Initialize a WebAssembly binary, given the
  *
@@ -553,13 +664,13 @@ async function handleCheckInitOutput(
 }
 
 /**
- * Detect known exports that correspond to certain interfaces
+ * Parse the module record of the JS source
  *
  * @param {string} filename - filename
  * @param {string} code - JS source code
- * @returns {Promise<string[]>} A Promise that resolves to a list of string that represent unversioned interfaces
+ * @returns {Promise<object>} A Promise that resolves to the static imports and exports of the module
  */
-async function detectKnownSourceExportNames(filename, code) {
+async function parseSourceModule(filename, code) {
   if (!filename) {
     throw new Error('missing filename');
   }
@@ -567,8 +678,6 @@ async function detectKnownSourceExportNames(filename, code) {
     throw new Error('missing JS code');
   }
 
-  const names = new Set();
-
   const results = await oxc.parseAsync(filename, code);
   if (results.errors.length > 0) {
     throw new Error(
@@ -576,7 +685,19 @@ async function detectKnownSourceExportNames(filename, code) {
     );
   }
 
-  for (const staticExport of results.module.staticExports) {
+  return results.module;
+}
+
+/**
+ * Detect known exports that correspond to certain interfaces
+ *
+ * @param {object} module - module record of the JS source
+ * @returns {Set<string>} A set of strings that represent unversioned interfaces
+ */
+function detectKnownSourceExportNames(module) {
+  const names = new Set();
+
+  for (const staticExport of module.staticExports) {
     for (const entry of staticExport.entries) {
       names.add(entry.exportName.name);
     }
@@ -584,3 +705,79 @@ async function detectKnownSourceExportNames(filename, code) {
 
   return names;
 }
+
+/**
+ * Detect the names imported from each specifier by the module and by the
+ * modules it imports through relative specifiers, so that unused imports of
+ * the world are not bound. Namespace imports and star re-exports import `*`.
+ *
+ * @param {object} module - module record of the JS source
+ * @param {string | undefined} modulePath - path of the JS source, if read from a file
+ * @returns {Promise<[string, string][] | undefined>} The `[specifier, name]` pairs, or undefined when they cannot be determined statically
+ */
+async function detectUsedImports(module, modulePath) {
+  const isPath = (specifier) =>
+    specifier.startsWith('./') ||
+    specifier.startsWith('../') ||
+    specifier.startsWith('/');
+
+  const used = [];
+  const visited = new Set([modulePath && resolve(modulePath)]);
+  const pending = [[module, modulePath]];
+  while (pending.length > 0) {
+    const [module, modulePath] = pending.pop();
+    if (module.dynamicImports.length > 0) {
+      return undefined;
+    }
+
+    const specifiers = [];
+    for (const staticImport of module.staticImports) {
+      const specifier = staticImport.moduleRequest.value;
+      specifiers.push(specifier);
+      for (const { importName } of staticImport.entries) {
+        const name =
+          importName.kind === 'NamespaceObject'
+            ? '*'
+            : importName.kind === 'Default'
+              ? 'default'
+              : importName.name;
+        used.push([specifier, name]);
+      }
+    }
+    for (const staticExport of module.staticExports) {
+      for (const { moduleRequest, importName } of staticExport.entries) {
+        if (!moduleRequest) {
+          continue;
+        }
+        specifiers.push(moduleRequest.value);
+        const name = importName.kind === 'Name' ? importName.name : '*';
+        used.push([moduleRequest.value, name]);
+      }
+    }
+
+    for (const specifier of specifiers) {
+      if (!isPath(specifier)) {
+        continue;
+      }
+      // absolute paths are resolved against the mapped directory at runtime,
+      // and inline sources have no directory to resolve relative paths against
+      if (specifier.startsWith('/') || !modulePath) {
+        return undefined;
+      }
+      const importPath = resolve(dirname(modulePath), specifier);
+      if (visited.has(importPath)) {
+        continue;
+      }
+      visited.add(importPath);
+      let code;
+      try {
+        code = await readFile(importPath, 'utf8');
+      } catch {
+        return undefined;
+      }
+      pending.push([await parseSourceModule(importPath, code), importPath]);
+    }
+  }
+
+  return used.filter(([specifier]) => !isPath(specifier));
+}
@@ -78,6 +78,8 @@ suite('Bindings', async () => {
           worldName,
           enableFeatures,
           disableFeatures: maybeLogging(disableFeatures),
+          stringEncoding: testcase.stringEncoding,
+          stubPolicy: testcase.stubPolicy,
           debugBuild: DEBUG_TEST_ENABLED,
         });
 
@@ -0,0 +1,4 @@
+export async function compute (x) {
+  await Promise.resolve();
+  return x * 2;
+}
@@ -0,0 +1,5 @@
+import { strictEqual } from 'node:assert';
+
+export async function test (instance) {
+  strictEqual(await instance.compute(21), 42);
+}
@@ -0,0 +1,4 @@
+export default async function double (x) {
+  await new Promise(resolve => setTimeout(resolve, 10));
+  return x * 2;
+}
@@ -0,0 +1,8 @@
+import double from 'double';
+
+export async function compute (x) {
+  const result = double(x);
+  if (!(result instanceof Promise))
+    throw new Error('async imports should return a promise');
+  return (await result) + 1;
+}
@@ -0,0 +1,3 @@
+export default async function fail (message) {
+  throw new Error(message);
+}
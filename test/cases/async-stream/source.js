@@ -0,0 +1,21 @@
+import fail from 'fail';
+
+export async function echo (chunks) {
+  const received = [];
+  for await (const chunk of chunks) {
+    if (!(chunk instanceof Uint8Array))
+      throw new Error('byte streams should be read as Uint8Array chunks');
+    received.push(chunk);
+  }
+  return received;
+}
+
+export async function relay (message) {
+  try {
+    await fail(message);
+  } catch (e) {
+    if (!(e.payload instanceof Error))
+      throw new Error('error contexts should be lifted as errors');
+    throw e.payload;
+  }
+}
@@ -0,0 +1,16 @@
+import { strictEqual, ok } from 'node:assert';
+
+export async function test (instance) {
+  const encoder = new TextEncoder();
+  const echoed = [];
+  for await (const chunk of await instance.echo([encoder.encode('hello '), encoder.encode('world')]))
+    echoed.push(...chunk);
+  strictEqual(new TextDecoder().decode(new Uint8Array(echoed)), 'hello world');
+
+  try {
+    await instance.relay('disk full');
+    ok(false);
+  } catch (e) {
+    ok(String(e.payload?.message ?? e.payload).includes('disk full'));
+  }
+}
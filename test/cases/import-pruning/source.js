@@ -0,0 +1,7 @@
+import { used, Counter } from 'local:import-pruning/used';
+
+export function run () {
+  const counter = new Counter();
+  counter.inc();
+  return used() + counter.inc();
+}
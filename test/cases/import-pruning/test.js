@@ -0,0 +1,7 @@
+import { ok, strictEqual } from 'node:assert';
+
+export function test (instance, { componentImports }) {
+  strictEqual(instance.run(), 3);
+  // the imports not used by the guest are not bound, so the component does not import them
+  ok(!componentImports.some((specifier) => specifier.includes('unused')));
+}
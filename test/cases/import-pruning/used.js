@@ -0,0 +1,10 @@
+export function used () {
+  return 1;
+}
+
+export class Counter {
+  #count = 0;
+  inc () {
+    return ++this.#count;
+  }
+}
@@ -0,0 +1,13 @@
+import { a, b, c } from 'local:strings-utf16/strings';
+
+export const strings = {
+  a (x) {
+    return a(x);
+  },
+  b () {
+    return b();
+  },
+  c (a, b) {
+    return c(a, b);
+  }
+};
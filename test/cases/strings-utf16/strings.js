@@ -0,0 +1,13 @@
+let z;
+
+export function a (x) {
+  z = x;
+}
+
+export function b () {
+  return z;
+}
+
+export function c (a, b) {
+  return a + b;
+}
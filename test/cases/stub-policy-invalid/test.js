@@ -0,0 +1,9 @@
+import { match } from 'node:assert';
+
+export const stubPolicy = {
+  'wasi:cli/exit': 'ignore',
+};
+
+export function err (e) {
+  match(e.message, /invalid stub policy entry for 'wasi:cli\/exit'/);
+}
@@ -0,0 +1,3 @@
+export function run () {
+  return 'stubbed';
+}
@@ -0,0 +1,10 @@
+import { strictEqual } from 'node:assert';
+
+export const stubPolicy = {
+  'wasi:cli/exit': 'unreachable',
+  'wasi:random/random#get-random-u64': { action: 'custom', results: [4] },
+};
+
+export function test (instance) {
+  strictEqual(instance.run(), 'stubbed');
+}
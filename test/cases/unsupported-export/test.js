@@ -0,0 +1,5 @@
+import { match } from 'node:assert';
+
+export function err (e) {
+  match(e.message, /Unable to bind the export "xY": the name is used by more than one function, resource or interface export/);
+}
@@ -41,6 +41,68 @@ interface ComponentizeOptions {
    * (no experimental subsystems currently supported)
    */
   enableFeatures?: [];
+  /**
+   * String encoding of the canonical ABI for the functions of the target world
+   * (defaults to 'utf8')
+   *
+   * Interfaces that are also imported by the engine always remain UTF-8.
+   */
+  stringEncoding?: 'utf8' | 'utf16' | 'compact-utf16';
+  /**
+   * Seed of the deterministic pseudorandom generator used when the random feature
+   * is disabled, or 'content-hash' to derive it from the built engine (defaults to 0)
+   */
+  randomSeed?: number | bigint | 'content-hash';
+  /**
+   * Epoch in seconds of the virtual clock used when the clocks feature is disabled
+   * (defaults to SOURCE_DATE_EPOCH, or the build time)
+   *
+   * The virtual clock advances by a millisecond on every read.
+   */
+  clockEpoch?: number | bigint;
+  /**
+   * Capture stdio into a ring buffer of this many bytes when the stdio feature is
+   * disabled, keeping the most recent output
+   *
+   * The captured output is read and cleared through a `drain-logs: func() -> list<u8>`
+   * export, which is added to the component. Only stdout and stderr writes through
+   * `fd_write` are captured, as the WASI 0.2 stdio streams remain unsupported.
+   */
+  stdioCapture?: number;
+  /**
+   * Stubbing policy for the WASI imports, as a path to a JSON policy file or a policy
+   * object, mapping interfaces (`wasi:cli/exit`) or functions (`wasi:cli/exit#exit`)
+   * to stub actions, which take precedence over the stubs of the disabled features
+   *
+   * - allow: keep the import
+   * - unreachable: trap when called
+   * - return-error: return the `err` case of the result, with an optional error `code`
+   * - custom: return the given core `results`
+   */
+  stubPolicy?: string | Record<
+    string,
+    | 'allow'
+    | 'unreachable'
+    | 'return-error'
+    | { action: 'return-error'; code?: string }
+    | { action: 'custom'; results: number[] }
+  >;
+  /**
+   * Directory to embed as a read-only filesystem, when the target world does not
+   * import `wasi:filesystem`
+   *
+   * The files are stored in the component before initialization and can be
+   * opened, read, listed and stat'ed through the `wasi:filesystem` imports of the
+   * engine, while writes return `read-only` errors. The directory is also
+   * preopened for the JS source while initializing, so that the files it reads
+   * then are captured in the component, while the WASI preview1 file functions
+   * are not backed by the files at runtime.
+   */
+  embedDir?: string;
+  /**
+   * Path of the preopened directory containing the embedded files (default `/`)
+   */
+  embedPreopen?: string;
   /**
    * Pass environment variables to the spawned Wizer Process
    * If set to true, all host environment variables are passed
@@ -117,6 +179,11 @@ interface ComponentizeOutput {
    * Used guest imports in JavaScript (excluding those from StarlingMonkey engine)
    */
   imports: [[string, string]][];
+  /**
+   * How each WASI import of the engine was treated when stubbing: stubbed (and how), kept,
+   * or not present, with the feature, world import or stub policy entry that determined it
+   */
+  stubReport: StubReportEntry[];
   /**
    * Debugging output (only present if enabled)
    */
@@ -132,6 +199,27 @@ interface ComponentizeOutput {
   };
 }
 
+interface StubReportEntry {
+  /**
+   * Imported module, such as `wasi:cli/stdout@0.2.3`, unversioned when not present
+   */
+  module: string;
+  /**
+   * Imported function name
+   */
+  name: string;
+  reason:
+    | { tag: 'feature'; val: 'stdio' | 'random' | 'clocks' | 'http' | 'fetch-event' }
+    | { tag: 'world-import'; val: string }
+    | { tag: 'policy' }
+    | { tag: 'engine' };
+  status:
+    | { tag: 'stubbed'; val: 'unreachable' | 'return-error' | 'emulated' | 'custom' }
+    | { tag: 'kept' }
+    | { tag: 'removed' }
+    | { tag: 'not-present' };
+}
+
 /**
  * ComponentizeJS version string
  */